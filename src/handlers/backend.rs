@@ -0,0 +1,371 @@
+use crate::models::{App, CircuitBreakerState, FailureCategory};
+use crate::services::{
+    build_model_list_content, extract_client_key, format_backend_error, get_available_models,
+    mask_token, sanitized_backend_error, split_batched_payload,
+};
+use axum::{
+    body::Bytes,
+    http::{HeaderMap, HeaderName, HeaderValue, StatusCode},
+};
+use futures::{Stream, StreamExt};
+use serde_json::Value;
+use std::{pin::Pin, sync::Arc};
+use tokio::{sync::RwLock, task};
+
+// ---------- Backend dispatch shared by the API handlers ----------
+
+/// Maximum size for error response bodies to prevent DoS (10KB)
+const MAX_ERROR_BODY_SIZE: usize = 10 * 1024;
+
+/// Backend body chunks as consumed by the streaming task
+pub(super) type ByteStream = Pin<Box<dyn Stream<Item = Result<Bytes, reqwest::Error>> + Send>>;
+
+/// Reject the request while the circuit breaker is open
+pub(super) async fn ensure_circuit_closed(app: &App) -> Result<(), (StatusCode, &'static str)> {
+    let mut cb = app.circuit_breaker.write().await;
+    if !cb.should_allow_request() {
+        log::error!("🔴 Circuit breaker is open - rejecting request");
+        return Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            "backend_unavailable_circuit_open",
+        ));
+    }
+    Ok(())
+}
+
+/// Apply the operator's `SSE_HEADERS` changes on top of a streamed response's
+/// default headers
+pub(super) fn apply_sse_header_overrides(
+    headers: &mut HeaderMap,
+    overrides: &[(String, Option<String>)],
+) {
+    for (name, value) in overrides {
+        let Ok(name) = HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        match value.as_deref().map(HeaderValue::from_str) {
+            Some(Ok(value)) => {
+                headers.insert(name, value);
+            }
+            Some(Err(_)) => {}
+            None => {
+                headers.remove(name);
+            }
+        }
+    }
+}
+
+/// The client's API key, which is forwarded to the backend as-is
+pub(super) fn require_client_key(
+    headers: &HeaderMap,
+) -> Result<String, (StatusCode, &'static str)> {
+    match extract_client_key(headers) {
+        Some(key) => {
+            log::info!("🔑 Client API Key: Bearer {}", mask_token(&key));
+            Ok(key)
+        }
+        None => {
+            log::warn!("❌ No client API key provided");
+            Err((StatusCode::UNAUTHORIZED, "missing_api_key"))
+        }
+    }
+}
+
+/// JSON POST to the backend carrying the client's key
+pub(super) fn backend_request(app: &App, client_key: &str) -> reqwest::RequestBuilder {
+    app.client
+        .post(&app.backend_url)
+        .header("content-type", "application/json")
+        .bearer_auth(client_key)
+}
+
+/// Record a circuit breaker failure asynchronously
+#[inline]
+pub(super) fn record_circuit_breaker_failure(
+    cb: Arc<RwLock<CircuitBreakerState>>,
+    category: FailureCategory,
+) {
+    task::spawn(async move {
+        cb.write().await.record_failure(category);
+    });
+}
+
+/// Send the backend request, bounding the wait for response headers
+pub(super) async fn send_backend_request(
+    app: &App,
+    backend_req: reqwest::RequestBuilder,
+) -> Result<reqwest::Response, (StatusCode, &'static str)> {
+    let send_future = backend_req.send();
+    let send_result = match app.config.backend_headers_timeout {
+        Some(limit) => match tokio::time::timeout(limit, send_future).await {
+            Ok(result) => result,
+            Err(_) => {
+                log::error!(
+                    "❌ Backend did not return response headers within {}ms",
+                    limit.as_millis()
+                );
+                record_circuit_breaker_failure(
+                    app.circuit_breaker.clone(),
+                    FailureCategory::Generic,
+                );
+                return Err((StatusCode::GATEWAY_TIMEOUT, "backend_connect_timeout"));
+            }
+        },
+        None => send_future.await,
+    };
+
+    send_result.map_err(|e| {
+        log::error!("❌ Backend connection failed: {}", e);
+        record_circuit_breaker_failure(app.circuit_breaker.clone(), FailureCategory::Generic);
+        (StatusCode::BAD_GATEWAY, "backend_unavailable")
+    })
+}
+
+/// Turn a non-success backend response into a client-facing message and code
+async fn backend_error_content(
+    app: &App,
+    status: StatusCode,
+    res: reqwest::Response,
+    model: &str,
+) -> (String, String) {
+    let error_body = read_bounded_error(res).await;
+    record_circuit_breaker_failure(
+        app.circuit_breaker.clone(),
+        FailureCategory::from_error_body(&error_body),
+    );
+
+    log::error!(
+        "❌ Backend returned error: {} {} - {} ({} bytes)",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or(""),
+        &error_body[..error_body.len().min(200)], // Log first 200 chars
+        error_body.len()
+    );
+
+    // Handle 404 with model list
+    if status == StatusCode::NOT_FOUND {
+        let models = get_available_models(app).await;
+        if !models.is_empty() {
+            log::info!("💡 Model '{}' not found - sending model list", model);
+            return (
+                build_model_list_content(model, &models),
+                "model_not_found".to_string(),
+            );
+        }
+    }
+
+    if app.config.sanitize_backend_errors {
+        log::error!(
+            "❌ Backend error detail (withheld from client): {}",
+            error_body
+        );
+        return (
+            sanitized_backend_error(status.as_u16()),
+            "backend_error".to_string(),
+        );
+    }
+
+    (
+        format_backend_error(&error_body, &error_body),
+        "backend_error".to_string(),
+    )
+}
+
+/// Send `body` to the backend, retrying a 404 once with `FALLBACK_MODEL`.
+/// Returns the response together with the model that produced it.
+pub(super) async fn send_with_model_fallback(
+    app: &App,
+    client_key: &str,
+    body: &Value,
+    model: &str,
+) -> Result<(reqwest::Response, String), (StatusCode, &'static str)> {
+    let res = send_backend_request(app, backend_request(app, client_key).json(body)).await?;
+    let fallback = app
+        .config
+        .fallback_model
+        .as_deref()
+        .filter(|fallback| !fallback.eq_ignore_ascii_case(model));
+    let Some(fallback) = fallback.filter(|_| res.status() == StatusCode::NOT_FOUND) else {
+        return Ok((res, model.to_string()));
+    };
+
+    log::warn!(
+        "🔀 Model '{}' not found - retrying with fallback model '{}'",
+        model,
+        fallback
+    );
+    let mut retry_body = body.clone();
+    retry_body["model"] = Value::String(fallback.to_string());
+    let retry =
+        send_backend_request(app, backend_request(app, client_key).json(&retry_body)).await?;
+    if retry.status() == StatusCode::NOT_FOUND {
+        // Report the original model as missing, with the usual model list
+        log::warn!("⚠️ Fallback model '{}' was not found either", fallback);
+        return Ok((res, model.to_string()));
+    }
+    Ok((retry, fallback.to_string()))
+}
+
+/// Contact the backend from inside the streaming task (early `response.created`
+/// mode), mapping every failure to a message and code for `response.failed`
+pub(super) async fn resolve_deferred_backend(
+    app: &App,
+    client_key: &str,
+    body: &Value,
+    model: &str,
+) -> Result<(reqwest::Response, String), (String, String)> {
+    let (res, served_model) = send_with_model_fallback(app, client_key, body, model)
+        .await
+        .map_err(|(_, code)| {
+            (
+                format!("Backend request failed: {}", code),
+                code.to_string(),
+            )
+        })?;
+    let res = check_backend_response(app, res, &served_model).await?;
+    Ok((res, served_model))
+}
+
+/// Send `body` to every ensemble member at once and keep the first stream that
+/// produces a usable chunk; dropping the slower requests cancels them
+pub(super) async fn race_ensemble(
+    app: &App,
+    client_key: &str,
+    body: &Value,
+    models: &[String],
+) -> Result<(ByteStream, String), (String, String)> {
+    let attempts = models.iter().map(|model| {
+        let mut member_body = body.clone();
+        member_body["model"] = Value::String(model.clone());
+        Box::pin(async move {
+            let res =
+                send_backend_request(app, backend_request(app, client_key).json(&member_body))
+                    .await
+                    .map_err(|(_, code)| {
+                        (
+                            format!("Backend request failed: {}", code),
+                            code.to_string(),
+                        )
+                    })?;
+            let res = check_backend_response(app, res, model).await?;
+            match first_usable_chunk(res).await {
+                Some(stream) => Ok((stream, model.clone())),
+                None => {
+                    log::warn!("⚠️  Ensemble model '{}' produced no usable chunk", model);
+                    Err((
+                        format!("Model '{}' produced no output", model),
+                        "ensemble_no_output".to_string(),
+                    ))
+                }
+            }
+        })
+    });
+
+    let ((stream, model), slower) = futures::future::select_ok(attempts).await?;
+    log::info!(
+        "🏁 Ensemble won by '{}', cancelling {} slower request(s)",
+        model,
+        slower.len()
+    );
+    Ok((stream, model))
+}
+
+/// Read until the body contains a `data:` line with choices, then hand back the
+/// whole stream with the bytes read so far replayed in front
+async fn first_usable_chunk(res: reqwest::Response) -> Option<ByteStream> {
+    let mut body = res.bytes_stream();
+    let mut buffered = Vec::new();
+    while let Some(chunk) = body.next().await {
+        buffered.extend_from_slice(&chunk.ok()?);
+        if has_usable_data_line(&buffered) {
+            let prefix = futures::stream::once(async move { Ok(Bytes::from(buffered)) });
+            return Some(Box::pin(prefix.chain(body)));
+        }
+    }
+    None
+}
+
+fn has_usable_data_line(buffered: &[u8]) -> bool {
+    let text = String::from_utf8_lossy(buffered);
+    // Only complete lines; the last one may still be arriving
+    let complete = text.rsplit_once('\n').map_or("", |(lines, _)| lines);
+    complete
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("data:"))
+        .flat_map(|data| split_batched_payload(data.trim().to_string()))
+        .filter_map(|data| serde_json::from_str::<Value>(&data).ok())
+        .any(|chunk| {
+            chunk
+                .get("choices")
+                .and_then(Value::as_array)
+                .is_some_and(|choices| !choices.is_empty())
+        })
+}
+
+/// Accept a backend response only if it succeeded with a streamable body;
+/// anything else becomes a message and code for `response.failed`
+pub(super) async fn check_backend_response(
+    app: &App,
+    res: reqwest::Response,
+    model: &str,
+) -> Result<reqwest::Response, (String, String)> {
+    let status = res.status();
+    if !status.is_success() {
+        return Err(backend_error_content(app, status, res, model).await);
+    }
+
+    // Gateways sometimes answer 200 with an HTML page (login, captive portal)
+    let content_type = res
+        .headers()
+        .get("content-type")
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    if !content_type.is_empty()
+        && !content_type.starts_with("text/event-stream")
+        && !content_type.contains("json")
+    {
+        log::error!(
+            "❌ Backend returned {} with unexpected content-type '{}'",
+            status,
+            content_type
+        );
+        record_circuit_breaker_failure(app.circuit_breaker.clone(), FailureCategory::Generic);
+        return Err((
+            format!(
+                "Backend returned unexpected content-type '{}' instead of an event stream",
+                content_type
+            ),
+            "backend_unexpected_content_type".to_string(),
+        ));
+    }
+
+    log::info!("✅ Backend responded successfully ({})", status);
+    Ok(res)
+}
+
+/// Read error response body with size limit to prevent DoS
+async fn read_bounded_error(res: reqwest::Response) -> String {
+    let mut body = res.bytes_stream();
+    let mut bytes = Vec::with_capacity(4096);
+    let mut total = 0;
+
+    while let Some(chunk_result) = body.next().await {
+        if let Ok(chunk) = chunk_result {
+            let remaining = MAX_ERROR_BODY_SIZE.saturating_sub(total);
+            if remaining == 0 {
+                log::warn!(
+                    "⚠️  Error body exceeded {} bytes, truncating",
+                    MAX_ERROR_BODY_SIZE
+                );
+                bytes.extend_from_slice(b"... (truncated)");
+                break;
+            }
+            let to_take = chunk.len().min(remaining);
+            bytes.extend_from_slice(&chunk[..to_take]);
+            total += to_take;
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
@@ -0,0 +1,154 @@
+use super::backend::{
+    apply_sse_header_overrides, check_backend_response, ensure_circuit_closed, require_client_key,
+    send_with_model_fallback,
+};
+use crate::models::App;
+use crate::services::normalize_model_name;
+use crate::utils::{spawn_with_request_id, with_request_id};
+use axum::{
+    body::Body,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response as HttpResponse},
+    Json,
+};
+use futures::StreamExt;
+use serde_json::{json, Value};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `POST /v1/chat/completions` - forward a Chat Completions request as-is
+/// (sampling params, `seed`, `stream` ...) apart from model normalization,
+/// behind the same connection limit, circuit breaker and auth as `/v1/responses`
+pub async fn chat_completions_passthrough(
+    State(app): State<App>,
+    headers: HeaderMap,
+    body: String,
+) -> Result<HttpResponse, (StatusCode, &'static str)> {
+    let request_id = format!(
+        "{:x}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos()
+    );
+    with_request_id(request_id, forward_chat_completions(app, headers, body)).await
+}
+
+async fn forward_chat_completions(
+    app: App,
+    headers: HeaderMap,
+    body: String,
+) -> Result<HttpResponse, (StatusCode, &'static str)> {
+    let Some(connection_guard) = app.try_acquire_sse_slot() else {
+        log::warn!(
+            "❌ Rejecting request: too many open SSE connections (max {})",
+            app.config.max_sse_connections.unwrap_or_default()
+        );
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "too_many_connections"));
+    };
+
+    let mut payload: Value = serde_json::from_str(&body).map_err(|e| {
+        log::error!("❌ Failed to parse chat completions request: {}", e);
+        (StatusCode::UNPROCESSABLE_ENTITY, "invalid_request_format")
+    })?;
+    let requested_model = payload
+        .get("model")
+        .and_then(Value::as_str)
+        .map(str::to_string)
+        .ok_or((StatusCode::BAD_REQUEST, "model_required"))?;
+
+    ensure_circuit_closed(&app).await?;
+    let client_key = require_client_key(&headers)?;
+
+    let backend_model = normalize_model_name(&requested_model, &app).await;
+    payload["model"] = Value::String(backend_model.clone());
+    app.hooks.transform_backend_request(&mut payload);
+    log::info!(
+        "📨 Chat Completions passthrough: model={}, stream={}",
+        backend_model,
+        payload
+            .get("stream")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+    );
+
+    let (res, served_model) =
+        send_with_model_fallback(&app, &client_key, &payload, &backend_model).await?;
+    let status = res.status();
+    let res = match check_backend_response(&app, res, &served_model).await {
+        Ok(res) => res,
+        Err((message, code)) => {
+            let status = if status.is_success() {
+                StatusCode::BAD_GATEWAY
+            } else {
+                status
+            };
+            let error =
+                json!({"error": {"message": message, "type": "backend_error", "code": code}});
+            return Ok((status, Json(error)).into_response());
+        }
+    };
+
+    let cb_clone = app.circuit_breaker.clone();
+    spawn_with_request_id(async move {
+        cb_clone.write().await.record_success();
+    });
+
+    let mut out_headers = HeaderMap::new();
+    if let Some(content_type) = res.headers().get("content-type") {
+        out_headers.insert("content-type", content_type.clone());
+    }
+    out_headers.insert("cache-control", "no-cache".parse().unwrap());
+    out_headers.insert("x-accel-buffering", "no".parse().unwrap());
+    apply_sse_header_overrides(&mut out_headers, &app.config.sse_headers);
+    let raw_stream = res.bytes_stream().map(move |chunk| {
+        let _ = &connection_guard;
+        chunk
+    });
+    Ok((status, out_headers, Body::from_stream(raw_stream)).into_response())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{CircuitBreakerState, ProxyConfig};
+    use std::{sync::Arc, time::SystemTime};
+    use tokio::sync::RwLock;
+
+    #[tokio::test]
+    async fn test_passthrough_respects_open_circuit_breaker() {
+        let mut breaker = CircuitBreakerState::new(true);
+        breaker.is_open = true;
+        breaker.last_failure_time = Some(SystemTime::now());
+        let app = App {
+            circuit_breaker: Arc::new(RwLock::new(breaker)),
+            ..App::for_tests(
+                "http://127.0.0.1:1/v1/chat/completions",
+                ProxyConfig::default(),
+            )
+        };
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer test-key".parse().unwrap());
+        let body = json!({
+            "model": "test-model",
+            "seed": 7,
+            "messages": [{"role": "user", "content": "hi"}]
+        });
+
+        let result =
+            chat_completions_passthrough(State(app.clone()), headers, body.to_string()).await;
+        assert_eq!(
+            result.err(),
+            Some((
+                StatusCode::SERVICE_UNAVAILABLE,
+                "backend_unavailable_circuit_open"
+            ))
+        );
+        // The rejected request released its connection slot
+        assert_eq!(
+            app.active_sse_connections
+                .load(std::sync::atomic::Ordering::Acquire),
+            0
+        );
+    }
+}
@@ -0,0 +1,69 @@
+use crate::models::App;
+use axum::{extract::State, Json};
+use serde_json::{json, Value};
+
+/// `GET /` and `GET /v1` - unauthenticated summary of routes and limits
+pub async fn discovery(State(app): State<App>) -> Json<Value> {
+    let config = &app.config;
+    Json(json!({
+        "object": "proxy.discovery",
+        "name": env!("CARGO_PKG_NAME"),
+        "version": env!("CARGO_PKG_VERSION"),
+        "endpoints": [
+            {"method": "POST", "path": "/v1/responses", "description": "OpenAI Responses API (SSE)"},
+            {"method": "GET", "path": "/v1/responses/{id}", "description": "A response created with store: true"},
+            {"method": "DELETE", "path": "/v1/responses/{id}", "description": "Delete a stored response"},
+            {"method": "POST", "path": "/v1/chat/completions", "description": "Chat Completions passthrough"},
+            {"method": "GET", "path": "/v1/models", "description": "Cached backend models with pricing"},
+            {"method": "GET", "path": "/v1/models/{id}", "description": "A single model"},
+            {"method": "GET", "path": "/health", "description": "Liveness and circuit breaker state"},
+            {"method": "GET", "path": "/health/ready", "description": "Readiness for load balancers"},
+        ],
+        "limits": {
+            "max_request_body_bytes": config.request_body_limit(),
+            "prompt_budget_bytes": config.prompt_budget_bytes,
+            "max_sse_connections": config.max_sse_connections,
+            "max_output_tokens_floor": config.max_output_tokens_floor,
+            "max_output_tokens_ceiling": config.max_output_tokens_ceiling,
+        },
+        "features": {
+            "streaming": true,
+            // Responses are always delivered as SSE
+            "non_streaming": false,
+            "tools": true,
+            "reasoning": true,
+            "prompt_templates": !app.prompt_registry.is_empty(),
+            "sse_replay": app.replay_buffer.is_some(),
+            "extra_body": config.allow_extra_body,
+            "response_store": app.response_store.is_some(),
+        },
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyConfig;
+
+    #[tokio::test]
+    async fn test_discovery_lists_routes_and_body_limit() {
+        let app = App::for_tests(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig {
+                max_request_body_bytes: Some(2048),
+                ..Default::default()
+            },
+        );
+
+        let Json(body) = discovery(State(app)).await;
+        let paths: Vec<&str> = body["endpoints"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|e| e["path"].as_str())
+            .collect();
+        assert!(paths.contains(&"/v1/responses"));
+        assert_eq!(body["limits"]["max_request_body_bytes"], 2048);
+        assert_eq!(body["features"]["streaming"], true);
+    }
+}
@@ -22,3 +22,74 @@ pub async fn health_check(State(app): State<App>) -> (StatusCode, Json<Value>) {
 
     (status, Json(response))
 }
+
+/// `GET /health/ready` - readiness for load balancers. With `REQUIRE_MODEL_CACHE`
+/// the proxy only reports ready once the backend model list has loaded.
+/// Only the cached list is read; refreshing is left to the background task.
+pub async fn readiness_check(State(app): State<App>) -> (StatusCode, Json<Value>) {
+    let circuit_open = {
+        let cb = app.circuit_breaker.read().await;
+        cb.enabled && cb.is_open
+    };
+    let models_cached = app
+        .models_cache
+        .read()
+        .await
+        .as_ref()
+        .map_or(0, |models| models.len());
+    let cache_missing = app.config.require_model_cache && models_cached == 0;
+
+    let status = if circuit_open || cache_missing {
+        StatusCode::SERVICE_UNAVAILABLE
+    } else {
+        StatusCode::OK
+    };
+
+    let response = json!({
+        "status": if status == StatusCode::OK { "ready" } else { "not_ready" },
+        "models_cached": models_cached,
+        "require_model_cache": app.config.require_model_cache,
+        "circuit_breaker_open": circuit_open,
+    });
+
+    (status, Json(response))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ModelInfo, ProxyConfig};
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_readiness_waits_for_required_model_cache() {
+        // A backend that accepts connections but never answers
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let app = App::for_tests(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                require_model_cache: true,
+                ..Default::default()
+            },
+        );
+
+        let (status, Json(body)) =
+            tokio::time::timeout(Duration::from_secs(2), readiness_check(State(app.clone())))
+                .await
+                .expect("readiness does not wait on the backend");
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body["status"], "not_ready");
+        assert_eq!(body["models_cached"], 0);
+
+        *app.models_cache.write().await = Some(vec![ModelInfo {
+            id: "test-model".to_string(),
+            input_price_usd: None,
+            output_price_usd: None,
+            supported_features: vec![],
+        }]);
+        let (status, Json(body)) = readiness_check(State(app)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["models_cached"], 1);
+    }
+}
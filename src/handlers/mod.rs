@@ -1,5 +1,14 @@
+pub mod admin;
+mod backend;
+pub mod chat_completions;
+pub mod discovery;
 pub mod health;
+pub mod models;
 pub mod responses;
 
+pub use admin::*;
+pub use chat_completions::*;
+pub use discovery::*;
 pub use health::*;
+pub use models::*;
 pub use responses::*;
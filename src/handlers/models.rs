@@ -0,0 +1,89 @@
+use crate::models::{App, ModelInfo};
+use crate::services::get_available_models;
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde_json::{json, Value};
+
+/// OpenAI-style model object extended with pricing and capabilities
+fn model_json(model: &ModelInfo) -> Value {
+    json!({
+        "id": model.id,
+        "object": "model",
+        "pricing": {
+            "input_usd": model.input_price_usd,
+            "output_usd": model.output_price_usd,
+        },
+        "supported_features": model.supported_features,
+    })
+}
+
+/// `GET /v1/models` - cached backend models with pricing
+pub async fn list_models(State(app): State<App>) -> Json<Value> {
+    let models = get_available_models(&app).await;
+    Json(json!({
+        "object": "list",
+        "data": models.iter().map(model_json).collect::<Vec<_>>(),
+    }))
+}
+
+/// `GET /v1/models/{id}` - a single model; ids may contain `/`
+pub async fn get_model(
+    State(app): State<App>,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, &'static str)> {
+    let id = id.trim_start_matches('/');
+    get_available_models(&app)
+        .await
+        .iter()
+        .find(|m| m.id.eq_ignore_ascii_case(id))
+        .map(|m| Json(model_json(m)))
+        .ok_or((StatusCode::NOT_FOUND, "model_not_found"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyConfig;
+    use std::sync::Arc;
+    use tokio::sync::RwLock;
+
+    fn app_with_models(models: Vec<ModelInfo>) -> App {
+        App {
+            models_cache: Arc::new(RwLock::new(Some(models))),
+            ..App::for_tests(
+                "http://127.0.0.1:1/v1/chat/completions",
+                ProxyConfig::default(),
+            )
+        }
+    }
+
+    #[tokio::test]
+    async fn test_model_lookup_includes_pricing() {
+        let app = app_with_models(vec![ModelInfo {
+            id: "Org/Coder-7B".to_string(),
+            input_price_usd: Some(0.2),
+            output_price_usd: Some(0.8),
+            supported_features: vec!["tools".to_string()],
+        }]);
+
+        let Json(model) = get_model(State(app.clone()), Path("org/coder-7b".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(model["id"], "Org/Coder-7B");
+        assert_eq!(model["pricing"]["input_usd"], 0.2);
+        assert_eq!(model["pricing"]["output_usd"], 0.8);
+        assert_eq!(model["supported_features"], json!(["tools"]));
+
+        let Json(list) = list_models(State(app.clone())).await;
+        assert_eq!(list["data"][0]["pricing"]["output_usd"], 0.8);
+
+        let missing = get_model(State(app), Path("unknown".to_string())).await;
+        assert_eq!(
+            missing.err(),
+            Some((StatusCode::NOT_FOUND, "model_not_found"))
+        );
+    }
+}
@@ -1,36 +1,46 @@
 use axum::{
-    extract::State,
-    http::{HeaderMap, StatusCode},
-    response::sse::{Event, Sse},
+    body::Body,
+    extract::{Path, State},
+    http::{HeaderMap, StatusCode, Version},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse, Response as HttpResponse,
+    },
+    Json,
 };
-use futures::{Stream, StreamExt};
-use serde_json::Value;
+use futures::StreamExt;
+use serde_json::{json, Value};
 use std::{
     convert::Infallible,
     sync::Arc,
-    time::{SystemTime, UNIX_EPOCH},
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::{sync::RwLock, task};
+use tokio::sync::mpsc::error::TrySendError;
+use tokio::time::Instant;
 use tokio_stream::wrappers::ReceiverStream;
 
-/// Maximum size for error response bodies to prevent DoS (10KB)
-const MAX_ERROR_BODY_SIZE: usize = 10 * 1024;
-
 /// Maximum size for input content to prevent memory exhaustion (5MB)
 const MAX_INPUT_CONTENT_SIZE: usize = 5 * 1024 * 1024;
-const REALTIME_ITEM_OBJECT: &str = "realtime.item";
+use super::backend::{
+    apply_sse_header_overrides, check_backend_response, ensure_circuit_closed, race_ensemble,
+    require_client_key, resolve_deferred_backend, send_with_model_fallback, ByteStream,
+};
 use crate::models::{
-    App, ChatCompletionChunk, IncompleteDetails, OutputContent, OutputItem, Response,
-    ResponseReasoningState, ResponseRequest, StreamEvent, TokenDetails, Usage,
+    App, ApplyPatchValidation, ChatCompletionChunk, ChatCompletionRequest, IncompleteDetails,
+    OutputContent, OutputItem, ProxyConfig, ProxyDebugInfo, ProxyMetrics, ReasoningSummaryPart,
+    Response, ResponseError, ResponseReasoningState, ResponseRequest, SlowClientPolicy,
+    SseConnectionGuard, StreamEvent, StreamEventHook, TokenDetails, ToolCallDelta, Usage,
+    VisionFallback,
 };
 use crate::services::{
-    build_model_list_content, convert_to_chat_completions, extract_client_key,
-    format_backend_error, get_available_models, mask_token, model_supports_feature,
-    normalize_model_name, SseEventParser,
+    convert_to_chat_completions, expand_prompt, input_has_media, model_lacks_feature,
+    model_supports_feature, normalize_model_name, split_batched_payload, store_owner,
+    strip_media_parts, ReplayBuffer, ReplayRecorder, ResponseStore, SseEventParser,
 };
 use crate::utils::{
-    dump_backend_chunk, dump_backend_request, dump_request, dump_stream_event,
-    extract_xml_tool_calls,
+    apply_patch_problem, dump_backend_chunk, dump_backend_request, dump_request, dump_stream_event,
+    extract_xml_tool_calls, json_tool_call_start, scan_json_tool_calls, spawn_with_request_id,
+    with_request_id, JsonToolScan,
 };
 
 /// Track state of a tool call as it streams
@@ -41,25 +51,194 @@ struct ToolCallState {
     type_: String,
     name: Option<String>,
     arguments: String,
-    item_added: bool,     // Whether we've sent the output_item.added event
-    end_emitted: bool,    // Whether we've emitted output_tool_call.end/legacy done events
-    pending_args: String, // Arguments buffered before name arrives
+    item_added: bool,           // Whether we've sent the output_item.added event
+    end_emitted: bool,          // Whether we've emitted output_tool_call.end/legacy done events
+    pending_args: String,       // Arguments buffered before name arrives
+    last_args_fragment: String, // Most recent argument delta, for resend detection
+    output_index: u32,          // Position in the response output, unique per item
+}
+
+/// Argument fragments shorter than this are never treated as resends, so
+/// legitimately repeated tokens such as `}` or `"` pass through
+const MIN_DUPLICATE_ARGS_FRAGMENT_LEN: usize = 8;
+
+impl ToolCallState {
+    /// Record an argument fragment, returning `true` when it exactly repeats the
+    /// previous one and looks like a backend resend rather than real content
+    fn is_resent_args_fragment(&mut self, fragment: &str) -> bool {
+        let resent = fragment.len() >= MIN_DUPLICATE_ARGS_FRAGMENT_LEN
+            && !fragment.trim().is_empty()
+            && fragment == self.last_args_fragment;
+        self.last_args_fragment = fragment.to_string();
+        resent
+    }
+
+    /// Responses output item type for this call, derived from the backend tool type
+    fn output_item_type(&self) -> &'static str {
+        tool_call_item_type(&self.type_)
+    }
+}
+
+/// Map a Chat Completions tool-call type onto the Responses output item type
+fn tool_call_item_type(backend_type: &str) -> &'static str {
+    match backend_type {
+        "custom" => "custom_tool_call",
+        "code_interpreter" => "code_interpreter_call",
+        "web_search" | "web_search_preview" => "web_search_call",
+        "file_search" => "file_search_call",
+        _ => "function_call",
+    }
+}
+
+/// A contiguous run of reasoning deltas; visible text closes it and a later
+/// reasoning delta opens a new one
+#[derive(Debug, Clone)]
+struct ReasoningSegment {
+    item_id: String,
+    output_index: u32,
+    text: String,
+}
+
+/// Read `tool_calls` from a full (non-delta) message as delta fragments; entries
+/// without an `index` take their position in the array
+fn message_tool_calls(message: &Value) -> Option<Vec<ToolCallDelta>> {
+    let calls = message.get("tool_calls")?.as_array()?;
+    let parsed: Vec<ToolCallDelta> = calls
+        .iter()
+        .enumerate()
+        .filter_map(|(position, call)| {
+            let mut call = call.clone();
+            if let Some(obj) = call.as_object_mut() {
+                obj.entry("index").or_insert_with(|| Value::from(position));
+            }
+            serde_json::from_value(call)
+                .map_err(|e| log::warn!("⚠️ Ignoring malformed message tool call: {}", e))
+                .ok()
+        })
+        .collect();
+    (!parsed.is_empty()).then_some(parsed)
+}
+
+/// Hand out the next free `output_index`; the message item owns index 0
+fn allocate_output_index(next: &mut u32) -> u32 {
+    let index = *next;
+    *next += 1;
+    index
+}
+
+const OUTPUT_TEXT_DELTA: &str = "response.output_text.delta";
+
+/// Holds back consecutive text deltas so bursts of tiny fragments go out as
+/// one event; anything else flushes the buffer first to keep ordering intact
+struct DeltaCoalescer {
+    window: Duration,
+    max_bytes: Option<usize>,
+    pending: Option<(StreamEvent, Instant)>,
+}
+
+impl DeltaCoalescer {
+    /// Buffer a text delta, returning whatever is now ready to send
+    fn push(&mut self, event: StreamEvent) -> Vec<StreamEvent> {
+        let mut ready = Vec::new();
+        match &mut self.pending {
+            Some((pending, _))
+                if pending.item_id == event.item_id
+                    && pending.content_index == event.content_index =>
+            {
+                if let (Some(buffered), Some(delta)) = (&mut pending.delta, &event.delta) {
+                    buffered.push_str(delta);
+                }
+            }
+            _ => {
+                ready.extend(self.take());
+                self.pending = Some((event, Instant::now()));
+            }
+        }
+
+        let full = match (&self.pending, self.max_bytes) {
+            (Some((pending, _)), Some(max)) => pending.delta.as_ref().map_or(0, String::len) >= max,
+            _ => false,
+        };
+        let expired = self
+            .deadline()
+            .is_some_and(|deadline| deadline <= Instant::now());
+        if full || expired {
+            ready.extend(self.take());
+        }
+        ready
+    }
+
+    fn take(&mut self) -> Option<StreamEvent> {
+        self.pending.take().map(|(event, _)| event)
+    }
+
+    /// When the buffered delta must go out even if nothing else arrives
+    fn deadline(&self) -> Option<Instant> {
+        self.pending
+            .as_ref()
+            .map(|(_, started)| *started + self.window)
+    }
 }
 
 /// Helper to assign monotonic event and sequence identifiers
 struct EventSequencer {
     next_event_id: u64,
     next_sequence: u32,
+    replay: Option<ReplayRecorder>,
+    coalescer: Option<DeltaCoalescer>,
+    item_object: Option<String>,
+    event_hook: Option<StreamEventHook>,
+    /// Drop non-terminal events instead of waiting when the channel is full
+    drop_when_full: bool,
+    dropped_events: u64,
 }
 
 impl EventSequencer {
-    fn new() -> Self {
+    fn new(replay: Option<ReplayRecorder>) -> Self {
         Self {
             next_event_id: 0,
             next_sequence: 0,
+            replay,
+            coalescer: None,
+            item_object: None,
+            event_hook: None,
+            drop_when_full: false,
+            dropped_events: 0,
         }
     }
 
+    /// Never wait on a slow client; see `SlowClientPolicy::Drop`
+    fn with_drop_when_full(mut self) -> Self {
+        self.drop_when_full = true;
+        self
+    }
+
+    /// Pass every event through an operator hook before it is sent
+    fn with_event_hook(mut self, hook: StreamEventHook) -> Self {
+        self.event_hook = Some(hook);
+        self
+    }
+
+    /// Stamp every output item with this `object` value (e.g. `realtime.item`)
+    fn with_item_object(mut self, object: String) -> Self {
+        self.item_object = Some(object);
+        self
+    }
+
+    /// Merge text deltas arriving within `window` (or until `max_bytes`)
+    fn with_coalescing(mut self, window: Duration, max_bytes: Option<usize>) -> Self {
+        self.coalescer = Some(DeltaCoalescer {
+            window,
+            max_bytes,
+            pending: None,
+        });
+        self
+    }
+
+    fn coalesce_deadline(&self) -> Option<Instant> {
+        self.coalescer.as_ref().and_then(DeltaCoalescer::deadline)
+    }
+
     fn prepare(
         &mut self,
         mut event: StreamEvent,
@@ -72,6 +251,12 @@ impl EventSequencer {
         event.event_id = Some(event_id);
         event.response_id = Some(response_id.to_string());
         event.sequence_number = Some(self.next_sequence);
+        if let Some(object) = &self.item_object {
+            let snapshot_items = event.response.iter_mut().flat_map(|r| r.output.iter_mut());
+            for item in event.item.iter_mut().chain(snapshot_items) {
+                item.object = Some(object.clone());
+            }
+        }
 
         let sequence_number = self.next_sequence;
         let json = serde_json::to_string(&event)?;
@@ -82,17 +267,83 @@ impl EventSequencer {
 
 /// Helper to serialize, log, and dispatch stream events
 async fn dispatch_event(
-    tx: &tokio::sync::mpsc::Sender<Event>,
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
+    sequencer: &mut EventSequencer,
+    response_id: &str,
+    request_id: &str,
+    event: StreamEvent,
+) {
+    let ready = match sequencer.coalescer.as_mut() {
+        Some(coalescer) if event.type_ == OUTPUT_TEXT_DELTA => coalescer.push(event),
+        Some(coalescer) => coalescer.take().into_iter().chain([event]).collect(),
+        None => vec![event],
+    };
+    for event in ready {
+        send_event(tx, sequencer, response_id, request_id, event).await;
+    }
+}
+
+/// Send any text delta still held by the coalescer
+async fn flush_coalesced_delta(
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
+    sequencer: &mut EventSequencer,
+    response_id: &str,
+    request_id: &str,
+) {
+    if let Some(event) = sequencer.coalescer.as_mut().and_then(DeltaCoalescer::take) {
+        send_event(tx, sequencer, response_id, request_id, event).await;
+    }
+}
+
+async fn send_event(
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
     sequencer: &mut EventSequencer,
     response_id: &str,
     request_id: &str,
     event: StreamEvent,
 ) {
+    // Dropped events never take a sequence number
+    let event = match &sequencer.event_hook {
+        Some(hook) => match hook(event) {
+            Some(event) => event,
+            None => return,
+        },
+        None => event,
+    };
+    // Only deltas may be skipped, and only before they take a sequence number,
+    // so the client still sees every item's full lifecycle without gaps
+    let permit = if sequencer.drop_when_full && is_droppable_event(&event.type_) {
+        match tx.try_reserve() {
+            Ok(permit) => Some(permit),
+            Err(TrySendError::Full(())) => {
+                if sequencer.dropped_events == 0 {
+                    log::warn!("🐢 Client is not keeping up, dropping stream deltas");
+                }
+                sequencer.dropped_events += 1;
+                return;
+            }
+            Err(TrySendError::Closed(())) => return,
+        }
+    } else {
+        None
+    };
     let event_type = event.type_.clone();
     match sequencer.prepare(event, response_id) {
         Ok((json, sequence_number)) => {
             dump_stream_event(&json, request_id, sequence_number);
-            let _ = tx.send(Event::default().data(json)).await;
+            if let Some(replay) = &sequencer.replay {
+                replay.record(sequence_number, &json);
+            }
+            let encoded = EncodedEvent {
+                sequence: sequence_number,
+                json,
+            };
+            match permit {
+                Some(permit) => permit.send(encoded),
+                None => {
+                    let _ = tx.send(encoded).await;
+                }
+            }
         }
         Err(err) => {
             log::error!("❌ Failed to serialize stream event {}: {err}", event_type);
@@ -100,8 +351,15 @@ async fn dispatch_event(
     }
 }
 
+/// Incremental events a slow client can miss; the matching `*.done` events
+/// and the final response still carry the complete content
+fn is_droppable_event(event_type: &str) -> bool {
+    event_type.ends_with(".delta")
+}
+
+#[allow(clippy::too_many_arguments)]
 async fn emit_tool_call_begin_events(
-    tx: &tokio::sync::mpsc::Sender<Event>,
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
     sequencer: &mut EventSequencer,
     response_id: &str,
     request_id: &str,
@@ -109,6 +367,7 @@ async fn emit_tool_call_begin_events(
     call_id: &str,
     function_name: &str,
     output_index: u32,
+    item_type: &str,
 ) {
     let begin_event = StreamEvent {
         type_: "response.output_tool_call.begin".to_string(),
@@ -126,6 +385,10 @@ async fn emit_tool_call_begin_events(
         name: Some(function_name.to_string()),
         arguments: None,
         error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
     };
 
     dispatch_event(tx, sequencer, response_id, request_id, begin_event).await;
@@ -142,8 +405,8 @@ async fn emit_tool_call_begin_events(
         text: None,
         item: Some(OutputItem {
             id: item_id.to_string(),
-            object: REALTIME_ITEM_OBJECT.to_string(),
-            type_: "function_call".to_string(),
+            object: None,
+            type_: item_type.to_string(),
             status: "in_progress".to_string(),
             role: None,
             content: None,
@@ -151,19 +414,26 @@ async fn emit_tool_call_begin_events(
             name: Some(function_name.to_string()),
             arguments: Some(String::new()),
             output: None,
+            summary: None,
+            encrypted_content: None,
         }),
         sequence_number: None,
         call_id: Some(call_id.to_string()),
         name: None,
         arguments: None,
         error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
     };
 
     dispatch_event(tx, sequencer, response_id, request_id, legacy_event).await;
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn emit_tool_call_delta_events(
-    tx: &tokio::sync::mpsc::Sender<Event>,
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
     sequencer: &mut EventSequencer,
     response_id: &str,
     request_id: &str,
@@ -190,6 +460,10 @@ async fn emit_tool_call_delta_events(
         name: None,
         arguments: None,
         error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
     };
 
     dispatch_event(tx, sequencer, response_id, request_id, modern_event).await;
@@ -210,13 +484,18 @@ async fn emit_tool_call_delta_events(
         name: None,
         arguments: None,
         error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
     };
 
     dispatch_event(tx, sequencer, response_id, request_id, legacy_event).await;
 }
 
+#[allow(clippy::too_many_arguments)]
 async fn emit_tool_call_end_event(
-    tx: &tokio::sync::mpsc::Sender<Event>,
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
     sequencer: &mut EventSequencer,
     response_id: &str,
     request_id: &str,
@@ -242,89 +521,721 @@ async fn emit_tool_call_end_event(
         name: Some(function_name.to_string()),
         arguments: Some(arguments.to_string()),
         error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
     };
 
     dispatch_event(tx, sequencer, response_id, request_id, end_event).await;
 }
 
-/// Record a circuit breaker failure asynchronously
-#[inline]
-fn record_circuit_breaker_failure(cb: Arc<RwLock<crate::models::CircuitBreakerState>>) {
-    task::spawn(async move {
-        cb.write().await.record_failure();
-    });
+/// Announce the assistant message item and its text part, once, taking the
+/// next output index; returns the message's output index
+async fn open_message_item(
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
+    sequencer: &mut EventSequencer,
+    response_id: &str,
+    request_id: &str,
+    message_id: &str,
+    message_index: &mut Option<u32>,
+    next_output_index: &mut u32,
+) -> u32 {
+    if let Some(index) = *message_index {
+        return index;
+    }
+    let index = allocate_output_index(next_output_index);
+    *message_index = Some(index);
+
+    // Send output_item.added event
+    let item_added_event = StreamEvent {
+        type_: "response.output_item.added".to_string(),
+        response: None,
+        item_id: Some(message_id.to_string()),
+        output_index: Some(index),
+        content_index: None,
+        delta: None,
+        text: None,
+        item: Some(OutputItem {
+            id: message_id.to_string(),
+            object: None,
+            type_: "message".to_string(),
+            status: "in_progress".to_string(),
+            role: Some("assistant".to_string()),
+            content: Some(vec![]),
+            call_id: None,
+            name: None,
+            arguments: None,
+            output: None,
+            summary: None,
+            encrypted_content: None,
+        }),
+        event_id: None,
+        response_id: None,
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
+    };
+    dispatch_event(tx, sequencer, response_id, request_id, item_added_event).await;
+
+    // Send content_part.added event; the part fills in through the deltas
+    let content_added_event = StreamEvent {
+        type_: "response.content_part.added".to_string(),
+        response: None,
+        item_id: Some(message_id.to_string()),
+        output_index: Some(index),
+        content_index: Some(0),
+        delta: None,
+        text: None,
+        item: None,
+        event_id: None,
+        response_id: None,
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part: Some(OutputContent::OutputText {
+            text: String::new(),
+            annotations: vec![],
+        }),
+        summary_index: None,
+    };
+    dispatch_event(tx, sequencer, response_id, request_id, content_added_event).await;
+    index
 }
 
-fn warn_unsupported_features(req: &ResponseRequest) {
-    if let Some(include) = &req.include {
-        if !include.is_empty() {
-            log::warn!(
-                "⚠️  'include' values {:?} are not supported by this proxy and will be ignored",
-                include
-            );
-        }
+async fn emit_reasoning_added_event(
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
+    sequencer: &mut EventSequencer,
+    response_id: &str,
+    request_id: &str,
+    item_id: &str,
+    output_index: u32,
+    summary: bool,
+) {
+    let added_event = StreamEvent {
+        type_: "response.output_item.added".to_string(),
+        response: None,
+        event_id: None,
+        response_id: None,
+        item_id: Some(item_id.to_string()),
+        output_index: Some(output_index),
+        content_index: None,
+        delta: None,
+        text: None,
+        item: Some(OutputItem {
+            id: item_id.to_string(),
+            object: None,
+            type_: "reasoning".to_string(),
+            status: "in_progress".to_string(),
+            role: Some("assistant".to_string()),
+            content: Some(vec![]),
+            call_id: None,
+            name: None,
+            arguments: None,
+            output: None,
+            summary: Some(vec![]),
+            encrypted_content: None,
+        }),
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
+    };
+
+    dispatch_event(tx, sequencer, response_id, request_id, added_event).await;
+
+    if summary {
+        let part_added_event = reasoning_summary_event(
+            "response.reasoning_summary_part.added",
+            item_id,
+            output_index,
+            None,
+            Some(OutputContent::SummaryText {
+                text: String::new(),
+            }),
+        );
+        dispatch_event(tx, sequencer, response_id, request_id, part_added_event).await;
     }
+}
 
-    if let Some(stream_options) = &req.stream_options {
-        if stream_options.include_obfuscation.is_some() {
-            log::warn!("⚠️  stream_options.include_obfuscation is not supported");
-        }
+/// A `response.reasoning_summary_*` event for the single summary part the proxy
+/// derives from a reasoning item's text
+fn reasoning_summary_event(
+    type_: &str,
+    item_id: &str,
+    output_index: u32,
+    text: Option<String>,
+    part: Option<OutputContent>,
+) -> StreamEvent {
+    StreamEvent {
+        type_: type_.to_string(),
+        response: None,
+        event_id: None,
+        response_id: None,
+        item_id: Some(item_id.to_string()),
+        output_index: Some(output_index),
+        content_index: None,
+        delta: None,
+        text,
+        item: None,
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part,
+        summary_index: Some(0),
     }
+}
 
-    if req.conversation.is_some() {
-        log::warn!("⚠️  conversation references are ignored (proxy is stateless)");
+/// The part of a reasoning delta that fits under `MAX_REASONING_BYTES`; once
+/// the cap is reached `capped` is set and every later delta is dropped
+fn cap_reasoning_delta<'a>(
+    reasoning: &'a str,
+    reasoning_len: usize,
+    max_bytes: Option<usize>,
+    capped: &mut bool,
+) -> &'a str {
+    if *capped {
+        return "";
+    }
+    let Some(max_bytes) = max_bytes else {
+        return reasoning;
+    };
+    let room = max_bytes.saturating_sub(reasoning_len);
+    if reasoning.len() <= room {
+        return reasoning;
+    }
+    log::warn!(
+        "✂️  Reasoning reached {} bytes, dropping the rest of it",
+        max_bytes
+    );
+    *capped = true;
+    let mut end = room;
+    while !reasoning.is_char_boundary(end) {
+        end -= 1;
     }
+    &reasoning[..end]
+}
 
-    if req.previous_response_id.is_some() {
-        log::warn!("⚠️  previous_response_id is ignored (proxy is stateless)");
+/// Stop sequences from a request's `stop`, a string or an array of strings
+fn stop_sequences(stop: Option<&Value>) -> Vec<String> {
+    match stop {
+        Some(Value::String(s)) if !s.is_empty() => vec![s.clone()],
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(Value::as_str)
+            .filter(|s| !s.is_empty())
+            .map(str::to_string)
+            .collect(),
+        _ => Vec::new(),
     }
+}
 
-    if let Some(reasoning) = &req.reasoning {
-        if reasoning.summary.is_some() || reasoning.generate_summary.is_some() {
-            log::warn!("⚠️  reasoning summary preferences are not supported and will be ignored");
-        }
+/// Length of the longest suffix of `text` that could still grow into a stop
+/// sequence; that much is held back until the next delta settles it
+fn stop_prefix_len(text: &str, stops: &[String]) -> usize {
+    stops
+        .iter()
+        .filter_map(|stop| {
+            (1..stop.len())
+                .rev()
+                .filter(|&len| stop.is_char_boundary(len))
+                .find(|&len| text.ends_with(&stop[..len]))
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Byte offset of the earliest stop sequence in `text`
+fn find_stop(text: &str, stops: &[String]) -> Option<usize> {
+    stops
+        .iter()
+        .filter_map(|stop| text.find(stop.as_str()))
+        .min()
+}
+
+async fn emit_reasoning_done_event(
+    tx: &tokio::sync::mpsc::Sender<EncodedEvent>,
+    sequencer: &mut EventSequencer,
+    response_id: &str,
+    request_id: &str,
+    segment: &ReasoningSegment,
+    summary: bool,
+) {
+    // A synthesized summary carries the reasoning text in place of reasoning_text
+    if summary {
+        let text_done_event = reasoning_summary_event(
+            "response.reasoning_summary_text.done",
+            &segment.item_id,
+            segment.output_index,
+            Some(segment.text.clone()),
+            None,
+        );
+        dispatch_event(tx, sequencer, response_id, request_id, text_done_event).await;
+        let part_done_event = reasoning_summary_event(
+            "response.reasoning_summary_part.done",
+            &segment.item_id,
+            segment.output_index,
+            None,
+            Some(OutputContent::SummaryText {
+                text: segment.text.clone(),
+            }),
+        );
+        dispatch_event(tx, sequencer, response_id, request_id, part_done_event).await;
+        return;
     }
 
-    if req.max_tool_calls.is_some() {
-        log::warn!("⚠️  max_tool_calls is not enforced");
+    let reasoning_done_event = StreamEvent {
+        type_: "response.reasoning_text.done".to_string(),
+        response: None,
+        event_id: None,
+        response_id: None,
+        item_id: Some(segment.item_id.clone()),
+        output_index: Some(segment.output_index),
+        content_index: Some(0),
+        delta: None,
+        text: Some(segment.text.clone()),
+        item: None,
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
+    };
+
+    dispatch_event(tx, sequencer, response_id, request_id, reasoning_done_event).await;
+}
+
+/// Drop less common parameters the backend model declares it cannot handle,
+/// rather than let the backend fail the whole request over them
+async fn drop_unsupported_params(chat_req: &mut ChatCompletionRequest, model: &str, app: &App) {
+    let mut dropped = Vec::new();
+    if (chat_req.logprobs.is_some() || chat_req.top_logprobs.is_some())
+        && model_lacks_feature(model, "logprobs", app).await
+    {
+        chat_req.logprobs = None;
+        chat_req.top_logprobs = None;
+        dropped.push("logprobs");
+    }
+    if chat_req.logit_bias.is_some() && model_lacks_feature(model, "logit_bias", app).await {
+        chat_req.logit_bias = None;
+        dropped.push("logit_bias");
+    }
+    if chat_req.n.is_some_and(|n| n > 1) && model_lacks_feature(model, "n", app).await {
+        chat_req.n = None;
+        dropped.push("n");
+    }
+    if !dropped.is_empty() {
+        log::warn!(
+            "⚠️ Model '{}' does not declare support for {} - dropping before forwarding",
+            model,
+            dropped.join(", ")
+        );
     }
+}
 
-    if let Some(text) = &req.text {
-        if text.verbosity.is_some() {
-            log::warn!("⚠️  text.verbosity is not supported");
+/// Merge client `extra_body` keys into the serialized backend request. Keys the
+/// proxy already set (model, messages, stream, ...) are kept as-is.
+fn merge_extra_body(backend_body: &mut Value, extra: serde_json::Map<String, Value>) {
+    let Some(body) = backend_body.as_object_mut() else {
+        return;
+    };
+    for (key, value) in extra {
+        if body.contains_key(&key) {
+            log::warn!(
+                "⚠️  extra_body key '{}' conflicts with a proxy field; ignoring",
+                key
+            );
+            continue;
         }
+        log::debug!("➕ Forwarding extra_body key '{}'", key);
+        body.insert(key, value);
     }
+}
 
-    if req.safety_identifier.is_some() {
-        log::warn!("⚠️  safety_identifier is not forwarded to the backend");
-    }
+/// Longest metadata value copied into logs
+const MAX_METADATA_TAG_LEN: usize = 64;
 
-    if req.prompt_cache_key.is_some() {
-        log::warn!("⚠️  prompt_cache_key is not forwarded to the backend");
+/// Render configured `metadata` keys as ` key=value` log tags. Values are cut
+/// to `MAX_METADATA_TAG_LEN` characters and whitespace is replaced so each tag
+/// stays a single token.
+fn metadata_log_tags(metadata: Option<&Value>, keys: &[String]) -> String {
+    let Some(metadata) = metadata.and_then(|m| m.as_object()) else {
+        return String::new();
+    };
+    let mut tags = String::new();
+    for key in keys {
+        let Some(value) = metadata.get(key) else {
+            continue;
+        };
+        let rendered = match value {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        let bounded: String = rendered
+            .chars()
+            .take(MAX_METADATA_TAG_LEN)
+            .map(|c| if c.is_whitespace() { '_' } else { c })
+            .collect();
+        tags.push_str(&format!(", {}={}", key, bounded));
     }
+    tags
+}
 
-    if req.service_tier.is_some() {
-        log::warn!("⚠️  service_tier overrides are not supported");
+/// Metadata keys whose values are never echoed back
+const SECRET_METADATA_MARKERS: [&str; 5] = ["key", "token", "secret", "password", "auth"];
+
+/// Copy of `metadata` for response snapshots: secret-looking keys are redacted
+/// and entries are kept in order only while they fit within `max_bytes` of JSON
+fn bounded_metadata(metadata: &Value, max_bytes: usize) -> Value {
+    let Some(entries) = metadata.as_object() else {
+        return metadata.clone();
+    };
+    let mut bounded = serde_json::Map::new();
+    let mut used = 2; // the surrounding braces
+    let mut dropped = 0;
+    for (key, value) in entries {
+        let lowered = key.to_ascii_lowercase();
+        let value = if SECRET_METADATA_MARKERS
+            .iter()
+            .any(|marker| lowered.contains(marker))
+        {
+            Value::String("***".to_string())
+        } else {
+            value.clone()
+        };
+        // "key":value plus a separating comma
+        let size = key.len() + value.to_string().len() + 4;
+        if used + size > max_bytes {
+            dropped += 1;
+            continue;
+        }
+        used += size;
+        bounded.insert(key.clone(), value);
+    }
+    if dropped > 0 {
+        log::debug!(
+            "✂️  Omitted {} metadata entr(ies) beyond {} bytes from response snapshots",
+            dropped,
+            max_bytes
+        );
     }
+    Value::Object(bounded)
 }
 
-pub async fn create_response(
-    State(app): State<App>,
-    headers: HeaderMap,
-    body: String,
-) -> Result<
-    (
-        HeaderMap,
-        Sse<impl Stream<Item = Result<Event, Infallible>>>,
-    ),
-    (StatusCode, &'static str),
-> {
+/// Re-serialize valid JSON without insignificant whitespace; `None` for
+/// anything that does not parse
+fn compact_json(text: &str) -> Option<String> {
+    serde_json::from_str::<Value>(text)
+        .ok()
+        .map(|value| value.to_string())
+}
+
+/// Backend token counts above this are treated as bogus and clamped
+const MAX_REPORTED_TOKENS: u32 = 100_000_000;
+
+/// Milliseconds since `start`, zero if the clock went backwards
+fn elapsed_ms(start: SystemTime) -> u64 {
+    start
+        .elapsed()
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Where a request's time went, in milliseconds: validation and conversion
+/// before the backend is contacted, the backend's wait until its first chunk,
+/// and streaming from there to the end
+struct TimingBreakdown {
+    prepare_ms: u64,
+    backend_ms: u64,
+    stream_ms: u64,
+}
+
+impl TimingBreakdown {
+    fn new(prepare_ms: u64, first_chunk_ms: Option<u64>, total_ms: u64) -> Self {
+        let first_chunk_ms = first_chunk_ms
+            .unwrap_or(total_ms)
+            .clamp(prepare_ms, total_ms);
+        Self {
+            prepare_ms,
+            backend_ms: first_chunk_ms - prepare_ms,
+            stream_ms: total_ms - first_chunk_ms,
+        }
+    }
+
+    /// Add these durations to the process-wide timing totals
+    fn record(&self, metrics: &ProxyMetrics) {
+        ProxyMetrics::incr(&metrics.timed_requests);
+        ProxyMetrics::add(&metrics.prepare_ms_total, self.prepare_ms);
+        ProxyMetrics::add(&metrics.backend_ms_total, self.backend_ms);
+        ProxyMetrics::add(&metrics.stream_ms_total, self.stream_ms);
+    }
+}
+
+/// Clamp an absurd backend-reported token count, logging the original value
+fn sane_token_count(reported: u32, field: &str) -> u32 {
+    if reported > MAX_REPORTED_TOKENS {
+        log::warn!(
+            "⚠️  Backend reported {}={}, clamping to {}",
+            field,
+            reported,
+            MAX_REPORTED_TOKENS
+        );
+        return MAX_REPORTED_TOKENS;
+    }
+    reported
+}
+
+/// Build a Responses usage block from backend token counts. `output_tokens`
+/// already includes `reasoning_tokens`, as in OpenAI usage.
+fn build_usage(input_tokens: u32, output_tokens: u32, reasoning_tokens: u32) -> Usage {
+    Usage {
+        input_tokens,
+        output_tokens,
+        total_tokens: input_tokens.saturating_add(output_tokens),
+        input_tokens_details: Some(TokenDetails {
+            cached_tokens: 0,
+            reasoning_tokens: 0,
+        }),
+        output_tokens_details: Some(TokenDetails {
+            cached_tokens: 0,
+            reasoning_tokens,
+        }),
+    }
+}
+
+/// Map converter errors that clients can act on to their own error codes
+fn conversion_error_code(error: &str) -> &'static str {
+    match error {
+        "missing_input" => "missing_input",
+        "duplicate_tool_name" => "duplicate_tool_name",
+        "invalid_reasoning_effort" => "invalid_reasoning_effort",
+        "tool_message_missing_tool_call_id" => "tool_message_missing_tool_call_id",
+        "tool_message_ambiguous_tool_call_id" => "tool_message_ambiguous_tool_call_id",
+        _ => "invalid_request",
+    }
+}
+
+/// `logit_bias` must map token-id strings to numbers in -100..=100
+fn is_valid_logit_bias(bias: &Value) -> bool {
+    bias.as_object().is_some_and(|map| {
+        map.iter().all(|(token, value)| {
+            token.parse::<u64>().is_ok()
+                && value
+                    .as_f64()
+                    .is_some_and(|b| (-100.0..=100.0).contains(&b))
+        })
+    })
+}
+
+fn warn_unsupported_features(req: &ResponseRequest) {
+    if let Some(include) = &req.include {
+        if !include.is_empty() {
+            log::warn!(
+                "⚠️  'include' values {:?} are not supported by this proxy and will be ignored",
+                include
+            );
+        }
+    }
+
+    if let Some(stream_options) = &req.stream_options {
+        if stream_options.include_obfuscation.is_some() {
+            log::warn!("⚠️  stream_options.include_obfuscation is not supported");
+        }
+    }
+
+    if req.conversation.is_some() {
+        log::warn!("⚠️  conversation references are ignored (proxy is stateless)");
+    }
+
+    if req.previous_response_id.is_some() {
+        log::warn!("⚠️  previous_response_id is ignored (proxy is stateless)");
+    }
+
+    if let Some(reasoning) = &req.reasoning {
+        if reasoning.summary.is_some() || reasoning.generate_summary.is_some() {
+            log::warn!(
+                "⚠️  reasoning summaries are not generated; the raw reasoning text is returned as the summary"
+            );
+        }
+    }
+
+    if req.max_tool_calls.is_some() {
+        log::warn!("⚠️  max_tool_calls is not enforced");
+    }
+
+    if let Some(text) = &req.text {
+        if text.verbosity.is_some() {
+            log::warn!("⚠️  text.verbosity is not supported");
+        }
+    }
+
+    if req.safety_identifier.is_some() {
+        log::warn!("⚠️  safety_identifier is not forwarded to the backend");
+    }
+
+    if req.prompt_cache_key.is_some() {
+        log::warn!("⚠️  prompt_cache_key is not forwarded to the backend");
+    }
+
+    if req.service_tier.is_some() {
+        log::warn!("⚠️  service_tier overrides are not supported");
+    }
+}
+
+pub async fn create_response(
+    State(app): State<App>,
+    version: Version,
+    headers: HeaderMap,
+    body: String,
+) -> Result<HttpResponse, (StatusCode, &'static str)> {
     let request_start = SystemTime::now();
     let request_id = format!(
         "{:x}",
         request_start.duration_since(UNIX_EPOCH).unwrap().as_nanos()
     );
 
+    let stream_format = StreamFormat::from_headers(&headers);
+    let config = Arc::clone(&app.config);
+    let sse_validation_errors = config.validation_errors_as_sse;
+    let model_for_error = sse_validation_errors
+        .then(|| serde_json::from_str::<Value>(&body).ok())
+        .flatten()
+        .and_then(|v| v.get("model")?.as_str().map(str::to_string))
+        .unwrap_or_default();
+
+    // Every log line emitted while handling this request is tagged with its id
+    let result = with_request_id(
+        request_id.clone(),
+        handle_create_response(app, version, headers, body, request_start, request_id),
+    )
+    .await;
+
+    // Optionally report a rejected request the way backend errors are
+    // reported, so SSE clients see it in their event state machine
+    match result {
+        Err((status, code)) if sse_validation_errors && is_validation_status(status) => {
+            let (tx, rx) = tokio::sync::mpsc::channel::<EncodedEvent>(1);
+            send_error_response(
+                tx,
+                model_for_error,
+                format!("Invalid request: {}", code),
+                code.to_string(),
+            );
+            Ok(event_stream_response(
+                &config,
+                version,
+                stream_format,
+                rx,
+                None,
+            ))
+        }
+        other => other,
+    }
+}
+
+/// Statuses `create_response` uses for requests it rejects as invalid
+fn is_validation_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY | StatusCode::PAYLOAD_TOO_LARGE
+    )
+}
+
+/// The response store and the caller's owner key within it
+fn caller_response_store<'a>(
+    app: &'a App,
+    headers: &HeaderMap,
+) -> Result<(&'a dyn ResponseStore, String), (StatusCode, &'static str)> {
+    let client_key = require_client_key(headers)?;
+    match app.response_store.as_deref() {
+        Some(store) => Ok((store, store_owner(&client_key))),
+        None => Err((StatusCode::NOT_FOUND, "response_not_found")),
+    }
+}
+
+/// `GET /v1/responses/{id}` - a response created with `store: true`
+pub async fn get_response(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Response>, (StatusCode, &'static str)> {
+    let (store, owner) = caller_response_store(&app, &headers)?;
+    store
+        .get(&owner, &id)
+        .map(Json)
+        .ok_or((StatusCode::NOT_FOUND, "response_not_found"))
+}
+
+/// `DELETE /v1/responses/{id}` - forget a stored response
+pub async fn delete_response(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Value>, (StatusCode, &'static str)> {
+    let (store, owner) = caller_response_store(&app, &headers)?;
+    if !store.delete(&owner, &id) {
+        return Err((StatusCode::NOT_FOUND, "response_not_found"));
+    }
+    log::info!("🗑️  Deleted stored response {}", id);
+    Ok(Json(
+        json!({"id": id, "object": "response.deleted", "deleted": true}),
+    ))
+}
+
+/// `POST /v1/responses/{id}/cancel` - only background responses can be
+/// cancelled, and stored responses have always finished
+pub async fn cancel_response(
+    State(app): State<App>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<Json<Response>, (StatusCode, &'static str)> {
+    let (store, owner) = caller_response_store(&app, &headers)?;
+    if store.get(&owner, &id).is_none() {
+        return Err((StatusCode::NOT_FOUND, "response_not_found"));
+    }
+    Err((StatusCode::BAD_REQUEST, "response_not_cancellable"))
+}
+
+async fn handle_create_response(
+    app: App,
+    version: Version,
+    headers: HeaderMap,
+    body: String,
+    request_start: SystemTime,
+    request_id: String,
+) -> Result<HttpResponse, (StatusCode, &'static str)> {
+    // Reserve a client connection slot; released when the SSE stream is dropped
+    let Some(connection_guard) = app.try_acquire_sse_slot() else {
+        log::warn!(
+            "❌ Rejecting request: too many open SSE connections (max {})",
+            app.config.max_sse_connections.unwrap_or_default()
+        );
+        return Err((StatusCode::SERVICE_UNAVAILABLE, "too_many_connections"));
+    };
+
     // Dump full request to logs
     dump_request(&body, &request_id);
 
@@ -335,13 +1246,13 @@ pub async fn create_response(
         } else {
             body.clone()
         };
-        log::debug!("📥 [{}] Incoming request body: {}", request_id, preview);
+        log::debug!("📥 Incoming request body: {}", preview);
     }
 
     // Parse request - detect if it's Chat Completions or Responses format
     let is_chat_completions_format = body.contains("\"messages\"") && !body.contains("\"input\"");
 
-    let req: ResponseRequest = match serde_json::from_str(&body) {
+    let mut req: ResponseRequest = match serde_json::from_str(&body) {
         Ok(r) => r,
         Err(e) => {
             log::error!("❌ Failed to parse request: {}", e);
@@ -357,8 +1268,8 @@ pub async fn create_response(
         log::info!("📨 Detected Chat Completions format request (using messages field)");
     }
 
-    if req.store.unwrap_or(false) {
-        log::warn!("⚠️  'store' flag requested but persistence is not supported; ignoring");
+    if req.store.unwrap_or(false) && app.response_store.is_none() {
+        log::warn!("⚠️  'store' flag requested but persistence is not configured; ignoring");
     }
 
     if req.background.unwrap_or(false) {
@@ -366,23 +1277,27 @@ pub async fn create_response(
         return Err((StatusCode::BAD_REQUEST, "background_not_supported"));
     }
 
-    if req.prompt.is_some() {
-        log::error!("❌ Prompt template references are not supported by this proxy");
-        return Err((StatusCode::BAD_REQUEST, "prompt_reference_not_supported"));
+    // Like the real API, a request may carry conversation state one way only
+    if req.conversation.is_some()
+        && req.previous_response_id.is_some()
+        && !app.config.allow_conflicting_state_params
+    {
+        log::warn!("❌ Validation failed: both conversation and previous_response_id set");
+        return Err((StatusCode::BAD_REQUEST, "conflicting_state_params"));
     }
 
-    // Circuit breaker check
-    {
-        let mut cb = app.circuit_breaker.write().await;
-        if !cb.should_allow_request() {
-            log::error!("🔴 Circuit breaker is open - rejecting request");
-            return Err((
-                StatusCode::SERVICE_UNAVAILABLE,
-                "backend_unavailable_circuit_open",
-            ));
-        }
+    if let Some(prompt) = &req.prompt {
+        let Some(template) = app.prompt_registry.get(&prompt.id) else {
+            log::error!("❌ Unknown prompt template reference '{}'", prompt.id);
+            return Err((StatusCode::BAD_REQUEST, "prompt_reference_not_supported"));
+        };
+        log::info!("📝 Expanding local prompt template '{}'", prompt.id);
+        expand_prompt(template, prompt, &mut req.instructions, &mut req.input);
     }
 
+    // Circuit breaker check
+    ensure_circuit_closed(&app).await?;
+
     // Request validation
     if let Some(crate::models::ResponseInput::Array(items)) = &req.input {
         if items.len() > 1000 {
@@ -405,6 +1320,31 @@ pub async fn create_response(
         }
     }
 
+    // Apply operator token clamps (after validation, so clamping never errors);
+    // a request without a limit gets the ceiling so no client goes uncapped
+    if req.max_output_tokens.is_none() && req.max_tokens.is_none() {
+        if let Some(ceiling) = app.config.max_output_tokens_ceiling {
+            log::debug!(
+                "✂️  No max_output_tokens requested, applying ceiling {}",
+                ceiling
+            );
+            req.max_output_tokens = Some(ceiling);
+        }
+    }
+    for limit in [&mut req.max_output_tokens, &mut req.max_tokens] {
+        if let Some(requested) = *limit {
+            let clamped = app.config.clamp_max_output_tokens(requested);
+            if clamped != requested {
+                log::info!(
+                    "✂️  Adjusted max_output_tokens {} → {} per configured limits",
+                    requested,
+                    clamped
+                );
+                *limit = Some(clamped);
+            }
+        }
+    }
+
     // Validate instructions length if provided
     if let Some(ref instructions) = req.instructions {
         if instructions.len() > 100 * 1024 {
@@ -430,6 +1370,25 @@ pub async fn create_response(
         }
     }
 
+    // Combined prompt budget, independent of the per-field limits above
+    if let Some(budget) = app.config.prompt_budget_bytes {
+        let prompt_size = req.instructions.as_ref().map_or(0, String::len)
+            + req.input.as_ref().map_or(0, estimate_input_size)
+            + req
+                .tools
+                .as_ref()
+                .and_then(|tools| serde_json::to_string(tools).ok())
+                .map_or(0, |schema| schema.len());
+        if prompt_size > budget {
+            log::warn!(
+                "❌ Validation failed: prompt size {} bytes exceeds budget of {} bytes",
+                prompt_size,
+                budget
+            );
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, "prompt_budget_exceeded"));
+        }
+    }
+
     if let Some(top_logprobs) = req.top_logprobs {
         if top_logprobs > 20 {
             log::warn!(
@@ -440,16 +1399,127 @@ pub async fn create_response(
         }
     }
 
+    // Validate penalties (Chat Completions accepts -2.0..=2.0)
+    if let Some(penalty) = req.frequency_penalty {
+        if !(-2.0..=2.0).contains(&penalty) {
+            log::warn!(
+                "❌ Validation failed: frequency_penalty out of range ({})",
+                penalty
+            );
+            return Err((StatusCode::BAD_REQUEST, "invalid_frequency_penalty"));
+        }
+    }
+    if let Some(penalty) = req.presence_penalty {
+        if !(-2.0..=2.0).contains(&penalty) {
+            log::warn!(
+                "❌ Validation failed: presence_penalty out of range ({})",
+                penalty
+            );
+            return Err((StatusCode::BAD_REQUEST, "invalid_presence_penalty"));
+        }
+    }
+
+    // Token-id keyed biases, as Chat Completions expects
+    if let Some(bias) = &req.logit_bias {
+        if !is_valid_logit_bias(bias) {
+            log::warn!("❌ Validation failed: malformed logit_bias {}", bias);
+            return Err((StatusCode::BAD_REQUEST, "invalid_logit_bias"));
+        }
+    }
+
+    if let Some(modalities) = &req.modalities {
+        if let Some(invalid) = modalities
+            .iter()
+            .find(|m| !matches!(m.as_str(), "text" | "audio"))
+        {
+            log::warn!("❌ Validation failed: unsupported modality '{}'", invalid);
+            return Err((StatusCode::BAD_REQUEST, "invalid_modalities"));
+        }
+    }
+
+    // The Responses API only knows `auto` and `disabled`; echo the normalized form
+    if let Some(truncation) = req.truncation.as_mut() {
+        let normalized = truncation.trim().to_ascii_lowercase();
+        if !matches!(normalized.as_str(), "auto" | "disabled") {
+            log::warn!(
+                "❌ Validation failed: unsupported truncation '{}'",
+                truncation
+            );
+            return Err((StatusCode::BAD_REQUEST, "invalid_truncation"));
+        }
+        *truncation = normalized;
+    }
+
     warn_unsupported_features(&req);
 
+    let extra_body = match req.extra_body.take() {
+        Some(Value::Object(extra)) if app.config.allow_extra_body => Some(extra),
+        Some(Value::Object(_)) => {
+            log::warn!("⚠️  extra_body ignored (ALLOW_EXTRA_BODY is off)");
+            None
+        }
+        Some(_) => {
+            log::warn!("❌ Validation failed: extra_body must be an object");
+            return Err((StatusCode::BAD_REQUEST, "invalid_extra_body"));
+        }
+        None => None,
+    };
+
+    let passthrough_requested = headers
+        .get("x-passthrough-backend")
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+    if passthrough_requested && !app.config.allow_backend_passthrough {
+        log::warn!("⚠️  x-passthrough-backend ignored (ENABLE_BACKEND_PASSTHROUGH is off)");
+    }
+    let passthrough_requested = passthrough_requested && app.config.allow_backend_passthrough;
+    let stream_format = StreamFormat::from_headers(&headers);
+    let api_version = headers
+        .get("x-responses-api-version")
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_string);
+    let debug_requested = app.config.proxy_debug_info
+        || headers
+            .get("x-proxy-debug")
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.eq_ignore_ascii_case("true") || v == "1");
+
     // Extract and validate auth
-    let client_key = extract_client_key(&headers);
+    let client_key = require_client_key(&headers)?;
 
-    if let Some(key) = &client_key {
-        log::info!("🔑 Client API Key: Bearer {}", mask_token(key));
-    } else {
-        log::warn!("❌ No client API key provided");
-        return Err((StatusCode::UNAUTHORIZED, "missing_api_key"));
+    // Resume an earlier generation of this exact request on Last-Event-ID reconnects
+    let replay = app.replay_buffer.as_ref().map(|buffer| {
+        let fingerprint = ReplayBuffer::fingerprint(&client_key, &body);
+        (Arc::clone(buffer), fingerprint)
+    });
+    let last_event_id = headers
+        .get("last-event-id")
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.trim().parse::<u32>().ok());
+    if let (Some((buffer, fingerprint)), Some(last_seq)) = (&replay, last_event_id) {
+        if buffer.can_resume(*fingerprint, last_seq) {
+            log::info!(
+                "🔁 Resuming stream after event {} from replay buffer",
+                last_seq
+            );
+            let (tx, rx) =
+                tokio::sync::mpsc::channel::<EncodedEvent>(app.config.event_channel_capacity());
+            tokio::spawn(with_request_id(
+                request_id.clone(),
+                replay_events(Arc::clone(buffer), *fingerprint, last_seq, tx),
+            ));
+            return Ok(event_stream_response(
+                &app.config,
+                version,
+                stream_format,
+                rx,
+                Some(connection_guard),
+            ));
+        }
+        log::info!(
+            "🔁 Last-Event-ID {} is not resumable, starting a fresh generation",
+            last_seq
+        );
     }
 
     // Extract and normalize model name
@@ -460,9 +1530,18 @@ pub async fn create_response(
 
     // Normalize model name (use Arc to avoid string clones for error/metrics)
     let backend_model: Arc<str> = Arc::from(normalize_model_name(&requested_model, &app).await);
+    // Ensemble aliases race several backend models instead of naming one
+    let ensemble = app
+        .config
+        .ensemble_for(&requested_model)
+        .map(<[String]>::to_vec);
     let backend_model_for_error = Arc::clone(&backend_model);
     let backend_model_for_metrics = Arc::clone(&backend_model);
 
+    // Models declared without reasoning support may leak spurious reasoning
+    let suppress_reasoning = app.config.reasoning_feature_gate
+        && model_lacks_feature(&backend_model, "reasoning", &app).await;
+
     // Check model capability for tool calling
     let supports_native_tools = model_supports_feature(&backend_model, "tools", &app).await
         || model_supports_feature(&backend_model, "function_calling", &app).await;
@@ -478,8 +1557,41 @@ pub async fn create_response(
         }
     }
 
+    // Text-only models: optionally drop or refuse image/file inputs up front
+    if app.config.vision_fallback != VisionFallback::Forward
+        && req.input.as_ref().is_some_and(input_has_media)
+        && !model_supports_feature(&backend_model, "vision", &app).await
+    {
+        if app.config.vision_fallback == VisionFallback::Reject {
+            log::warn!(
+                "❌ Model '{}' does not support vision but the input has images/files",
+                backend_model
+            );
+            return Err((StatusCode::BAD_REQUEST, "model_lacks_vision"));
+        }
+        if let Some(input) = req.input.as_mut() {
+            let stripped = strip_media_parts(input);
+            log::info!(
+                "🖼️  Model '{}' is text-only - replaced {} image/file part(s) with notes",
+                backend_model,
+                stripped
+            );
+        }
+    }
+
+    let audio_requested = req
+        .modalities
+        .as_ref()
+        .is_some_and(|m| m.iter().any(|m| m == "audio"));
+    if audio_requested && !model_supports_feature(&backend_model, "audio", &app).await {
+        log::warn!(
+            "⚠️ Model '{}' does not declare audio output support - forwarding modalities anyway",
+            backend_model
+        );
+    }
+
     // Convert Responses API request to Chat Completions format
-    let chat_req = match convert_to_chat_completions(&req, supports_native_tools) {
+    let mut chat_req = match convert_to_chat_completions(&req, supports_native_tools, &app.config) {
         Ok(mut cr) => {
             // Ensure the normalized model name is used in the converted request
             cr.model = backend_model.to_string();
@@ -487,10 +1599,12 @@ pub async fn create_response(
         }
         Err(e) => {
             log::error!("❌ Request conversion failed: {}", e);
-            return Err((StatusCode::BAD_REQUEST, "invalid_request"));
+            return Err((StatusCode::BAD_REQUEST, conversion_error_code(&e)));
         }
     };
 
+    drop_unsupported_params(&mut chat_req, &backend_model, &app).await;
+
     // Add detailed tool logging for debugging
     if let Some(ref tools) = req.tools {
         log::info!("🔧 Original request contains {} tool(s)", tools.len());
@@ -534,122 +1648,152 @@ pub async fn create_response(
         }
     }
 
+    // Client-supplied metadata the operator wants on logs and metrics
+    let metadata_tags = metadata_log_tags(req.metadata.as_ref(), &app.config.metrics_metadata_keys);
+
     log::info!(
-        "📨 Request: model={}, messages={}, stream={}, backend={}",
+        "📨 Request: model={}, messages={}, stream={}, backend={}{}",
         backend_model.as_ref(),
         chat_req.messages.len(),
         chat_req.stream,
-        app.backend_url
+        app.backend_url,
+        metadata_tags
     );
 
-    // Build the backend request
-    let mut backend_req = app
-        .client
-        .post(&app.backend_url)
-        .header("content-type", "application/json");
-
-    // Forward client auth to backend
-    if let Some(key) = &client_key {
-        backend_req = backend_req.bearer_auth(key);
-        log::info!("🔄 Auth: Forwarding client key to backend");
-    }
+    log::info!("🔄 Auth: Forwarding client key to backend");
 
     // Send request to backend
     log::debug!(
-        "🚀 [{}] Sending request to backend with {} messages",
-        request_id,
+        "🚀 Sending request to backend with {} messages",
         chat_req.messages.len()
     );
 
-    // Dump backend request
-    if let Ok(backend_body) = serde_json::to_string(&chat_req) {
-        dump_backend_request(&backend_body, &request_id);
-    }
-
-    let res = backend_req.json(&chat_req).send().await.map_err(|e| {
-        log::error!("❌ Backend connection failed: {}", e);
-        record_circuit_breaker_failure(app.circuit_breaker.clone());
-        (StatusCode::BAD_GATEWAY, "backend_unavailable")
+    // Serialize once so provider-specific extra_body keys can be merged in
+    let mut backend_body = serde_json::to_value(&chat_req).map_err(|e| {
+        log::error!("❌ Failed to serialize backend request: {}", e);
+        (StatusCode::INTERNAL_SERVER_ERROR, "internal_error")
     })?;
+    if let Some(extra) = extra_body {
+        merge_extra_body(&mut backend_body, extra);
+    }
+    app.hooks.transform_backend_request(&mut backend_body);
 
-    let status = res.status();
-    log::debug!("📥 Backend response status: {}", status);
-
-    // Handle non-success responses
-    if !status.is_success() {
-        record_circuit_breaker_failure(app.circuit_breaker.clone());
-
-        let error_body = read_bounded_error(res).await;
-
-        log::error!(
-            "❌ Backend returned error: {} {} - {} ({} bytes)",
-            status.as_u16(),
-            status.canonical_reason().unwrap_or(""),
-            &error_body[..error_body.len().min(200)], // Log first 200 chars
-            error_body.len()
-        );
+    // Dump backend request
+    dump_backend_request(&backend_body.to_string(), &request_id);
 
-        // Create error stream for non-success responses
-        let (tx, rx) = tokio::sync::mpsc::channel::<Event>(64);
+    let prepare_ms = elapsed_ms(request_start);
 
-        // Handle 404 with model list
-        if status == StatusCode::NOT_FOUND {
-            let models = get_available_models(&app).await;
-            if !models.is_empty() {
-                log::info!(
-                    "💡 Model '{}' not found - sending model list",
-                    backend_model_for_error
-                );
-                send_error_response(
-                    tx,
-                    backend_model_for_error.to_string(),
-                    build_model_list_content(&backend_model_for_error, &models),
-                    "model_not_found".to_string(),
-                );
-            } else {
-                send_error_response(
-                    tx,
-                    backend_model_for_error.to_string(),
-                    format_backend_error(&error_body, &error_body),
-                    "backend_error".to_string(),
-                );
+    // Early acknowledgement: return the stream now and contact the backend from
+    // the streaming task, so response.created reaches the client first
+    let (tx, rx) = tokio::sync::mpsc::channel::<EncodedEvent>(app.config.event_channel_capacity());
+    let backend = if app.config.early_response_created && !passthrough_requested {
+        log::debug!("⏩ Emitting response.created before contacting backend");
+        BackendResponse::Deferred(backend_body.clone())
+    } else {
+        let backend = match &ensemble {
+            Some(models) => race_ensemble(&app, &client_key, &backend_body, models)
+                .await
+                .map(|(stream, model)| BackendResponse::Raced(stream, Arc::from(model))),
+            None => {
+                let (res, served_model) =
+                    send_with_model_fallback(&app, &client_key, &backend_body, &backend_model)
+                        .await?;
+                check_backend_response(&app, res, &served_model)
+                    .await
+                    .map(|res| BackendResponse::Ready(res, Arc::from(served_model)))
+            }
+        };
+        match backend {
+            Ok(backend) => backend,
+            Err((message, code)) => {
+                send_error_response(tx, backend_model_for_error.to_string(), message, code);
+
+                return Ok(event_stream_response(
+                    &app.config,
+                    version,
+                    stream_format,
+                    rx,
+                    Some(connection_guard),
+                ));
             }
-        } else {
-            send_error_response(
-                tx,
-                backend_model_for_error.to_string(),
-                format_backend_error(&error_body, &error_body),
-                "backend_error".to_string(),
-            );
         }
+    };
 
-        let mut out_headers = HeaderMap::new();
-        out_headers.insert("cache-control", "no-cache".parse().unwrap());
-        out_headers.insert("connection", "keep-alive".parse().unwrap());
-        out_headers.insert("x-accel-buffering", "no".parse().unwrap());
-        out_headers.insert("content-type", "text/event-stream".parse().unwrap());
+    // Debug passthrough: hand the backend's SSE bytes to the client untouched
+    let backend = match backend {
+        BackendResponse::Ready(res, _) if passthrough_requested => {
+            log::info!("🪞 Passthrough mode: forwarding raw backend stream");
+            let cb_clone = app.circuit_breaker.clone();
+            spawn_with_request_id(async move {
+                cb_clone.write().await.record_success();
+            });
 
-        let stream = ReceiverStream::new(rx).map(Ok::<Event, Infallible>);
-        return Ok((out_headers, Sse::new(stream)));
-    }
+            let content_type = res
+                .headers()
+                .get("content-type")
+                .cloned()
+                .unwrap_or_else(|| "text/event-stream".parse().unwrap());
+            let raw_stream = res.bytes_stream().map(move |chunk| {
+                let _ = &connection_guard;
+                chunk
+            });
 
-    log::info!("✅ Backend responded successfully ({})", status);
+            let mut out_headers = HeaderMap::new();
+            out_headers.insert("cache-control", "no-cache".parse().unwrap());
+            out_headers.insert("x-accel-buffering", "no".parse().unwrap());
+            out_headers.insert("content-type", content_type);
+            apply_sse_header_overrides(&mut out_headers, &app.config.sse_headers);
+            return Ok((out_headers, Body::from_stream(raw_stream)).into_response());
+        }
+        backend => {
+            // Ensemble races pick a winner from several streams, so there is no
+            // single backend body to forward
+            if passthrough_requested {
+                log::warn!("⚠️  x-passthrough-backend ignored for ensemble requests");
+            }
+            backend
+        }
+    };
 
-    let (tx, rx) = tokio::sync::mpsc::channel::<Event>(64);
-    let model_for_response = Arc::clone(&backend_model);
+    let mut model_for_response = match &backend {
+        BackendResponse::Ready(_, served_model) | BackendResponse::Raced(_, served_model) => {
+            Arc::clone(served_model)
+        }
+        BackendResponse::Deferred(_) => Arc::clone(&backend_model),
+    };
 
     // Clone request parameters to echo back in response
     let req_instructions = req.instructions.clone();
     let req_tools = req.tools.clone();
     let req_tool_choice = req.tool_choice.clone();
     let req_parallel_tool_calls = req.parallel_tool_calls;
-    let req_temperature = req.temperature;
-    let req_top_p = req.top_p;
+    // Echo what the backend was actually sent, including server defaults and
+    // whichever sampling parameter SAMPLING_PARAM_PREFERENCE dropped
+    let req_temperature = chat_req.temperature;
+    let req_top_p = chat_req.top_p;
     let req_max_output_tokens = req.max_output_tokens;
-    let req_metadata = req.metadata.clone();
-    let req_store = Some(false);
+    let req_metadata = match app.config.metadata_echo_max_bytes {
+        Some(max_bytes) => req
+            .metadata
+            .as_ref()
+            .map(|metadata| bounded_metadata(metadata, max_bytes)),
+        None => req.metadata.clone(),
+    };
+    // Persist the finished response under the caller's key when asked to
+    let store_target = app
+        .response_store
+        .clone()
+        .filter(|_| req.store.unwrap_or(false))
+        .map(|store| (store, store_owner(&client_key)));
+    let req_store = Some(store_target.is_some());
     let req_previous_response_id = req.previous_response_id.clone();
     let req_reasoning_state = req.reasoning.as_ref().map(ResponseReasoningState::from);
+    let reasoning_summary_requested = req.reasoning.as_ref().is_some_and(|r| {
+        r.summary
+            .as_deref()
+            .or(r.generate_summary.as_deref())
+            .is_some_and(|s| s != "none")
+    });
     let req_background = req.background;
     let req_max_tool_calls = req.max_tool_calls;
     let req_text = req.text.clone();
@@ -657,16 +1801,47 @@ pub async fn create_response(
     let req_truncation = req.truncation.clone();
     let req_conversation = req.conversation.clone();
     let req_top_logprobs = req.top_logprobs;
+    let req_include_usage = req
+        .stream_options
+        .as_ref()
+        .and_then(|so| so.include_usage)
+        .unwrap_or(false);
+    let req_stop = if app.config.enforce_stop_locally {
+        stop_sequences(req.stop.as_ref())
+    } else {
+        Vec::new()
+    };
     let req_user = req.user.clone();
     let req_safety_identifier = req.safety_identifier.clone();
     let req_prompt_cache_key = req.prompt_cache_key.clone();
     let req_service_tier = req.service_tier.clone();
 
+    // Non-streaming retry body, only built when the fallback is enabled
+    let mut fallback_body = app.config.non_stream_fallback.then(|| {
+        let mut body = backend_body.clone();
+        body["stream"] = Value::Bool(false);
+        if let Some(obj) = body.as_object_mut() {
+            obj.remove("stream_options");
+        }
+        body
+    });
+    // Body re-sent when the stream fails before any content, if retries are on
+    let early_retry_body = (app.config.early_error_retries > 0).then(|| backend_body.clone());
+    let task_client_key = client_key.clone();
+
     // Clone request_id for logging in spawn
     let request_id_clone = request_id.clone();
+    let replay_recorder = replay.and_then(|(buffer, fingerprint)| {
+        let recorder = buffer.begin(fingerprint);
+        if recorder.is_none() {
+            log::info!("🔁 Identical request already streaming, not recording this one for replay");
+        }
+        recorder
+    });
+    let stream_config = Arc::clone(&app.config);
 
     // Spawn streaming task
-    tokio::spawn(async move {
+    tokio::spawn(with_request_id(request_id.clone(), async move {
         let request_id = request_id_clone;
         log::debug!("🎬 Streaming task started");
 
@@ -676,7 +1851,19 @@ pub async fn create_response(
         let response_id = format!("resp_{}", request_id);
         let message_id = format!("msg_{}", id_seed);
         let reasoning_id_seed = format!("reasoning_{}", id_seed);
-        let mut sequencer = EventSequencer::new();
+        let mut sequencer = EventSequencer::new(replay_recorder);
+        if let Some(window) = app.config.delta_coalesce_window {
+            sequencer = sequencer.with_coalescing(window, app.config.delta_coalesce_max_bytes);
+        }
+        if let Some(object) = app.config.item_object_for(api_version.as_deref()) {
+            sequencer = sequencer.with_item_object(object.to_string());
+        }
+        if let Some(hook) = &app.hooks.stream_event {
+            sequencer = sequencer.with_event_hook(Arc::clone(hook));
+        }
+        if app.config.slow_client_policy == SlowClientPolicy::Drop {
+            sequencer = sequencer.with_drop_when_full();
+        }
 
         // Send response.created event
         let created_event = StreamEvent {
@@ -714,6 +1901,7 @@ pub async fn create_response(
                 safety_identifier: req_safety_identifier.clone(),
                 prompt_cache_key: req_prompt_cache_key.clone(),
                 service_tier: req_service_tier.clone(),
+                proxy_debug: None,
             }),
             event_id: None,
             response_id: None,
@@ -728,6 +1916,10 @@ pub async fn create_response(
             name: None,
             arguments: None,
             error: None,
+            annotations: None,
+            usage: None,
+            part: None,
+            summary_index: None,
         };
         dispatch_event(
             &tx,
@@ -738,102 +1930,196 @@ pub async fn create_response(
         )
         .await;
 
-        // Send output_item.added event
-        let item_added_event = StreamEvent {
-            type_: "response.output_item.added".to_string(),
-            response: None,
-            item_id: Some(message_id.clone()),
-            output_index: Some(0),
-            content_index: None,
-            delta: None,
-            text: None,
-            item: Some(OutputItem {
-                id: message_id.clone(),
-                object: REALTIME_ITEM_OBJECT.to_string(),
-                type_: "message".to_string(),
-                status: "in_progress".to_string(),
-                role: Some("assistant".to_string()),
-                content: Some(vec![]),
-                call_id: None,
-                name: None,
-                arguments: None,
-                output: None,
-            }),
-            event_id: None,
-            response_id: None,
-            sequence_number: None,
-            call_id: None,
-            name: None,
-            arguments: None,
-            error: None,
-        };
-        dispatch_event(
-            &tx,
-            &mut sequencer,
-            &response_id,
-            &request_id,
-            item_added_event,
-        )
-        .await;
-
-        // Send content_part.added event
-        let content_added_event = StreamEvent {
-            type_: "response.content_part.added".to_string(),
-            response: None,
-            item_id: Some(message_id.clone()),
-            output_index: Some(0),
-            content_index: Some(0),
-            delta: None,
-            text: None,
-            item: None,
-            event_id: None,
-            response_id: None,
-            sequence_number: None,
-            call_id: None,
-            name: None,
-            arguments: None,
-            error: None,
+        // Deferred mode: the backend is only contacted now that created is out
+        let mut bytes_stream: ByteStream = match backend {
+            BackendResponse::Ready(res, _) => Box::pin(res.bytes_stream()),
+            BackendResponse::Raced(stream, _) => stream,
+            BackendResponse::Deferred(backend_body) => {
+                let resolved = match &ensemble {
+                    Some(models) => {
+                        race_ensemble(&app, &task_client_key, &backend_body, models).await
+                    }
+                    None => resolve_deferred_backend(
+                        &app,
+                        &task_client_key,
+                        &backend_body,
+                        &model_for_response,
+                    )
+                    .await
+                    .map(|(res, model)| (Box::pin(res.bytes_stream()) as ByteStream, model)),
+                };
+                match resolved {
+                    Ok((stream, served_model)) => {
+                        model_for_response = Arc::from(served_model);
+                        stream
+                    }
+                    Err((message, code)) => {
+                        let failed_event = failed_response_event(
+                            &response_id,
+                            created_at,
+                            model_for_response.to_string(),
+                            message,
+                            code,
+                        );
+                        dispatch_event(
+                            &tx,
+                            &mut sequencer,
+                            &response_id,
+                            &request_id,
+                            failed_event,
+                        )
+                        .await;
+                        return;
+                    }
+                }
+            }
         };
-        dispatch_event(
-            &tx,
-            &mut sequencer,
-            &response_id,
-            &request_id,
-            content_added_event,
-        )
-        .await;
 
-        let mut bytes_stream = res.bytes_stream();
-        let mut sse_parser = SseEventParser::new();
+        // Message item; deferred until the first text delta when configured
+        let defer_message_item = app.config.defer_message_item;
+        let mut message_index: Option<u32> = None;
+        let mut next_output_index = 0u32;
+        if !defer_message_item {
+            open_message_item(
+                &tx,
+                &mut sequencer,
+                &response_id,
+                &request_id,
+                &message_id,
+                &mut message_index,
+                &mut next_output_index,
+            )
+            .await;
+        }
+
+        let mut usable_chunks = 0u32;
+        let mut content_started = false;
+        let mut early_retries_left = app.config.early_error_retries;
+        let mut restart_stream = false;
+        let mut first_chunk_ms: Option<u64> = None;
+        let mut sse_parser =
+            SseEventParser::new().with_max_event_bytes(app.config.max_sse_event_bytes());
         let mut accumulated_text = String::new();
         let mut accumulated_reasoning = String::new();
-        let mut reasoning_started = false;
-        let mut reasoning_item_id: Option<String> = None;
+        let mut suppressed_reasoning = String::new();
+        let mut accumulated_annotations: Vec<Value> = Vec::new();
+        let mut reasoning_segments: Vec<ReasoningSegment> = Vec::new();
+        let mut reasoning_open = false;
+        let mut reasoning_capped = false;
         let mut done = false;
         let mut final_status = "completed";
+        let mut response_error = None;
+        // A retry the backend refused has already been counted by the breaker
+        let mut retry_failed = false;
         let mut total_input_tokens = 0u32;
         let mut total_output_tokens = 0u32;
+        let mut total_reasoning_tokens = 0u32;
         let mut backend_chunk_num = 0u32;
 
         // Tool call tracking
         use std::collections::HashMap;
         let mut tool_calls: HashMap<usize, ToolCallState> = HashMap::new();
+        // Reasoning items and tool calls each take their own output index
         let mut next_xml_index: usize = 0; // Track next available index for XML tool calls
 
         // XML buffering - track if we're waiting for closing tag
+        // Trailing text that may be the start of a stop sequence
+        let mut stop_held = String::new();
         let mut xml_buffering = false;
+        let mut xml_buffer_start = 0usize; // Offset in accumulated_text where buffering began
+                                           // The same buffer also holds JSON `tool_calls` written as text
+        let mut json_buffering = false;
+        let mut json_start = 0usize; // Offset of the JSON structure being buffered
+        let mut json_scan_from = 0usize; // Text before this was already ruled out
 
         // Process streaming response
-        while let Some(item) = bytes_stream.next().await {
-            let chunk = match item {
-                Ok(chunk) => chunk,
-                Err(e) => {
+        let mut client_cancelled = false;
+        loop {
+            // A dropped receiver means the client went away; stop pulling from the
+            // backend unless the replay buffer keeps the generation resumable
+            if tx.is_closed() && sequencer.replay.is_none() {
+                client_cancelled = true;
+                break;
+            }
+            // Don't let a quiet backend hold a coalesced delta past its window
+            let next_item = match sequencer.coalesce_deadline() {
+                Some(deadline) => {
+                    match tokio::time::timeout_at(deadline, bytes_stream.next()).await {
+                        Ok(item) => item,
+                        Err(_) => {
+                            flush_coalesced_delta(&tx, &mut sequencer, &response_id, &request_id)
+                                .await;
+                            continue;
+                        }
+                    }
+                }
+                None => bytes_stream.next().await,
+            };
+            let events = match next_item {
+                Some(Ok(chunk)) => match sse_parser.push_and_drain_events(&chunk) {
+                    Ok(events) => events,
+                    Err(oversized) => {
+                        log::error!(
+                            "❌ Backend event exceeded {} bytes without a terminator ({} buffered), aborting stream",
+                            oversized.limit,
+                            oversized.bytes
+                        );
+                        final_status = "failed";
+                        response_error = Some(ResponseError {
+                            code: "backend_oversized_event".to_string(),
+                            message: format!(
+                                "backend sent an SSE event larger than {} bytes",
+                                oversized.limit
+                            ),
+                        });
+                        break;
+                    }
+                },
+                Some(Err(e)) => {
                     log::error!("❌ Error reading chunk from stream: {}", e);
-                    break;
+                    if content_started || early_retries_left == 0 {
+                        break;
+                    }
+                    restart_stream = true;
+                    Vec::new()
+                }
+                None => {
+                    // Events the backend never terminated with a blank line still count
+                    let pending = sse_parser.finish();
+                    if !pending.is_empty() {
+                        bytes_stream = Box::pin(futures::stream::empty());
+                        pending
+                    } else {
+                        // Nothing usable arrived: retry once without streaming and feed
+                        // the JSON reply through this same loop
+                        if usable_chunks == 0 {
+                            if let Some(body) = fallback_body.take() {
+                                if let Some(stream) = non_streaming_fallback(
+                                    &app,
+                                    &task_client_key,
+                                    &body,
+                                    &model_for_response,
+                                )
+                                .await
+                                {
+                                    bytes_stream = stream;
+                                    done = false;
+                                    continue;
+                                }
+                                retry_failed = true;
+                            }
+                        }
+                        break;
+                    }
                 }
             };
 
-            for payload in sse_parser.push_and_drain_events(&chunk) {
+            let payloads = events.into_iter().flat_map(split_batched_payload);
+            for payload in payloads {
+                // Nothing after a locally enforced stop is streamed
+                if done {
+                    break;
+                }
                 let data = payload.trim();
 
                 // Dump backend chunk
@@ -841,7 +2127,7 @@ pub async fn create_response(
                 dump_backend_chunk(data, &request_id, backend_chunk_num);
 
                 if data == "[DONE]" {
-                    log::debug!("🏁 [{}] Received [DONE] marker from backend", request_id);
+                    log::debug!("🏁 Received [DONE] marker from backend");
                     done = true;
                     break;
                 }
@@ -858,14 +2144,72 @@ pub async fn create_response(
                         continue;
                     }
                 };
+                usable_chunks += 1;
+                if first_chunk_ms.is_none() {
+                    first_chunk_ms = Some(elapsed_ms(request_start));
+                }
 
-                // Handle error in chunk
+                // Handle error in chunk; before any content it may be retried
                 if let Some(error) = &chunk.error {
                     log::error!("❌ Backend returned error in chunk: {:?}", error);
+                    if !content_started && early_retries_left > 0 {
+                        restart_stream = true;
+                        break;
+                    }
                     final_status = "failed";
                     done = true;
                     break;
                 }
+                content_started |= chunk_has_content(&chunk);
+
+                // Capture usage if provided (usage-only chunks carry no choices)
+                if let Some(usage) = &chunk.usage {
+                    if let Some(prompt) = usage.prompt_tokens {
+                        total_input_tokens = sane_token_count(prompt, "prompt_tokens");
+                    }
+                    if let Some(completion) = usage.completion_tokens {
+                        total_output_tokens = sane_token_count(completion, "completion_tokens");
+                    }
+                    if let Some(reasoning) = usage.reasoning_tokens() {
+                        total_reasoning_tokens = sane_token_count(reasoning, "reasoning_tokens");
+                        // Some backends count reasoning outside completion_tokens
+                        if total_reasoning_tokens > total_output_tokens {
+                            total_output_tokens =
+                                total_output_tokens.saturating_add(total_reasoning_tokens);
+                        }
+                    }
+
+                    // Surface interim usage to clients that asked for it
+                    if req_include_usage {
+                        let usage_event = StreamEvent {
+                            type_: "response.usage.updated".to_string(),
+                            response: None,
+                            event_id: None,
+                            response_id: None,
+                            item_id: None,
+                            output_index: None,
+                            content_index: None,
+                            delta: None,
+                            text: None,
+                            item: None,
+                            sequence_number: None,
+                            call_id: None,
+                            name: None,
+                            arguments: None,
+                            error: None,
+                            annotations: None,
+                            usage: Some(build_usage(
+                                total_input_tokens,
+                                total_output_tokens,
+                                total_reasoning_tokens,
+                            )),
+                            part: None,
+                            summary_index: None,
+                        };
+                        dispatch_event(&tx, &mut sequencer, &response_id, &request_id, usage_event)
+                            .await;
+                    }
+                }
 
                 if chunk.choices.is_empty() {
                     continue;
@@ -883,29 +2227,66 @@ pub async fn create_response(
                     );
                 }
 
-                // Capture usage if provided
-                if let Some(usage) = &chunk.usage {
-                    if let Some(prompt) = usage.prompt_tokens {
-                        total_input_tokens = prompt;
-                    }
-                    if let Some(completion) = usage.completion_tokens {
-                        total_output_tokens = completion;
-                    }
+                // Legacy Completions backends put the text directly on the choice
+                let legacy_text = match (&choice.message, &choice.delta) {
+                    (None, None) => choice.text.as_deref(),
+                    _ => None,
+                };
+                if legacy_text.is_some() {
+                    log::debug!("📜 Backend sent legacy choices[].text, treating as content");
                 }
 
+                // Tool calls delivered whole on a terminal message share the delta path below
+                let message_tool_calls = choice.message.as_ref().and_then(message_tool_calls);
+
                 // Handle complete message (non-streaming fallback)
-                if let Some(message) = &choice.message {
-                    if let Some(content) = message.get("content").and_then(|v| v.as_str()) {
+                if choice.message.is_some() || legacy_text.is_some() {
+                    let message = choice.message.as_ref();
+                    if let Some(annotations) = message
+                        .and_then(|m| m.get("annotations"))
+                        .and_then(|v| v.as_array())
+                    {
+                        accumulated_annotations.extend(annotations.iter().cloned());
+                    }
+                    let content = message
+                        .and_then(|m| m.get("content"))
+                        .and_then(|v| v.as_str())
+                        .or(legacy_text);
+                    if let Some(content) = content.filter(|c| !c.is_empty()) {
+                        if reasoning_open {
+                            reasoning_open = false;
+                            if let Some(segment) = reasoning_segments.last() {
+                                emit_reasoning_done_event(
+                                    &tx,
+                                    &mut sequencer,
+                                    &response_id,
+                                    &request_id,
+                                    segment,
+                                    reasoning_summary_requested,
+                                )
+                                .await;
+                            }
+                        }
                         accumulated_text.push_str(content);
 
                         // Send delta event
+                        let message_output_index = open_message_item(
+                            &tx,
+                            &mut sequencer,
+                            &response_id,
+                            &request_id,
+                            &message_id,
+                            &mut message_index,
+                            &mut next_output_index,
+                        )
+                        .await;
                         let delta_event = StreamEvent {
                             type_: "response.output_text.delta".to_string(),
                             response: None,
                             event_id: None,
                             response_id: None,
                             item_id: Some(message_id.clone()),
-                            output_index: Some(0),
+                            output_index: Some(message_output_index),
                             content_index: Some(0),
                             delta: Some(content.to_string()),
                             text: None,
@@ -915,47 +2296,120 @@ pub async fn create_response(
                             name: None,
                             arguments: None,
                             error: None,
+                            annotations: None,
+                            usage: None,
+                            part: None,
+                            summary_index: None,
                         };
 
                         dispatch_event(&tx, &mut sequencer, &response_id, &request_id, delta_event)
                             .await;
                     }
-                    continue;
+                    if message_tool_calls.is_none() {
+                        continue;
+                    }
                 }
 
                 // Handle streaming delta
                 if let Some(delta) = &choice.delta {
-                    // Handle reasoning content (for reasoning models)
-                    if let Some(reasoning) = &delta.reasoning_content {
+                    // Collect citations so the done events can finalize them
+                    if let Some(annotations) = &delta.annotations {
+                        accumulated_annotations.extend(annotations.iter().cloned());
+                    }
+
+                    // Handle reasoning content (for reasoning models); a gated-off
+                    // model's reasoning is only kept for the log
+                    if let (true, Some(reasoning)) = (suppress_reasoning, &delta.reasoning_content)
+                    {
+                        suppressed_reasoning.push_str(reasoning);
+                    } else if let Some(reasoning) = &delta.reasoning_content {
+                        let reasoning = cap_reasoning_delta(
+                            reasoning,
+                            accumulated_reasoning.len(),
+                            app.config.max_reasoning_bytes,
+                            &mut reasoning_capped,
+                        );
                         if !reasoning.is_empty() {
                             accumulated_reasoning.push_str(reasoning);
 
-                            // Start reasoning item if not started
-                            if !reasoning_started {
-                                reasoning_item_id = Some(reasoning_id_seed.clone());
-                                reasoning_started = true;
-                                log::info!(
-                                    "🧠 Reasoning content detected, emitting reasoning events"
-                                );
+                            // Open a reasoning item (a new one if text interrupted the last)
+                            if !reasoning_open {
+                                let item_id = if reasoning_segments.is_empty() {
+                                    log::info!(
+                                        "🧠 Reasoning content detected, emitting reasoning events"
+                                    );
+                                    reasoning_id_seed.clone()
+                                } else {
+                                    log::debug!(
+                                        "🧠 Reasoning resumed after text, opening new item"
+                                    );
+                                    format!("{}_{}", reasoning_id_seed, reasoning_segments.len())
+                                };
+                                let output_index = allocate_output_index(&mut next_output_index);
+                                emit_reasoning_added_event(
+                                    &tx,
+                                    &mut sequencer,
+                                    &response_id,
+                                    &request_id,
+                                    &item_id,
+                                    output_index,
+                                    reasoning_summary_requested,
+                                )
+                                .await;
+                                reasoning_segments.push(ReasoningSegment {
+                                    item_id,
+                                    output_index,
+                                    text: String::new(),
+                                });
+                                reasoning_open = true;
                             }
-
-                            // Send reasoning delta event
-                            let reasoning_delta_event = StreamEvent {
-                                type_: "response.reasoning_text.delta".to_string(),
-                                response: None,
-                                event_id: None,
-                                response_id: None,
-                                item_id: reasoning_item_id.clone(),
-                                output_index: Some(0),
-                                content_index: Some(0),
-                                delta: Some(reasoning.clone()),
-                                text: None,
-                                item: None,
-                                sequence_number: None,
-                                call_id: None,
-                                name: None,
-                                arguments: None,
-                                error: None,
+                            let (reasoning_item_id, reasoning_output_index) = reasoning_segments
+                                .last_mut()
+                                .map(|segment| {
+                                    segment.text.push_str(reasoning);
+                                    (Some(segment.item_id.clone()), Some(segment.output_index))
+                                })
+                                .unwrap_or_default();
+
+                            // Send reasoning delta event, as summary text when a
+                            // summary was requested
+                            let reasoning_delta_event = match (
+                                reasoning_summary_requested,
+                                reasoning_item_id,
+                                reasoning_output_index,
+                            ) {
+                                (true, Some(item_id), Some(output_index)) => {
+                                    let mut event = reasoning_summary_event(
+                                        "response.reasoning_summary_text.delta",
+                                        &item_id,
+                                        output_index,
+                                        None,
+                                        None,
+                                    );
+                                    event.delta = Some(reasoning.to_string());
+                                    event
+                                }
+                                (_, item_id, output_index) => StreamEvent {
+                                    type_: "response.reasoning_text.delta".to_string(),
+                                    response: None,
+                                    event_id: None,
+                                    response_id: None,
+                                    item_id,
+                                    output_index,
+                                    content_index: Some(0),
+                                    delta: Some(reasoning.to_string()),
+                                    text: None,
+                                    item: None,
+                                    sequence_number: None,
+                                    call_id: None,
+                                    name: None,
+                                    arguments: None,
+                                    error: None,
+                                    annotations: None,
+                                    usage: None,
+                                    part: None,
+                                    summary_index: None,
+                                },
                             };
 
                             dispatch_event(
@@ -967,44 +2421,157 @@ pub async fn create_response(
                             )
                             .await;
                         }
+                        // At the cap the reasoning item is finished; text carries on
+                        if reasoning_capped && reasoning_open {
+                            reasoning_open = false;
+                            if let Some(segment) = reasoning_segments.last() {
+                                emit_reasoning_done_event(
+                                    &tx,
+                                    &mut sequencer,
+                                    &response_id,
+                                    &request_id,
+                                    segment,
+                                    reasoning_summary_requested,
+                                )
+                                .await;
+                            }
+                        }
                     }
 
                     // Handle regular text content
                     if let Some(content) = &delta.content {
-                        if let Some(content_text) = extract_text_delta(content) {
+                        if let Some(mut content_text) = extract_text_delta(content) {
                             if !content_text.is_empty() {
+                                // Visible text closes any open reasoning item first
+                                if reasoning_open {
+                                    reasoning_open = false;
+                                    if let Some(segment) = reasoning_segments.last() {
+                                        emit_reasoning_done_event(
+                                            &tx,
+                                            &mut sequencer,
+                                            &response_id,
+                                            &request_id,
+                                            segment,
+                                            reasoning_summary_requested,
+                                        )
+                                        .await;
+                                    }
+                                }
+
+                                // Stop sequences are searched in the held-back tail plus this
+                                // delta; a tail that may begin one waits for the next delta
+                                let mut stop_hit = false;
+                                if !req_stop.is_empty() {
+                                    let mut window = std::mem::take(&mut stop_held);
+                                    window.push_str(&content_text);
+                                    if let Some(stop_at) = find_stop(&window, &req_stop) {
+                                        window.truncate(stop_at);
+                                        stop_hit = true;
+                                    } else {
+                                        let held = stop_prefix_len(&window, &req_stop);
+                                        stop_held = window.split_off(window.len() - held);
+                                    }
+                                    content_text = window;
+                                }
                                 accumulated_text.push_str(&content_text);
 
+                                // A stop sequence ends the text, including any XML still being
+                                // buffered: the unsent pre-stop part goes out as plain text
+                                if stop_hit {
+                                    let cut = accumulated_text.len();
+                                    if xml_buffering {
+                                        content_text =
+                                            accumulated_text[xml_buffer_start..].to_string();
+                                        xml_buffering = false;
+                                        ProxyMetrics::incr(&app.metrics.xml_flushed_as_text);
+                                    }
+                                    log::debug!("🛑 Stop sequence reached at byte {}", cut);
+                                    final_status = "completed";
+                                    done = true;
+                                }
+
                                 // Check if we should start XML buffering
-                                if !xml_buffering && content_text.contains("<function=") {
+                                if !stop_hit
+                                    && !xml_buffering
+                                    && content_text.contains("<function=")
+                                {
                                     xml_buffering = true;
+                                    xml_buffer_start = accumulated_text.len() - content_text.len();
+                                    ProxyMetrics::incr(&app.metrics.xml_buffering_started);
                                     log::debug!(
                                         "🔍 Started XML buffering - detected <function= tag"
                                     );
                                 }
 
+                                // Or a JSON tool-call structure; an opening cut off at the
+                                // end of this delta is held back until the next one
+                                if !stop_hit && !xml_buffering {
+                                    let unsent = accumulated_text.len() - content_text.len();
+                                    let scan_from =
+                                        json_scan_from.clamp(unsent, accumulated_text.len());
+                                    if let Some(offset) =
+                                        json_tool_call_start(&accumulated_text[scan_from..])
+                                    {
+                                        xml_buffering = true;
+                                        json_buffering = true;
+                                        json_start = scan_from + offset;
+                                        xml_buffer_start = json_start;
+                                        ProxyMetrics::incr(&app.metrics.xml_buffering_started);
+                                        log::debug!(
+                                            "🔍 Started buffering possible JSON tool calls"
+                                        );
+                                    }
+                                }
+
                                 // If buffering, check if we have the closing tag
                                 if xml_buffering {
-                                    // Check if we now have a complete XML tool call (has closing tag)
-                                    if accumulated_text.contains("</tool_call>")
+                                    // Text of this delta ahead of a buffer it opened still streams
+                                    let chunk_start = accumulated_text.len() - content_text.len();
+                                    let pre_buffer = accumulated_text
+                                        .get(chunk_start..xml_buffer_start)
+                                        .unwrap_or_default()
+                                        .to_string();
+
+                                    // Complete markup yields the cleaned text and its calls
+                                    let extracted = if json_buffering {
+                                        match scan_json_tool_calls(&accumulated_text, json_start) {
+                                            JsonToolScan::Incomplete => None,
+                                            JsonToolScan::Calls(cleaned, calls) => {
+                                                Some((cleaned, calls))
+                                            }
+                                            JsonToolScan::NotToolCalls => {
+                                                Some((String::new(), Vec::new()))
+                                            }
+                                        }
+                                    } else if accumulated_text.contains("</tool_call>")
                                         || accumulated_text.contains("</function>")
                                     {
                                         log::debug!(
                                             "🔍 Found closing tag - extracting XML tool calls"
                                         );
+                                        Some(extract_xml_tool_calls(&accumulated_text))
+                                    } else {
+                                        None
+                                    };
 
-                                        // Extract and convert XML to function calls
-                                        let (cleaned, xml_calls) =
-                                            extract_xml_tool_calls(&accumulated_text);
-
+                                    if let Some((cleaned, xml_calls)) = extracted {
                                         if !xml_calls.is_empty() {
+                                            let format =
+                                                if json_buffering { "JSON" } else { "XML" };
                                             log::warn!(
-                                                "⚠️ Converted {} XML-style tool call(s) to proper function calls",
-                                                xml_calls.len()
+                                                "⚠️ Converted {} {}-style tool call(s) to proper function calls",
+                                                xml_calls.len(),
+                                                format
                                             );
 
                                             // Replace accumulated text with cleaned version
                                             accumulated_text = cleaned;
+                                            let counter = if json_buffering {
+                                                &app.metrics.json_conversions
+                                            } else {
+                                                &app.metrics.xml_conversions
+                                            };
+                                            ProxyMetrics::add(counter, xml_calls.len() as u64);
 
                                             // Convert each XML call to function call events
                                             for xml_call in xml_calls.into_iter() {
@@ -1028,11 +2595,15 @@ pub async fn create_response(
                                                     item_added: true,
                                                     end_emitted: false,
                                                     pending_args: String::new(),
+                                                    last_args_fragment: String::new(),
+                                                    output_index: allocate_output_index(
+                                                        &mut next_output_index,
+                                                    ),
                                                 };
+                                                let output_idx = call_state.output_index;
 
                                                 tool_calls.insert(call_idx, call_state.clone());
 
-                                                let output_idx = (call_idx + 1) as u32;
                                                 emit_tool_call_begin_events(
                                                     &tx,
                                                     &mut sequencer,
@@ -1042,6 +2613,7 @@ pub async fn create_response(
                                                     &call_id,
                                                     &xml_call.name,
                                                     output_idx,
+                                                    call_state.output_item_type(),
                                                 )
                                                 .await;
 
@@ -1087,6 +2659,10 @@ pub async fn create_response(
                                                     name: Some(xml_call.name.clone()),
                                                     arguments: Some(xml_call.arguments.clone()),
                                                     error: None,
+                                                    annotations: None,
+                                                    usage: None,
+                                                    part: None,
+                                                    summary_index: None,
                                                 };
 
                                                 dispatch_event(
@@ -1110,8 +2686,10 @@ pub async fn create_response(
                                                     text: None,
                                                     item: Some(OutputItem {
                                                         id: item_id.clone(),
-                                                        object: REALTIME_ITEM_OBJECT.to_string(),
-                                                        type_: "function_call".to_string(),
+                                                        object: None,
+                                                        type_: call_state
+                                                            .output_item_type()
+                                                            .to_string(),
                                                         status: "completed".to_string(),
                                                         role: None,
                                                         content: None,
@@ -1119,12 +2697,18 @@ pub async fn create_response(
                                                         name: Some(xml_call.name.clone()),
                                                         arguments: Some(xml_call.arguments.clone()),
                                                         output: None,
+                                                        summary: None,
+                                                        encrypted_content: None,
                                                     }),
                                                     sequence_number: None,
                                                     call_id: Some(call_id.clone()),
                                                     name: None,
                                                     arguments: None,
                                                     error: None,
+                                                    annotations: None,
+                                                    usage: None,
+                                                    part: None,
+                                                    summary_index: None,
                                                 };
 
                                                 dispatch_event(
@@ -1148,25 +2732,51 @@ pub async fn create_response(
 
                                             // Done buffering
                                             xml_buffering = false;
-
-                                            // Skip emitting the XML as text since we converted it
-                                            continue;
+                                            json_buffering = false;
+                                            json_scan_from = accumulated_text.len();
+
+                                            // The converted markup itself is never emitted as text
+                                            content_text = pre_buffer;
+                                        } else if json_buffering {
+                                            // Only an ordinary JSON block - release it as text
+                                            log::debug!("🔍 Buffered JSON holds no tool calls - emitting as text");
+                                            xml_buffering = false;
+                                            json_buffering = false;
+                                            json_scan_from = accumulated_text.len();
+                                            content_text = accumulated_text
+                                                [xml_buffer_start.min(chunk_start)..]
+                                                .to_string();
                                         } else {
                                             // Had closing tag but parser failed - fall through to emit
                                             log::warn!("Found closing tag but XML parser failed - emitting as text");
                                             xml_buffering = false;
+                                            ProxyMetrics::incr(&app.metrics.xml_parse_failures);
+                                            // Emit everything held back while buffering
+                                            content_text = accumulated_text
+                                                [xml_buffer_start.min(chunk_start)..]
+                                                .to_string();
                                         }
                                     } else {
-                                        // No closing tag yet - keep buffering, don't emit anything
+                                        // No closing tag yet - keep buffering, emit only what precedes it
                                         log::debug!("🔍 Buffering XML ({} bytes) - waiting for </tool_call>", accumulated_text.len());
-                                        continue;
+                                        content_text = pre_buffer;
                                     }
                                 }
 
                                 // Legacy path removed - buffering path above handles all XML conversion
 
-                                // Only emit text delta if we have actual text content AND we're not buffering XML
-                                if !content_text.is_empty() && !xml_buffering {
+                                // Only emit text delta if we have actual text content
+                                if !content_text.is_empty() {
+                                    let message_output_index = open_message_item(
+                                        &tx,
+                                        &mut sequencer,
+                                        &response_id,
+                                        &request_id,
+                                        &message_id,
+                                        &mut message_index,
+                                        &mut next_output_index,
+                                    )
+                                    .await;
                                     let delta_str = content_text.clone();
                                     let delta_event = StreamEvent {
                                         type_: "response.output_text.delta".to_string(),
@@ -1174,7 +2784,7 @@ pub async fn create_response(
                                         event_id: None,
                                         response_id: None,
                                         item_id: Some(message_id.clone()),
-                                        output_index: Some(0),
+                                        output_index: Some(message_output_index),
                                         content_index: Some(0),
                                         delta: Some(delta_str.clone()),
                                         text: None,
@@ -1184,6 +2794,10 @@ pub async fn create_response(
                                         name: None,
                                         arguments: None,
                                         error: None,
+                                        annotations: None,
+                                        usage: None,
+                                        part: None,
+                                        summary_index: None,
                                     };
 
                                     dispatch_event(
@@ -1200,126 +2814,138 @@ pub async fn create_response(
                             log::debug!("⚠️ Unhandled content delta shape: {:?}", content);
                         }
                     }
+                }
 
-                    // Handle tool_calls (function calling)
-                    if let Some(tool_calls_delta) = &delta.tool_calls {
-                        for tc in tool_calls_delta {
-                            let call_state = tool_calls.entry(tc.index).or_insert_with(|| {
-                                let fallback_id = format!("call_{}_{}", request_id, tc.index);
-                                let call_id = tc.id.clone().unwrap_or_else(|| fallback_id.clone());
-                                ToolCallState {
-                                    call_id: call_id.clone(),
-                                    item_id: call_id,
-                                    type_: tc
-                                        .type_
-                                        .clone()
-                                        .unwrap_or_else(|| "function".to_string()),
-                                    name: None,
-                                    arguments: String::new(),
-                                    item_added: false,
-                                    end_emitted: false,
-                                    pending_args: String::new(),
-                                }
-                            });
-
-                            // Update ID if provided
-                            if let Some(ref id) = tc.id {
-                                call_state.call_id = id.clone();
-                                call_state.item_id = id.clone();
+                // Handle tool_calls (function calling)
+                if let Some(tool_calls_delta) = choice
+                    .delta
+                    .as_ref()
+                    .and_then(|d| d.tool_calls.as_ref())
+                    .or(message_tool_calls.as_ref())
+                {
+                    for tc in tool_calls_delta {
+                        let call_state = tool_calls.entry(tc.index).or_insert_with(|| {
+                            let fallback_id = format!("call_{}_{}", request_id, tc.index);
+                            let call_id = tc.id.clone().unwrap_or_else(|| fallback_id.clone());
+                            ToolCallState {
+                                call_id: call_id.clone(),
+                                item_id: call_id,
+                                type_: tc.type_.clone().unwrap_or_else(|| "function".to_string()),
+                                name: None,
+                                arguments: String::new(),
+                                item_added: false,
+                                end_emitted: false,
+                                pending_args: String::new(),
+                                last_args_fragment: String::new(),
+                                output_index: allocate_output_index(&mut next_output_index),
                             }
+                        });
 
-                            // Update type if provided
-                            if let Some(ref type_) = tc.type_ {
-                                call_state.type_ = type_.clone();
-                            }
+                        // Update ID if provided
+                        if let Some(ref id) = tc.id {
+                            call_state.call_id = id.clone();
+                            call_state.item_id = id.clone();
+                        }
+
+                        // Update type if provided
+                        if let Some(ref type_) = tc.type_ {
+                            call_state.type_ = type_.clone();
+                        }
+
+                        // Handle function call delta
+                        if let Some(ref func) = tc.function {
+                            // Update name if provided
+                            if let Some(ref name) = func.name {
+                                call_state.name = Some(name.clone());
 
-                            // Handle function call delta
-                            if let Some(ref func) = tc.function {
-                                // Update name if provided
-                                if let Some(ref name) = func.name {
-                                    call_state.name = Some(name.clone());
+                                // Send output_item.added when we first get the function name
+                                if !call_state.item_added {
+                                    call_state.item_added = true;
 
-                                    // Send output_item.added when we first get the function name
-                                    if !call_state.item_added {
-                                        call_state.item_added = true;
+                                    let output_idx = call_state.output_index;
+
+                                    let function_name =
+                                        call_state.name.as_deref().unwrap_or("function_call");
+                                    log::info!(
+                                        "🔧 Tool call started: {} (index {})",
+                                        function_name,
+                                        tc.index
+                                    );
 
-                                        let output_idx = tc.index as u32 + 1; // +1 because message is at index 0
+                                    emit_tool_call_begin_events(
+                                        &tx,
+                                        &mut sequencer,
+                                        &response_id,
+                                        &request_id,
+                                        &call_state.item_id,
+                                        &call_state.call_id,
+                                        function_name,
+                                        output_idx,
+                                        call_state.output_item_type(),
+                                    )
+                                    .await;
 
-                                        let function_name =
-                                            call_state.name.as_deref().unwrap_or("function_call");
+                                    // If we buffered arguments before the name arrived, replay them now
+                                    if !call_state.pending_args.is_empty() {
                                         log::info!(
-                                            "🔧 Tool call started: {} (index {})",
-                                            function_name,
-                                            tc.index
+                                            "🔧 Replaying {} buffered argument bytes for {}",
+                                            call_state.pending_args.len(),
+                                            function_name
                                         );
 
-                                        emit_tool_call_begin_events(
+                                        emit_tool_call_delta_events(
                                             &tx,
                                             &mut sequencer,
                                             &response_id,
                                             &request_id,
                                             &call_state.item_id,
                                             &call_state.call_id,
-                                            function_name,
                                             output_idx,
+                                            &call_state.pending_args,
                                         )
                                         .await;
 
-                                        // If we buffered arguments before the name arrived, replay them now
-                                        if !call_state.pending_args.is_empty() {
-                                            log::info!(
-                                                "🔧 Replaying {} buffered argument bytes for {}",
-                                                call_state.pending_args.len(),
-                                                function_name
-                                            );
-
-                                            emit_tool_call_delta_events(
-                                                &tx,
-                                                &mut sequencer,
-                                                &response_id,
-                                                &request_id,
-                                                &call_state.item_id,
-                                                &call_state.call_id,
-                                                output_idx,
-                                                &call_state.pending_args,
-                                            )
-                                            .await;
-
-                                            // Move pending to arguments
-                                            call_state.arguments.push_str(&call_state.pending_args);
-                                            call_state.pending_args.clear();
-                                        }
+                                        // Move pending to arguments
+                                        call_state.arguments.push_str(&call_state.pending_args);
+                                        call_state.pending_args.clear();
                                     }
                                 }
+                            }
 
-                                // Update arguments if provided
-                                if let Some(ref args) = func.arguments {
-                                    if call_state.item_added {
-                                        // Name already sent, emit delta immediately
-                                        call_state.arguments.push_str(args);
+                            // Update arguments if provided. Client stop sequences never
+                            // apply here: they only govern visible text.
+                            if let Some(ref args) = func.arguments {
+                                if call_state.is_resent_args_fragment(args) {
+                                    log::warn!(
+                                        "⚠️ Skipping duplicated argument fragment for tool index {} ({} bytes)",
+                                        tc.index,
+                                        args.len()
+                                    );
+                                } else if call_state.item_added {
+                                    // Name already sent, emit delta immediately
+                                    call_state.arguments.push_str(args);
 
-                                        let output_idx = tc.index as u32 + 1;
+                                    let output_idx = call_state.output_index;
 
-                                        emit_tool_call_delta_events(
-                                            &tx,
-                                            &mut sequencer,
-                                            &response_id,
-                                            &request_id,
-                                            &call_state.item_id,
-                                            &call_state.call_id,
-                                            output_idx,
-                                            args,
-                                        )
-                                        .await;
-                                    } else {
-                                        // Name not yet received, buffer the arguments
-                                        call_state.pending_args.push_str(args);
-                                        log::debug!(
-                                            "🔍 Buffering {} argument bytes for tool index {} (name not yet received)",
-                                            args.len(),
-                                            tc.index
-                                        );
-                                    }
+                                    emit_tool_call_delta_events(
+                                        &tx,
+                                        &mut sequencer,
+                                        &response_id,
+                                        &request_id,
+                                        &call_state.item_id,
+                                        &call_state.call_id,
+                                        output_idx,
+                                        args,
+                                    )
+                                    .await;
+                                } else {
+                                    // Name not yet received, buffer the arguments
+                                    call_state.pending_args.push_str(args);
+                                    log::debug!(
+                                        "🔍 Buffering {} argument bytes for tool index {} (name not yet received)",
+                                        args.len(),
+                                        tc.index
+                                    );
                                 }
                             }
                         }
@@ -1327,55 +2953,150 @@ pub async fn create_response(
                 }
             }
 
-            if done {
+            // Nothing user-visible was streamed yet, so a fresh backend stream
+            // can carry on under the same response
+            if restart_stream {
+                restart_stream = false;
+                early_retries_left -= 1;
+                let restarted = match &early_retry_body {
+                    Some(body) => {
+                        restart_backend_stream(&app, &task_client_key, body, &model_for_response)
+                            .await
+                    }
+                    None => None,
+                };
+                let Some(stream) = restarted else {
+                    final_status = "failed";
+                    retry_failed = true;
+                    break;
+                };
+                bytes_stream = stream;
+                sse_parser =
+                    SseEventParser::new().with_max_event_bytes(app.config.max_sse_event_bytes());
+                done = false;
+                continue;
+            }
+
+            // An empty stream may still fall back once the backend closes it
+            if done && !(usable_chunks == 0 && fallback_body.is_some()) {
                 break;
             }
         }
 
-        // Send reasoning.done event if reasoning was emitted
-        if reasoning_started {
-            let reasoning_done_event = StreamEvent {
-                type_: "response.reasoning_text.done".to_string(),
+        if client_cancelled {
+            log::info!("🔌 Client disconnected mid-stream, abandoning backend response");
+            ProxyMetrics::incr(&app.metrics.client_cancelled);
+            if let Ok(elapsed) = request_start.elapsed() {
+                let total_ms = elapsed.as_millis() as u64;
+                let timing = TimingBreakdown::new(prepare_ms, first_chunk_ms, total_ms);
+                timing.record(&app.metrics);
+                log::info!(target: "metrics",
+                    "request_completed: model={}, duration_ms={}, prepare_ms={}, backend_ms={}, stream_ms={}, status=client_cancelled{}",
+                    backend_model_for_metrics, total_ms, timing.prepare_ms, timing.backend_ms,
+                    timing.stream_ms, metadata_tags
+                );
+            }
+            return;
+        }
+
+        // Text held back for a stop sequence, or a buffer never closed by the
+        // model, is plain text after all
+        let unsent_from = if xml_buffering {
+            log::warn!("⚠️ Stream ended mid XML buffer - emitting buffered text as-is");
+            ProxyMetrics::incr(&app.metrics.xml_flushed_as_text);
+            xml_buffer_start
+        } else {
+            accumulated_text.len()
+        };
+        accumulated_text.push_str(&stop_held);
+        if unsent_from < accumulated_text.len() {
+            let message_output_index = open_message_item(
+                &tx,
+                &mut sequencer,
+                &response_id,
+                &request_id,
+                &message_id,
+                &mut message_index,
+                &mut next_output_index,
+            )
+            .await;
+            let delta_event = StreamEvent {
+                type_: "response.output_text.delta".to_string(),
                 response: None,
                 event_id: None,
                 response_id: None,
-                item_id: reasoning_item_id.clone(),
-                output_index: Some(0),
+                item_id: Some(message_id.clone()),
+                output_index: Some(message_output_index),
                 content_index: Some(0),
-                delta: None,
-                text: Some(accumulated_reasoning.clone()),
+                delta: Some(accumulated_text[unsent_from..].to_string()),
+                text: None,
                 item: None,
                 sequence_number: None,
                 call_id: None,
                 name: None,
                 arguments: None,
                 error: None,
+                annotations: None,
+                usage: None,
+                part: None,
+                summary_index: None,
             };
+            dispatch_event(&tx, &mut sequencer, &response_id, &request_id, delta_event).await;
+        }
 
-            dispatch_event(
-                &tx,
-                &mut sequencer,
-                &response_id,
-                &request_id,
-                reasoning_done_event,
-            )
-            .await;
-
+        // Close the reasoning item still open at end of stream
+        if reasoning_open {
+            if let Some(segment) = reasoning_segments.last() {
+                emit_reasoning_done_event(
+                    &tx,
+                    &mut sequencer,
+                    &response_id,
+                    &request_id,
+                    segment,
+                    reasoning_summary_requested,
+                )
+                .await;
+            }
+        }
+        if !reasoning_segments.is_empty() {
+            log::info!(
+                "🧠 Reasoning content complete ({} chars in {} item(s))",
+                accumulated_reasoning.len(),
+                reasoning_segments.len()
+            );
+        }
+        if !suppressed_reasoning.is_empty() {
             log::info!(
-                "🧠 Reasoning content complete ({} chars)",
-                accumulated_reasoning.len()
+                "🙈 Suppressed {} chars of reasoning from '{}' (no `reasoning` feature)",
+                suppressed_reasoning.len(),
+                backend_model_for_metrics
             );
+            log::debug!("🙈 Suppressed reasoning: {}", suppressed_reasoning);
         }
 
-        // Send output_text.done event only if we have text content
-        if !accumulated_text.is_empty() {
+        // Drop trailing blank lines some backends append after the real content
+        if app.config.trim_trailing_whitespace {
+            let trimmed_len = accumulated_text.trim_end().len();
+            if trimmed_len < accumulated_text.len() {
+                log::debug!(
+                    "✂️  Trimming {} trailing whitespace bytes from final text",
+                    accumulated_text.len() - trimmed_len
+                );
+                accumulated_text.truncate(trimmed_len);
+            }
+        }
+
+        // Close the message item, even when no text arrived, so every added item
+        // and content part gets its done event; a deferred item never announced
+        // stays out of the response entirely
+        if let Some(message_output_index) = message_index {
             let text_done_event = StreamEvent {
                 type_: "response.output_text.done".to_string(),
                 response: None,
                 event_id: None,
                 response_id: None,
                 item_id: Some(message_id.clone()),
-                output_index: Some(0),
+                output_index: Some(message_output_index),
                 content_index: Some(0),
                 delta: None,
                 text: Some(accumulated_text.clone()),
@@ -1385,6 +3106,10 @@ pub async fn create_response(
                 name: None,
                 arguments: None,
                 error: None,
+                annotations: Some(accumulated_annotations.clone()),
+                usage: None,
+                part: None,
+                summary_index: None,
             };
 
             dispatch_event(
@@ -1403,7 +3128,7 @@ pub async fn create_response(
                 event_id: None,
                 response_id: None,
                 item_id: Some(message_id.clone()),
-                output_index: Some(0),
+                output_index: Some(message_output_index),
                 content_index: Some(0),
                 delta: None,
                 text: None,
@@ -1413,6 +3138,13 @@ pub async fn create_response(
                 name: None,
                 arguments: None,
                 error: None,
+                annotations: Some(accumulated_annotations.clone()),
+                usage: None,
+                part: Some(OutputContent::OutputText {
+                    text: accumulated_text.clone(),
+                    annotations: accumulated_annotations.clone(),
+                }),
+                summary_index: None,
             };
 
             dispatch_event(
@@ -1423,40 +3155,44 @@ pub async fn create_response(
                 content_done_event,
             )
             .await;
-        }
 
-        // Send output_item.done event for the message (only if we have text)
-        if !accumulated_text.is_empty() {
+            // Send output_item.done event for the message
             let item_done_event = StreamEvent {
                 type_: "response.output_item.done".to_string(),
                 response: None,
                 event_id: None,
                 response_id: None,
                 item_id: Some(message_id.clone()),
-                output_index: Some(0),
+                output_index: Some(message_output_index),
                 content_index: None,
                 delta: None,
                 text: None,
                 item: Some(OutputItem {
                     id: message_id.clone(),
-                    object: REALTIME_ITEM_OBJECT.to_string(),
+                    object: None,
                     type_: "message".to_string(),
                     status: "completed".to_string(),
                     role: Some("assistant".to_string()),
                     content: Some(vec![OutputContent::OutputText {
                         text: accumulated_text.clone(),
-                        annotations: vec![],
+                        annotations: accumulated_annotations.clone(),
                     }]),
                     call_id: None,
                     name: None,
                     arguments: None,
                     output: None,
+                    summary: None,
+                    encrypted_content: None,
                 }),
                 sequence_number: None,
                 call_id: None,
                 name: None,
                 arguments: None,
                 error: None,
+                annotations: None,
+                usage: None,
+                part: None,
+                summary_index: None,
             };
 
             dispatch_event(
@@ -1469,6 +3205,28 @@ pub async fn create_response(
             .await;
         }
 
+        // Arguments still held for a name that never came belong to the call
+        for call_state in tool_calls
+            .values_mut()
+            .filter(|c| !c.pending_args.is_empty())
+        {
+            log::warn!(
+                "⚠️ Tool call {} ended before its name arrived; keeping {} buffered argument bytes",
+                call_state.call_id,
+                call_state.pending_args.len()
+            );
+            let pending = std::mem::take(&mut call_state.pending_args);
+            call_state.arguments.push_str(&pending);
+        }
+
+        if app.config.compact_tool_arguments {
+            for call_state in tool_calls.values_mut().filter(|c| !c.end_emitted) {
+                if let Some(compact) = compact_json(&call_state.arguments) {
+                    call_state.arguments = compact;
+                }
+            }
+        }
+
         // Collect and sort tool calls for processing
         let mut sorted_calls: Vec<_> = tool_calls.into_iter().collect();
         sorted_calls.sort_by_key(|(idx, _)| *idx);
@@ -1477,9 +3235,8 @@ pub async fn create_response(
         let sorted_calls_clone = sorted_calls.clone();
 
         // Send function_call_arguments.done and output_item.done for each tool call
-        // Tool calls always start at index 1 (message is at index 0)
-        for (idx, call_state) in sorted_calls {
-            let output_idx = idx as u32 + 1;
+        for (_idx, call_state) in sorted_calls {
+            let output_idx = call_state.output_index;
             let function_name = call_state
                 .name
                 .clone()
@@ -1519,6 +3276,10 @@ pub async fn create_response(
                 name: Some(function_name.clone()),
                 arguments: Some(call_state.arguments.clone()),
                 error: None,
+                annotations: None,
+                usage: None,
+                part: None,
+                summary_index: None,
             };
 
             dispatch_event(
@@ -1549,8 +3310,8 @@ pub async fn create_response(
                 text: None,
                 item: Some(OutputItem {
                     id: call_state.item_id.clone(),
-                    object: REALTIME_ITEM_OBJECT.to_string(),
-                    type_: "function_call".to_string(),
+                    object: None,
+                    type_: call_state.output_item_type().to_string(),
                     status: "completed".to_string(),
                     role: None,
                     content: None,
@@ -1558,12 +3319,18 @@ pub async fn create_response(
                     name: Some(function_name.clone()),
                     arguments: Some(call_state.arguments.clone()),
                     output: None,
+                    summary: None,
+                    encrypted_content: None,
                 }),
                 sequence_number: None,
                 call_id: Some(call_state.call_id.clone()),
                 name: None,
                 arguments: None,
                 error: None,
+                annotations: None,
+                usage: None,
+                part: None,
+                summary_index: None,
             };
 
             dispatch_event(
@@ -1576,73 +3343,154 @@ pub async fn create_response(
             .await;
         }
 
+        // Catch patches the agent would fail to apply before the client runs them
+        if app.config.apply_patch_validation != ApplyPatchValidation::Off {
+            for (_idx, call_state) in &sorted_calls_clone {
+                if call_state.name.as_deref() != Some("apply_patch") {
+                    continue;
+                }
+                let Some(problem) = apply_patch_problem(&call_state.arguments) else {
+                    continue;
+                };
+                log::warn!(
+                    "⚠️  Malformed apply_patch call {}: {}",
+                    call_state.call_id,
+                    problem
+                );
+                ProxyMetrics::incr(&app.metrics.apply_patch_malformed);
+                if app.config.apply_patch_validation == ApplyPatchValidation::Reject {
+                    final_status = "failed";
+                    response_error = Some(ResponseError {
+                        code: "malformed_apply_patch".to_string(),
+                        message: format!("apply_patch call {}: {}", call_state.call_id, problem),
+                    });
+                }
+            }
+        }
+
         // Send response.completed/done events
         let mut final_reasoning_state = req_reasoning_state.clone();
-        if final_reasoning_state.is_none() && reasoning_started {
+        if final_reasoning_state.is_none() && !reasoning_segments.is_empty() {
             final_reasoning_state = Some(ResponseReasoningState::default());
         }
 
-        let mut output_items = vec![];
-
-        // Add reasoning item if present
-        if reasoning_started && !accumulated_reasoning.is_empty() {
-            output_items.push(OutputItem {
-                id: reasoning_item_id.unwrap_or_else(|| reasoning_id_seed.clone()),
-                object: REALTIME_ITEM_OBJECT.to_string(),
-                type_: "reasoning".to_string(),
-                status: "completed".to_string(),
-                role: Some("assistant".to_string()),
-                content: Some(vec![OutputContent::Reasoning {
-                    text: accumulated_reasoning.clone(),
-                }]),
-                call_id: None,
-                name: None,
-                arguments: None,
-                output: None,
-            });
+        // (output_index, item) pairs, ordered to match the streamed indices below
+        let mut indexed_items = vec![];
+
+        // Add reasoning items if present (one per contiguous reasoning run)
+        for segment in reasoning_segments.iter().filter(|seg| !seg.text.is_empty()) {
+            indexed_items.push((
+                segment.output_index,
+                OutputItem {
+                    id: segment.item_id.clone(),
+                    object: None,
+                    type_: "reasoning".to_string(),
+                    status: "completed".to_string(),
+                    role: Some("assistant".to_string()),
+                    content: Some(if reasoning_summary_requested {
+                        vec![]
+                    } else {
+                        vec![OutputContent::Reasoning {
+                            text: segment.text.clone(),
+                        }]
+                    }),
+                    call_id: None,
+                    name: None,
+                    arguments: None,
+                    output: None,
+                    // The backend only streams raw reasoning, so a requested summary
+                    // carries it instead of the reasoning_text content
+                    summary: Some(if reasoning_summary_requested {
+                        vec![ReasoningSummaryPart::SummaryText {
+                            text: segment.text.clone(),
+                        }]
+                    } else {
+                        vec![]
+                    }),
+                    encrypted_content: None,
+                },
+            ));
         }
 
-        // Add text message item (always include at index 0 for consistent indices)
-        output_items.push(OutputItem {
-            id: message_id.clone(),
-            object: REALTIME_ITEM_OBJECT.to_string(),
-            type_: "message".to_string(),
-            status: "completed".to_string(),
-            role: Some("assistant".to_string()),
-            content: Some(vec![OutputContent::OutputText {
-                text: accumulated_text.clone(),
-                annotations: vec![],
-            }]),
-            call_id: None,
-            name: None,
-            arguments: None,
-            output: None,
-        });
+        // Add the text message item at the index it was announced under
+        if let Some(message_output_index) = message_index {
+            indexed_items.push((
+                message_output_index,
+                OutputItem {
+                    id: message_id.clone(),
+                    object: None,
+                    type_: "message".to_string(),
+                    status: "completed".to_string(),
+                    role: Some("assistant".to_string()),
+                    content: Some(vec![OutputContent::OutputText {
+                        text: accumulated_text.clone(),
+                        annotations: accumulated_annotations.clone(),
+                    }]),
+                    call_id: None,
+                    name: None,
+                    arguments: None,
+                    output: None,
+                    summary: None,
+                    encrypted_content: None,
+                },
+            ));
+        }
 
         // Reconstruct the sorted tool calls for the final response
         let mut final_tool_calls: Vec<_> = sorted_calls_clone
             .iter()
-            .map(|(_idx, call_state)| OutputItem {
-                id: call_state.item_id.clone(),
-                object: REALTIME_ITEM_OBJECT.to_string(),
-                type_: "function_call".to_string(),
-                status: "completed".to_string(),
-                role: None,
-                content: None,
-                call_id: Some(call_state.call_id.clone()),
-                name: call_state.name.clone(),
-                arguments: Some(call_state.arguments.clone()),
-                output: None,
+            .map(|(_idx, call_state)| {
+                (
+                    call_state.output_index,
+                    OutputItem {
+                        id: call_state.item_id.clone(),
+                        object: None,
+                        type_: call_state.output_item_type().to_string(),
+                        status: "completed".to_string(),
+                        role: None,
+                        content: None,
+                        call_id: Some(call_state.call_id.clone()),
+                        name: call_state.name.clone(),
+                        arguments: Some(call_state.arguments.clone()),
+                        output: None,
+                        summary: None,
+                        encrypted_content: None,
+                    },
+                )
             })
             .collect();
 
         // Add all tool calls to output
-        output_items.append(&mut final_tool_calls);
+        indexed_items.append(&mut final_tool_calls);
+        indexed_items.sort_by_key(|(index, _)| *index);
+        let output_items: Vec<OutputItem> =
+            indexed_items.into_iter().map(|(_, item)| item).collect();
+
+        // A client that missed events did not get the whole response
+        let slow_client = sequencer.dropped_events > 0 && final_status != "failed";
+        if sequencer.dropped_events > 0 {
+            log::warn!(
+                "🐢 Dropped {} events for a slow client",
+                sequencer.dropped_events
+            );
+            ProxyMetrics::add(
+                &app.metrics.slow_client_dropped_events,
+                sequencer.dropped_events,
+            );
+        }
+        if slow_client {
+            final_status = "incomplete";
+        }
 
         // Determine incomplete_details if status is incomplete
         let incomplete_details = if final_status == "incomplete" {
+            let reason = if slow_client {
+                "slow_client"
+            } else {
+                "max_output_tokens"
+            };
             Some(IncompleteDetails {
-                reason: "max_output_tokens".to_string(),
+                reason: reason.to_string(),
             })
         } else {
             None
@@ -1653,23 +3501,16 @@ pub async fn create_response(
             object: "response".to_string(),
             created_at,
             status: final_status.to_string(),
-            error: None,
+            error: response_error,
             incomplete_details,
             model: Some(model_for_response.to_string()),
             output: output_items,
-            usage: Some(Usage {
-                input_tokens: total_input_tokens,
-                output_tokens: total_output_tokens,
-                total_tokens: total_input_tokens + total_output_tokens,
-                input_tokens_details: Some(TokenDetails {
-                    cached_tokens: 0,
-                    reasoning_tokens: 0,
-                }),
-                output_tokens_details: Some(TokenDetails {
-                    cached_tokens: 0,
-                    reasoning_tokens: 0,
-                }),
-            }),
+            // Final usage is authoritative over any interim snapshots
+            usage: Some(build_usage(
+                total_input_tokens,
+                total_output_tokens,
+                total_reasoning_tokens,
+            )),
             metadata: req_metadata.clone(),
             // Echo back request parameters
             instructions: req_instructions.clone(),
@@ -1693,10 +3534,25 @@ pub async fn create_response(
             safety_identifier: req_safety_identifier.clone(),
             prompt_cache_key: req_prompt_cache_key.clone(),
             service_tier: req_service_tier.clone(),
+            proxy_debug: debug_requested.then(|| ProxyDebugInfo {
+                backend_chunk_count: backend_chunk_num,
+                ttft_ms: first_chunk_ms,
+                total_ms: elapsed_ms(request_start),
+            }),
         };
 
+        // Each terminal status has its own event type
+        if let Some((store, owner)) = &store_target {
+            store.put(owner, final_response.clone());
+        }
+
+        let terminal_type = match final_status {
+            "incomplete" => "response.incomplete",
+            "failed" => "response.failed",
+            _ => "response.completed",
+        };
         let completed_event = StreamEvent {
-            type_: "response.completed".to_string(),
+            type_: terminal_type.to_string(),
             event_id: None,
             response_id: None,
             response: Some(final_response.clone()),
@@ -1711,6 +3567,10 @@ pub async fn create_response(
             name: None,
             arguments: None,
             error: None,
+            annotations: None,
+            usage: None,
+            part: None,
+            summary_index: None,
         };
 
         dispatch_event(
@@ -1738,6 +3598,10 @@ pub async fn create_response(
             name: None,
             arguments: None,
             error: None,
+            annotations: None,
+            usage: None,
+            part: None,
+            summary_index: None,
         };
 
         dispatch_event(&tx, &mut sequencer, &response_id, &request_id, done_event).await;
@@ -1745,28 +3609,157 @@ pub async fn create_response(
         log::debug!("🏁 Streaming task completed");
 
         // Record circuit breaker success
-        let cb_clone = app.circuit_breaker.clone();
-        tokio::spawn(async move {
-            cb_clone.write().await.record_success();
-        });
+        if !retry_failed {
+            let cb_clone = app.circuit_breaker.clone();
+            spawn_with_request_id(async move {
+                cb_clone.write().await.record_success();
+            });
+        }
 
         // Log metrics
         if let Ok(elapsed) = request_start.elapsed() {
+            let total_ms = elapsed.as_millis() as u64;
+            let timing = TimingBreakdown::new(prepare_ms, first_chunk_ms, total_ms);
+            timing.record(&app.metrics);
             log::info!(target: "metrics",
-                "request_completed: model={}, duration_ms={}, status={}",
-                backend_model_for_metrics, elapsed.as_millis(), final_status
+                "request_completed: model={}, duration_ms={}, prepare_ms={}, backend_ms={}, stream_ms={}, status={}{}",
+                backend_model_for_metrics, total_ms, timing.prepare_ms, timing.backend_ms,
+                timing.stream_ms, final_status, metadata_tags
             );
         }
-    });
+    }));
+
+    Ok(event_stream_response(
+        &stream_config,
+        version,
+        stream_format,
+        rx,
+        Some(connection_guard),
+    ))
+}
+
+/// Stream buffered events after `last_seq`, following the original generation
+/// until it completes or stays idle for the replay window
+async fn replay_events(
+    buffer: Arc<ReplayBuffer>,
+    fingerprint: u64,
+    mut last_seq: u32,
+    tx: tokio::sync::mpsc::Sender<EncodedEvent>,
+) {
+    let Some(notify) = buffer.notifier(fingerprint) else {
+        return;
+    };
+    loop {
+        // Register for wake-ups before reading so no event slips in between
+        let notified = notify.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        let Some(batch) = buffer.events_after(fingerprint, last_seq) else {
+            return;
+        };
+        for (sequence, json) in batch.events {
+            last_seq = sequence;
+            if tx.send(EncodedEvent { sequence, json }).await.is_err() {
+                return;
+            }
+        }
+        if batch.complete {
+            return;
+        }
+        if tokio::time::timeout(buffer.window(), notified)
+            .await
+            .is_err()
+        {
+            log::warn!("⚠️  Replay source went idle, closing resumed stream");
+            return;
+        }
+    }
+}
+
+/// Wire framing of the event stream returned by `/v1/responses`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum StreamFormat {
+    Sse,
+    /// One JSON event per line, for clients sending `Accept: application/x-ndjson`
+    Ndjson,
+}
+
+impl StreamFormat {
+    fn from_headers(headers: &HeaderMap) -> Self {
+        let wants_ndjson = headers
+            .get_all("accept")
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .any(|v| v.to_ascii_lowercase().contains("application/x-ndjson"));
+        if wants_ndjson {
+            StreamFormat::Ndjson
+        } else {
+            StreamFormat::Sse
+        }
+    }
+}
+
+/// A serialized stream event and the sequence number it was sent under
+struct EncodedEvent {
+    sequence: u32,
+    json: String,
+}
 
+/// Headers for streamed responses. Connection-level headers are only valid on
+/// HTTP/1.x; HTTP/2 and later forbid them, so they are omitted there.
+fn stream_response_headers(
+    config: &ProxyConfig,
+    version: Version,
+    format: StreamFormat,
+) -> HeaderMap {
     let mut out_headers = HeaderMap::new();
     out_headers.insert("cache-control", "no-cache".parse().unwrap());
-    out_headers.insert("connection", "keep-alive".parse().unwrap());
+    if version < Version::HTTP_2 {
+        out_headers.insert("connection", "keep-alive".parse().unwrap());
+    }
     out_headers.insert("x-accel-buffering", "no".parse().unwrap());
-    out_headers.insert("content-type", "text/event-stream".parse().unwrap());
+    let content_type = match format {
+        StreamFormat::Sse => "text/event-stream; charset=utf-8",
+        StreamFormat::Ndjson => "application/x-ndjson",
+    };
+    out_headers.insert("content-type", content_type.parse().unwrap());
+    apply_sse_header_overrides(&mut out_headers, &config.sse_headers);
+    out_headers
+}
 
-    let stream = ReceiverStream::new(rx).map(Ok::<Event, Infallible>);
-    Ok((out_headers, Sse::new(stream)))
+/// Frame the event channel for the client, holding the connection slot (if
+/// any) for the stream's lifetime
+fn event_stream_response(
+    config: &ProxyConfig,
+    version: Version,
+    format: StreamFormat,
+    rx: tokio::sync::mpsc::Receiver<EncodedEvent>,
+    guard: Option<SseConnectionGuard>,
+) -> HttpResponse {
+    let out_headers = stream_response_headers(config, version, format);
+    let events = ReceiverStream::new(rx);
+    match format {
+        StreamFormat::Sse => {
+            let stream = events.map(move |event| {
+                let _ = &guard;
+                // SSE id mirrors the sequence number so clients can send Last-Event-ID
+                Ok::<Event, Infallible>(
+                    Event::default()
+                        .id(event.sequence.to_string())
+                        .data(event.json),
+                )
+            });
+            (out_headers, Sse::new(stream)).into_response()
+        }
+        StreamFormat::Ndjson => {
+            let stream = events.map(move |event| {
+                let _ = &guard;
+                Ok::<String, Infallible>(event.json + "\n")
+            });
+            (out_headers, Body::from_stream(stream)).into_response()
+        }
+    }
 }
 
 /// Estimate size of input content to prevent memory exhaustion
@@ -1789,8 +3782,8 @@ fn estimate_input_size(input: &crate::models::ResponseInput) -> usize {
                         ResponseContent::Array(parts) => parts
                             .iter()
                             .map(|p| match p {
-                                ContentPart::InputText { text }
-                                | ContentPart::OutputText { text } => text.len(),
+                                ContentPart::InputText { text, .. }
+                                | ContentPart::OutputText { text, .. } => text.len(),
                                 ContentPart::ToolOutput { body, .. } => body.len(),
                                 ContentPart::InputImage { image_url } => image_url.url.len(),
                                 ContentPart::InputFile {
@@ -1884,100 +3877,3240 @@ fn extract_text_delta(value: &Value) -> Option<String> {
     }
 }
 
-/// Read error response body with size limit to prevent DoS
-async fn read_bounded_error(res: reqwest::Response) -> String {
-    let mut body = res.bytes_stream();
-    let mut bytes = Vec::with_capacity(4096);
-    let mut total = 0;
+/// Whether a chunk carries anything the client would see: text, reasoning or
+/// a tool call
+fn chunk_has_content(chunk: &ChatCompletionChunk) -> bool {
+    chunk.choices.iter().any(|choice| {
+        choice.message.is_some()
+            || choice.text.as_deref().is_some_and(|text| !text.is_empty())
+            || choice.delta.as_ref().is_some_and(|delta| {
+                delta.tool_calls.is_some()
+                    || delta
+                        .reasoning_content
+                        .as_deref()
+                        .is_some_and(|reasoning| !reasoning.is_empty())
+                    || delta
+                        .content
+                        .as_ref()
+                        .and_then(extract_text_delta)
+                        .is_some_and(|text| !text.is_empty())
+            })
+    })
+}
 
-    while let Some(chunk_result) = body.next().await {
-        if let Ok(chunk) = chunk_result {
-            let remaining = MAX_ERROR_BODY_SIZE.saturating_sub(total);
-            if remaining == 0 {
-                log::warn!(
-                    "⚠️  Error body exceeded {} bytes, truncating",
-                    MAX_ERROR_BODY_SIZE
-                );
-                bytes.extend_from_slice(b"... (truncated)");
-                break;
-            }
-            let to_take = chunk.len().min(remaining);
-            bytes.extend_from_slice(&chunk[..to_take]);
-            total += to_take;
+/// Send the backend request again after its stream failed before any content
+async fn restart_backend_stream(
+    app: &App,
+    client_key: &str,
+    body: &Value,
+    model: &str,
+) -> Option<ByteStream> {
+    log::warn!("🔁 Backend stream failed before any content - retrying the request");
+    let mut body = body.clone();
+    body["model"] = Value::String(model.to_string());
+    match resolve_deferred_backend(app, client_key, &body, model).await {
+        Ok((res, _)) => Some(Box::pin(res.bytes_stream())),
+        Err((message, _)) => {
+            log::error!("❌ Early-error retry failed: {}", message);
+            None
         }
     }
+}
+
+/// Re-request a completion with `stream: false` and present the JSON reply as a
+/// one-event SSE stream (its `choices[].message` takes the non-streaming path)
+async fn non_streaming_fallback(
+    app: &App,
+    client_key: &str,
+    body: &Value,
+    model: &str,
+) -> Option<ByteStream> {
+    log::warn!("⚠️ Backend stream produced no usable chunks - retrying without streaming");
+    let mut body = body.clone();
+    body["model"] = Value::String(model.to_string());
+    let res = match resolve_deferred_backend(app, client_key, &body, model).await {
+        Ok((res, _)) => res,
+        Err((message, _)) => {
+            log::error!("❌ Non-streaming fallback failed: {}", message);
+            return None;
+        }
+    };
+    let reply: Value = match res.json().await {
+        Ok(reply) => reply,
+        Err(e) => {
+            log::error!("❌ Non-streaming fallback returned invalid JSON: {}", e);
+            return None;
+        }
+    };
+    let sse = format!("data: {}\n\ndata: [DONE]\n\n", reply);
+    Some(Box::pin(futures::stream::once(async move {
+        Ok(axum::body::Bytes::from(sse))
+    })))
+}
 
-    String::from_utf8_lossy(&bytes).into_owned()
+/// Backend response for the streaming task: already received (with the model
+/// that served it), an ensemble race's winning stream, or (in early
+/// `response.created` mode) the request body still to be sent from inside the task
+enum BackendResponse {
+    Ready(reqwest::Response, Arc<str>),
+    Raced(ByteStream, Arc<str>),
+    Deferred(Value),
 }
 
 /// Create an error response as a channel sender
 fn send_error_response(
-    tx: tokio::sync::mpsc::Sender<Event>,
+    tx: tokio::sync::mpsc::Sender<EncodedEvent>,
     model: String,
     error_message: String,
     error_code: String,
 ) {
-    tokio::spawn(async move {
+    spawn_with_request_id(async move {
         let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
-        let created_at = timestamp.as_secs();
         let response_id = format!("resp_{:x}", timestamp.as_nanos());
 
-        let error_event = StreamEvent {
-            type_: "response.failed".to_string(),
-            event_id: Some(format!("evt_{response_id}_0001")),
-            response_id: Some(response_id.clone()),
-            response: Some(Response {
-                id: response_id,
-                object: "response".to_string(),
-                created_at,
-                status: "failed".to_string(),
-                error: Some(crate::models::ResponseError {
-                    code: error_code,
-                    message: error_message,
-                }),
-                incomplete_details: None,
-                model: Some(model),
-                output: vec![],
-                usage: None,
-                metadata: None,
-                instructions: None,
-                tools: None,
-                tool_choice: None,
-                parallel_tool_calls: None,
-                temperature: None,
-                top_p: None,
-                max_output_tokens: None,
-                store: Some(false),
-                previous_response_id: None,
-                reasoning: None,
-                background: None,
-                max_tool_calls: None,
-                text: None,
-                prompt: None,
-                truncation: None,
-                conversation: None,
-                top_logprobs: None,
-                user: None,
-                safety_identifier: None,
-                prompt_cache_key: None,
-                service_tier: None,
-            }),
-            item_id: None,
-            output_index: None,
-            content_index: None,
-            delta: None,
-            text: None,
-            item: None,
-            sequence_number: Some(1),
-            call_id: None,
-            name: None,
-            arguments: None,
-            error: None,
-        };
+        let mut error_event = failed_response_event(
+            &response_id,
+            timestamp.as_secs(),
+            model,
+            error_message,
+            error_code,
+        );
+        error_event.event_id = Some(format!("evt_{response_id}_0001"));
+        error_event.response_id = Some(response_id);
+        error_event.sequence_number = Some(1);
 
         if let Ok(json) = serde_json::to_string(&error_event) {
             dump_stream_event(&json, "error", 1);
-            let _ = tx.send(Event::default().data(json)).await;
+            let _ = tx.send(EncodedEvent { sequence: 1, json }).await;
         }
     });
 }
+
+/// Build a terminal `response.failed` event
+fn failed_response_event(
+    response_id: &str,
+    created_at: u64,
+    model: String,
+    error_message: String,
+    error_code: String,
+) -> StreamEvent {
+    StreamEvent {
+        type_: "response.failed".to_string(),
+        event_id: None,
+        response_id: None,
+        response: Some(Response {
+            id: response_id.to_string(),
+            object: "response".to_string(),
+            created_at,
+            status: "failed".to_string(),
+            error: Some(crate::models::ResponseError {
+                code: error_code,
+                message: error_message,
+            }),
+            incomplete_details: None,
+            model: Some(model),
+            output: vec![],
+            usage: None,
+            metadata: None,
+            instructions: None,
+            tools: None,
+            tool_choice: None,
+            parallel_tool_calls: None,
+            temperature: None,
+            top_p: None,
+            max_output_tokens: None,
+            store: Some(false),
+            previous_response_id: None,
+            reasoning: None,
+            background: None,
+            max_tool_calls: None,
+            text: None,
+            prompt: None,
+            truncation: None,
+            conversation: None,
+            top_logprobs: None,
+            user: None,
+            safety_identifier: None,
+            prompt_cache_key: None,
+            service_tier: None,
+            proxy_debug: None,
+        }),
+        item_id: None,
+        output_index: None,
+        content_index: None,
+        delta: None,
+        text: None,
+        item: None,
+        sequence_number: None,
+        call_id: None,
+        name: None,
+        arguments: None,
+        error: None,
+        annotations: None,
+        usage: None,
+        part: None,
+        summary_index: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyHooks;
+    use crate::services::{PromptRegistry, PromptTemplate};
+    use crate::utils::captured_logs;
+    use axum::{response::IntoResponse, routing::post, Json, Router};
+    use serde_json::json;
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+    use tokio::sync::{Notify, RwLock};
+
+    /// Minimal Chat Completions backend that replays a canned SSE body and
+    /// records every request it receives.
+    struct MockBackend {
+        url: String,
+        requests: Arc<Mutex<Vec<Value>>>,
+    }
+
+    impl MockBackend {
+        fn last_request(&self) -> Value {
+            self.requests
+                .lock()
+                .unwrap()
+                .last()
+                .cloned()
+                .expect("backend received a request")
+        }
+    }
+
+    async fn spawn_backend(sse_body: String) -> MockBackend {
+        spawn_gated_backend(sse_body, None).await
+    }
+
+    /// Like `spawn_backend`, but each response waits for `gate` to be notified
+    async fn spawn_gated_backend(sse_body: String, gate: Option<Arc<Notify>>) -> MockBackend {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(payload): Json<Value>| {
+                recorded.lock().unwrap().push(payload);
+                let body = sse_body.clone();
+                let gate = gate.clone();
+                async move {
+                    if let Some(gate) = gate {
+                        gate.notified().await;
+                    }
+                    ([("content-type", "text/event-stream")], body)
+                }
+            }),
+        );
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        MockBackend {
+            url: format!("http://{addr}/v1/chat/completions"),
+            requests,
+        }
+    }
+
+    fn sse_body(chunks: &[Value]) -> String {
+        let mut body = String::new();
+        for chunk in chunks {
+            body.push_str(&format!("data: {chunk}\n\n"));
+        }
+        body.push_str("data: [DONE]\n\n");
+        body
+    }
+
+    fn text_chunk(text: &str) -> Value {
+        json!({"choices": [{"index": 0, "delta": {"content": text}}]})
+    }
+
+    fn test_app(backend_url: &str, config: ProxyConfig) -> App {
+        App {
+            models_cache: Arc::new(RwLock::new(Some(vec![]))),
+            ..App::for_tests(backend_url, config)
+        }
+    }
+
+    fn auth_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer test-key".parse().unwrap());
+        headers
+    }
+
+    fn request_body(extra: Value) -> String {
+        let mut body = json!({"model": "test-model", "input": "hello", "stream": true});
+        if let (Some(base), Some(extra)) = (body.as_object_mut(), extra.as_object()) {
+            base.extend(extra.clone());
+        }
+        body.to_string()
+    }
+
+    async fn send(app: &App, body: String) -> axum::response::Response {
+        send_with_headers(app, auth_headers(), body).await
+    }
+
+    async fn send_with_headers(
+        app: &App,
+        headers: HeaderMap,
+        body: String,
+    ) -> axum::response::Response {
+        create_response(State(app.clone()), Version::HTTP_11, headers, body)
+            .await
+            .into_response()
+    }
+
+    async fn collect_events(response: axum::response::Response) -> Vec<Value> {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8_lossy(&bytes)
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .filter_map(|data| serde_json::from_str(data).ok())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_sse_connection_limit_rejects_and_frees_slots() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                max_sse_connections: Some(2),
+                ..Default::default()
+            },
+        );
+
+        let first = send(&app, request_body(json!({}))).await;
+        let second = send(&app, request_body(json!({}))).await;
+        assert_eq!(first.status(), StatusCode::OK);
+        assert_eq!(second.status(), StatusCode::OK);
+
+        let rejected = send(&app, request_body(json!({}))).await;
+        assert_eq!(rejected.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body = axum::body::to_bytes(rejected.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"too_many_connections");
+
+        // Dropping a client stream (disconnect) releases its slot
+        drop(first);
+        assert_eq!(app.active_sse_connections.load(Ordering::Acquire), 1);
+
+        let reopened = send(&app, request_body(json!({}))).await;
+        assert_eq!(reopened.status(), StatusCode::OK);
+        drop(second);
+        collect_events(reopened).await;
+        assert_eq!(app.active_sse_connections.load(Ordering::Acquire), 0);
+    }
+
+    #[tokio::test]
+    async fn test_tiny_text_deltas_are_coalesced() {
+        let text = "coalesce me, please";
+        let chunks: Vec<Value> = text.chars().map(|c| text_chunk(&c.to_string())).collect();
+        let mut body_chunks = vec![reasoning_chunk("think")];
+        body_chunks.extend(chunks);
+        let backend = spawn_backend(sse_body(&body_chunks)).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                delta_coalesce_window: Some(std::time::Duration::from_secs(5)),
+                delta_coalesce_max_bytes: Some(8),
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let deltas: Vec<&str> = events
+            .iter()
+            .filter(|e| e["type"] == "response.output_text.delta")
+            .map(|e| e["delta"].as_str().unwrap())
+            .collect();
+        assert!(
+            deltas.len() < text.len(),
+            "deltas were not merged: {deltas:?}"
+        );
+        assert!(deltas.len() > 1, "size threshold should split the text");
+        assert_eq!(deltas.concat(), text);
+        assert_eq!(
+            completed_response(&events)["output"][0]["content"][0]["text"],
+            text
+        );
+
+        // Reasoning deltas pass through untouched
+        let reasoning_deltas = events
+            .iter()
+            .filter(|e| e["type"] == "response.reasoning_text.delta")
+            .count();
+        assert_eq!(reasoning_deltas, 1);
+
+        // Sequence numbers stay gapless despite the merging
+        let sequences: Vec<u64> = events
+            .iter()
+            .filter_map(|e| e["sequence_number"].as_u64())
+            .collect();
+        assert!(sequences.windows(2).all(|w| w[1] == w[0] + 1));
+    }
+
+    #[tokio::test]
+    async fn test_client_disconnect_is_counted_as_cancelled() {
+        let backend =
+            spawn_backend(sse_body(&[text_chunk("partial"), text_chunk(" answer")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let response = send(&app, request_body(json!({}))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        drop(response);
+
+        for _ in 0..50 {
+            if app.metrics.client_cancelled.load(Ordering::Relaxed) > 0 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(app.metrics.client_cancelled.load(Ordering::Relaxed), 1);
+
+        // A stream read to the end is not a cancellation
+        collect_events(send(&app, request_body(json!({}))).await).await;
+        assert_eq!(app.metrics.client_cancelled.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn test_argument_fragments_after_finish_reason_are_kept() {
+        let args_chunk = |arguments: &str| {
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "function": {"arguments": arguments}}
+            ]}}]})
+        };
+        let chunks = [
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [{
+                "index": 0,
+                "id": "call_late",
+                "type": "function",
+                "function": {"name": "read_file", "arguments": "{\"path\": "}
+            }]}}]}),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+            args_chunk("\"src/"),
+            args_chunk("main.rs\"}"),
+        ];
+        // Every event is its own network write, so the fragments trailing
+        // finish_reason arrive in separate reads before `[DONE]`
+        let mut writes: Vec<String> = chunks.iter().map(|c| format!("data: {c}\n\n")).collect();
+        writes.push("data: [DONE]\n\n".to_string());
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move || {
+                let writes = writes.clone();
+                async move {
+                    let stream = futures::stream::iter(writes).then(|write| async move {
+                        tokio::time::sleep(Duration::from_millis(5)).await;
+                        Ok::<_, std::convert::Infallible>(write)
+                    });
+                    (
+                        [("content-type", "text/event-stream")],
+                        Body::from_stream(stream),
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig::default(),
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let expected = r#"{"path": "src/main.rs"}"#;
+        let args_done = events
+            .iter()
+            .find(|e| e["type"] == "response.function_call_arguments.done")
+            .unwrap();
+        assert_eq!(args_done["arguments"], expected);
+        let response = completed_response(&events);
+        assert_eq!(response["status"], "completed");
+        let call = response["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|i| i["call_id"] == "call_late")
+            .unwrap();
+        assert_eq!(call["arguments"], expected);
+    }
+
+    #[tokio::test]
+    async fn test_stored_response_is_retrievable_by_its_owner() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello")])).await;
+        let mut app = test_app(&backend.url, ProxyConfig::default());
+        app.response_store = Some(Arc::new(crate::services::InMemoryResponseStore::new(8)));
+
+        let events = collect_events(send(&app, request_body(json!({"store": true}))).await).await;
+        let completed = completed_response(&events);
+        assert_eq!(completed["store"], true);
+        let id = completed["id"].as_str().unwrap().to_string();
+
+        let Json(stored) = get_response(State(app.clone()), auth_headers(), Path(id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(stored.id, id);
+        assert_eq!(stored.status, "completed");
+        assert_eq!(
+            serde_json::to_value(&stored).unwrap()["output"],
+            completed["output"]
+        );
+
+        // Other API keys cannot see it
+        let mut other = HeaderMap::new();
+        other.insert("authorization", "Bearer other-key".parse().unwrap());
+        let err = get_response(State(app.clone()), other, Path(id.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(err, (StatusCode::NOT_FOUND, "response_not_found"));
+
+        // Finished responses cannot be cancelled, but can be deleted
+        let err = cancel_response(State(app.clone()), auth_headers(), Path(id.clone()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.1, "response_not_cancellable");
+        let Json(deleted) = delete_response(State(app.clone()), auth_headers(), Path(id.clone()))
+            .await
+            .unwrap();
+        assert_eq!(deleted["deleted"], true);
+        assert!(get_response(State(app.clone()), auth_headers(), Path(id))
+            .await
+            .is_err());
+
+        // Without `store: true` nothing is kept
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let completed = completed_response(&events);
+        assert_eq!(completed["store"], false);
+        let id = completed["id"].as_str().unwrap().to_string();
+        assert!(get_response(State(app), auth_headers(), Path(id))
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_custom_tool_call_type_is_surfaced() {
+        let backend = spawn_backend(sse_body(&[
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [{
+                "index": 0,
+                "id": "call_custom",
+                "type": "custom",
+                "function": {"name": "grammar_tool", "arguments": "{\"q\":1}"}
+            }]}}]}),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let added = events
+            .iter()
+            .find(|e| {
+                e["type"] == "response.output_item.added" && e["item"]["call_id"] == "call_custom"
+            })
+            .expect("tool call added event");
+        assert_eq!(added["item"]["type"], "custom_tool_call");
+
+        let completed = events
+            .iter()
+            .find(|e| e["type"] == "response.completed")
+            .expect("completed event");
+        let call_item = completed["response"]["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["call_id"] == "call_custom")
+            .expect("tool call in final output");
+        assert_eq!(call_item["type"], "custom_tool_call");
+    }
+
+    #[tokio::test]
+    async fn test_modalities_forwarded_to_backend() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let response = send(&app, request_body(json!({"modalities": ["text", "audio"]}))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        collect_events(response).await;
+
+        assert_eq!(
+            backend.last_request()["modalities"],
+            json!(["text", "audio"])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_invalid_modality_rejected() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let response = send(&app, request_body(json!({"modalities": ["video"]}))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(backend.requests.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_conversation_with_previous_response_id_rejected() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let body = || {
+            request_body(json!({
+                "conversation": "conv_123",
+                "previous_response_id": "resp_456"
+            }))
+        };
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let response = send(&app, body()).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("conflicting_state_params"));
+        assert!(backend.requests.lock().unwrap().is_empty());
+
+        // Either one alone is still accepted (and ignored)
+        let alone = request_body(json!({"previous_response_id": "resp_456"}));
+        assert_eq!(send(&app, alone).await.status(), StatusCode::OK);
+
+        let lenient = test_app(
+            &backend.url,
+            ProxyConfig {
+                allow_conflicting_state_params: true,
+                ..Default::default()
+            },
+        );
+        assert_eq!(send(&lenient, body()).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_truncation_values_validated() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        for (value, echoed) in [
+            ("auto", "auto"),
+            ("disabled", "disabled"),
+            (" Auto ", "auto"),
+        ] {
+            let response = send(&app, request_body(json!({"truncation": value}))).await;
+            assert_eq!(response.status(), StatusCode::OK);
+            let events = collect_events(response).await;
+            assert_eq!(completed_response(&events)["truncation"], echoed);
+        }
+
+        let requests_before = backend.requests.lock().unwrap().len();
+        let response = send(&app, request_body(json!({"truncation": "middle"}))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("invalid_truncation"));
+        assert_eq!(backend.requests.lock().unwrap().len(), requests_before);
+    }
+
+    fn reasoning_chunk(text: &str) -> Value {
+        json!({"choices": [{"index": 0, "delta": {"reasoning_content": text}}]})
+    }
+
+    /// The response carried by the terminal completed/incomplete/failed event
+    fn completed_response(events: &[Value]) -> &Value {
+        &events
+            .iter()
+            .find(|e| {
+                matches!(
+                    e["type"].as_str(),
+                    Some("response.completed" | "response.incomplete" | "response.failed")
+                )
+            })
+            .expect("terminal event")["response"]
+    }
+
+    #[tokio::test]
+    async fn test_request_completed_line_has_timing_breakdown() {
+        let logs = captured_logs();
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let body = request_body(json!({"model": "timing-model"}));
+        collect_events(send(&app, body).await).await;
+
+        let line = logs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|line| line.contains("request_completed: model=timing-model,"))
+            .cloned()
+            .expect("request_completed line");
+        let field = |name: &str| -> u64 {
+            let start = line.find(&format!(" {}=", name)).expect(name) + name.len() + 2;
+            line[start..].split(',').next().unwrap().parse().unwrap()
+        };
+        let (prepare, backend_ms, stream, total) = (
+            field("prepare_ms"),
+            field("backend_ms"),
+            field("stream_ms"),
+            field("duration_ms"),
+        );
+        assert_eq!(prepare + backend_ms + stream, total, "{line}");
+        assert!(app.metrics.timed_requests.load(Ordering::Relaxed) >= 1);
+
+        // The first chunk falls between preparation and the end of the request
+        let timing = TimingBreakdown::new(5, Some(30), 100);
+        assert_eq!(
+            (timing.prepare_ms, timing.backend_ms, timing.stream_ms),
+            (5, 25, 70)
+        );
+        let timing = TimingBreakdown::new(5, None, 40);
+        assert_eq!(
+            (timing.prepare_ms, timing.backend_ms, timing.stream_ms),
+            (5, 35, 0)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_helper_log_lines_carry_request_id() {
+        let logs = captured_logs();
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let config = ProxyConfig {
+            default_tool_choices: vec![("tagged-".to_string(), "auto".to_string())],
+            ..Default::default()
+        };
+        let app = test_app(&backend.url, config);
+        let tools = json!([{"type": "function", "name": "lookup", "parameters": {}}]);
+        let body = request_body(json!({"model": "tagged-model", "tools": tools}));
+        collect_events(send(&app, body).await).await;
+
+        let lines = logs.lock().unwrap().clone();
+        let line_with = |needle: &str| {
+            lines
+                .iter()
+                .find(|line| line.contains(needle))
+                .unwrap_or_else(|| panic!("no log line containing {needle:?}"))
+                .clone()
+        };
+        let request_line = line_with("Request: model=tagged-model");
+        let tag = &request_line[..request_line.find("] ").expect("request id tag") + 2];
+        assert!(tag.starts_with('['), "{request_line}");
+        // Converter, streaming task and metrics lines share the handler's id
+        for needle in [
+            "Using default tool_choice 'auto' for tagged-model",
+            "request_completed: model=tagged-model,",
+        ] {
+            let line = line_with(needle);
+            assert!(line.starts_with(tag), "{line}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_logprobs_dropped_for_model_without_support() {
+        let logs = captured_logs();
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let model = |id: &str, features: &[&str]| crate::models::ModelInfo {
+            id: id.to_string(),
+            input_price_usd: None,
+            output_price_usd: None,
+            supported_features: features.iter().map(|f| f.to_string()).collect(),
+        };
+        *app.models_cache.write().await = Some(vec![
+            model("test-model", &["tools"]),
+            model("logprob-model", &["tools", "logprobs"]),
+        ]);
+        let body = |model: &str| {
+            request_body(json!({
+                "model": model,
+                "top_logprobs": 3,
+                "include": ["message.output_text.logprobs"]
+            }))
+        };
+
+        let response = send(&app, body("test-model")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        collect_events(response).await;
+        let forwarded = backend.last_request();
+        assert!(forwarded.get("logprobs").is_none());
+        assert!(forwarded.get("top_logprobs").is_none());
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("does not declare support for logprobs")));
+
+        collect_events(send(&app, body("logprob-model")).await).await;
+        assert_eq!(backend.last_request()["top_logprobs"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_backend_error_detail_sanitized_for_clients() {
+        let logs = captured_logs();
+        let detail = "upstream http://10.0.0.12:9000/internal/infer failed: Traceback ...";
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move || async move {
+                (
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    [("content-type", "application/json")],
+                    json!({"error": {"message": detail}}).to_string(),
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                sanitize_backend_errors: true,
+                ..Default::default()
+            },
+        );
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let failed = events
+            .iter()
+            .find(|e| e["type"] == "response.failed")
+            .expect("failed event");
+        let message = failed["response"]["error"]["message"].as_str().unwrap();
+        assert!(message.contains("temporarily unavailable"));
+        assert!(!serde_json::to_string(&events)
+            .unwrap()
+            .contains("10.0.0.12"));
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("withheld from client") && line.contains(detail)));
+    }
+
+    #[tokio::test]
+    async fn test_items_omit_realtime_object_unless_configured() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let added = events
+            .iter()
+            .find(|e| e["type"] == "response.output_item.added")
+            .unwrap();
+        assert!(added["item"].get("object").is_none());
+        let message = &completed_response(&events)["output"][0];
+        assert_eq!(message["type"], "message");
+        assert!(message.get("object").is_none());
+
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                item_object: Some("realtime.item".to_string()),
+                ..Default::default()
+            },
+        );
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let added = events
+            .iter()
+            .find(|e| e["type"] == "response.output_item.added")
+            .unwrap();
+        assert_eq!(added["item"]["object"], "realtime.item");
+        assert_eq!(
+            completed_response(&events)["output"][0]["object"],
+            "realtime.item"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_item_object_follows_requested_api_version() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                item_object: Some("realtime.item".to_string()),
+                item_object_versions: vec![
+                    ("legacy".to_string(), "realtime.item".to_string()),
+                    ("2025-03".to_string(), String::new()),
+                ],
+                ..Default::default()
+            },
+        );
+        let item_object = |version: Option<&'static str>| {
+            let app = app.clone();
+            async move {
+                let mut headers = auth_headers();
+                if let Some(version) = version {
+                    headers.insert("x-responses-api-version", version.parse().unwrap());
+                }
+                let response = send_with_headers(&app, headers, request_body(json!({}))).await;
+                let events = collect_events(response).await;
+                let added = events
+                    .iter()
+                    .find(|e| e["type"] == "response.output_item.added")
+                    .unwrap();
+                let object = added["item"].get("object").cloned();
+                assert_eq!(
+                    completed_response(&events)["output"][0].get("object"),
+                    object.as_ref()
+                );
+                object
+            }
+        };
+
+        assert_eq!(
+            item_object(Some("LEGACY")).await,
+            Some(json!("realtime.item"))
+        );
+        assert_eq!(item_object(Some("2025-03")).await, None);
+        // Unknown and missing versions keep the configured default
+        assert_eq!(
+            item_object(Some("1999")).await,
+            Some(json!("realtime.item"))
+        );
+        assert_eq!(item_object(None).await, Some(json!("realtime.item")));
+    }
+
+    #[tokio::test]
+    async fn test_omitted_sampling_params_use_server_defaults() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                default_temperature: Some(0.25),
+                default_top_p: Some(0.5),
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({"top_p": 0.75}))).await).await;
+        let sent = backend.last_request();
+        assert_eq!(sent["temperature"], 0.25);
+        assert_eq!(sent["top_p"], 0.75);
+        let response = completed_response(&events);
+        assert_eq!(response["temperature"], 0.25);
+        assert_eq!(response["top_p"], 0.75);
+    }
+
+    #[tokio::test]
+    async fn test_dropped_sampling_param_is_not_echoed() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                sampling_param_preference: crate::models::SamplingParamPreference::Temperature,
+                ..Default::default()
+            },
+        );
+
+        let body = request_body(json!({"temperature": 0.5, "top_p": 0.25}));
+        let events = collect_events(send(&app, body).await).await;
+        let sent = backend.last_request();
+        assert_eq!(sent["temperature"], 0.5);
+        assert!(sent.get("top_p").is_none());
+        let created = &events[0]["response"];
+        let completed = completed_response(&events);
+        for response in [created, completed] {
+            assert_eq!(response["temperature"], 0.5);
+            assert!(response["top_p"].is_null());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_tool_choice_applied_per_model() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                default_tool_choices: vec![("test-".to_string(), "required".to_string())],
+                ..Default::default()
+            },
+        );
+        let tools = json!([{"type": "function", "name": "lookup", "parameters": {}}]);
+
+        collect_events(send(&app, request_body(json!({"tools": tools}))).await).await;
+        assert_eq!(backend.last_request()["tool_choice"], "required");
+
+        // The client's own choice still wins
+        let body = request_body(json!({"tools": tools, "tool_choice": "none"}));
+        collect_events(send(&app, body).await).await;
+        assert_eq!(backend.last_request()["tool_choice"], "none");
+
+        // Without tools there is nothing to choose
+        collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(backend.last_request().get("tool_choice").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batched_array_payload_processes_every_chunk() {
+        let batch = json!([text_chunk("Hello, "), text_chunk("world")]);
+        let backend = spawn_backend(format!("data: {batch}\n\ndata: [DONE]\n\n")).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let deltas: Vec<&str> = events
+            .iter()
+            .filter(|e| e["type"] == "response.output_text.delta")
+            .filter_map(|e| e["delta"].as_str())
+            .collect();
+        assert_eq!(deltas, ["Hello, ", "world"]);
+        assert_eq!(
+            completed_response(&events)["output"][0]["content"][0]["text"],
+            "Hello, world"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pretty_tool_arguments_compacted_when_enabled() {
+        let pretty_call = |arguments: &str| {
+            sse_body(&[json!({"choices": [{
+                "index": 0,
+                "delta": {"tool_calls": [{
+                    "index": 0,
+                    "id": "call_pretty",
+                    "type": "function",
+                    "function": {"name": "read_file", "arguments": arguments}
+                }]}
+            }]})])
+        };
+        let config = || ProxyConfig {
+            compact_tool_arguments: true,
+            ..Default::default()
+        };
+
+        let backend =
+            spawn_backend(pretty_call("{\n  \"path\": \"a.txt\",\n  \"limit\": 5\n}")).await;
+        let app = test_app(&backend.url, config());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let done = events
+            .iter()
+            .find(|e| e["type"] == "response.function_call_arguments.done")
+            .unwrap();
+        assert_eq!(done["arguments"], "{\"limit\":5,\"path\":\"a.txt\"}");
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        let call = output
+            .iter()
+            .find(|i| i["call_id"] == "call_pretty")
+            .unwrap();
+        assert_eq!(call["arguments"], done["arguments"]);
+
+        // Arguments that are not valid JSON pass through untouched
+        let backend = spawn_backend(pretty_call("{ \"path\": ")).await;
+        let app = test_app(&backend.url, config());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        let call = output
+            .iter()
+            .find(|i| i["call_id"] == "call_pretty")
+            .unwrap();
+        assert_eq!(call["arguments"], "{ \"path\": ");
+    }
+
+    #[tokio::test]
+    async fn test_validation_error_streamed_as_failed_event_when_enabled() {
+        let body = || request_body(json!({"max_output_tokens": 0}));
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig::default(),
+        );
+        assert_eq!(send(&app, body()).await.status(), StatusCode::BAD_REQUEST);
+
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig {
+                validation_errors_as_sse: true,
+                ..Default::default()
+            },
+        );
+        let response = send(&app, body()).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let events = collect_events(response).await;
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "response.failed");
+        assert_eq!(events[0]["response"]["model"], "test-model");
+        assert_eq!(events[0]["response"]["error"]["code"], "invalid_max_tokens");
+    }
+
+    #[tokio::test]
+    async fn test_sse_header_overrides_on_success_and_error_streams() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                validation_errors_as_sse: true,
+                sse_headers: vec![
+                    ("x-accel-buffering".to_string(), None),
+                    (
+                        "cdn-cache-control".to_string(),
+                        Some("no-store".to_string()),
+                    ),
+                    (
+                        "cache-control".to_string(),
+                        Some("no-cache, no-transform".to_string()),
+                    ),
+                ],
+                ..Default::default()
+            },
+        );
+
+        let success = send(&app, request_body(json!({}))).await;
+        let error = send(&app, request_body(json!({"max_output_tokens": 0}))).await;
+        for response in [success, error] {
+            assert_eq!(response.status(), StatusCode::OK);
+            let headers = response.headers();
+            assert!(headers.get("x-accel-buffering").is_none());
+            assert_eq!(headers["cdn-cache-control"], "no-store");
+            assert_eq!(headers["cache-control"], "no-cache, no-transform");
+            assert!(headers["content-type"]
+                .to_str()
+                .unwrap()
+                .starts_with("text/event-stream"));
+        }
+
+        // Defaults are untouched without overrides
+        let response = send(
+            &test_app(&backend.url, ProxyConfig::default()),
+            request_body(json!({})),
+        )
+        .await;
+        assert_eq!(response.headers()["x-accel-buffering"], "no");
+        assert_eq!(response.headers()["cache-control"], "no-cache");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_capped_while_text_continues() {
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("0123456789"),
+            reasoning_chunk("abcdefghij"),
+            reasoning_chunk("more thinking"),
+            text_chunk("the answer"),
+        ]))
+        .await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                max_reasoning_bytes: Some(15),
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let reasoning: String = events
+            .iter()
+            .filter(|e| e["type"] == "response.reasoning_text.delta")
+            .filter_map(|e| e["delta"].as_str())
+            .collect();
+        assert_eq!(reasoning, "0123456789abcde");
+        let done: Vec<&Value> = events
+            .iter()
+            .filter(|e| e["type"] == "response.reasoning_text.done")
+            .collect();
+        assert_eq!(done.len(), 1);
+        assert_eq!(done[0]["text"], "0123456789abcde");
+
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        assert_eq!(output[0]["content"][0]["text"], "the answer");
+        let item = output.iter().find(|i| i["type"] == "reasoning").unwrap();
+        assert_eq!(item["content"][0]["text"], "0123456789abcde");
+    }
+
+    #[tokio::test]
+    async fn test_backend_reasoning_tokens_reported_in_usage() {
+        let usage = |completion: u32, reasoning: u32| {
+            json!({"choices": [], "usage": {
+                "prompt_tokens": 10,
+                "completion_tokens": completion,
+                "completion_tokens_details": {"reasoning_tokens": reasoning}
+            }})
+        };
+
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("think"),
+            text_chunk("answer"),
+            usage(50, 30),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let reported = &completed_response(&events)["usage"];
+        assert_eq!(reported["output_tokens"], 50);
+        assert_eq!(reported["output_tokens_details"]["reasoning_tokens"], 30);
+        assert_eq!(reported["total_tokens"], 60);
+
+        // Reasoning counted outside completion_tokens is added to the output
+        let backend = spawn_backend(sse_body(&[text_chunk("answer"), usage(5, 30)])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let reported = &completed_response(&events)["usage"];
+        assert_eq!(reported["output_tokens"], 35);
+        assert_eq!(reported["output_tokens_details"]["reasoning_tokens"], 30);
+    }
+
+    #[tokio::test]
+    async fn test_backend_request_hook_edits_sent_body() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let mut app = test_app(&backend.url, ProxyConfig::default());
+        app.hooks = Arc::new(ProxyHooks {
+            backend_request: Some(Arc::new(|body: &mut Value| {
+                body["top_k"] = json!(40);
+            })),
+            ..Default::default()
+        });
+
+        collect_events(send(&app, request_body(json!({}))).await).await;
+        let sent = backend.last_request();
+        assert_eq!(sent["top_k"], 40);
+        assert_eq!(sent["model"], "test-model");
+    }
+
+    #[tokio::test]
+    async fn test_stream_event_hook_can_drop_events() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let mut app = test_app(&backend.url, ProxyConfig::default());
+        app.hooks = Arc::new(ProxyHooks {
+            stream_event: Some(Arc::new(|event: StreamEvent| {
+                (event.type_ != "response.content_part.added").then_some(event)
+            })),
+            ..Default::default()
+        });
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(!events
+            .iter()
+            .any(|e| e["type"] == "response.content_part.added"));
+        assert!(events
+            .iter()
+            .any(|e| e["type"] == "response.output_text.delta"));
+        // Sequence numbers stay contiguous around the dropped event
+        let sequence: Vec<u64> = events
+            .iter()
+            .filter_map(|e| e["sequence_number"].as_u64())
+            .collect();
+        assert_eq!(sequence, (1..=sequence.len() as u64).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_length_finish_emits_response_incomplete() {
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("partial"),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "length"}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let types: Vec<&str> = events.iter().filter_map(|e| e["type"].as_str()).collect();
+        assert!(types.contains(&"response.incomplete"));
+        assert!(!types.contains(&"response.completed"));
+        let response = completed_response(&events);
+        assert_eq!(response["status"], "incomplete");
+        assert_eq!(
+            response["incomplete_details"]["reason"],
+            "max_output_tokens"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_terminal_message_tool_calls_become_function_calls() {
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("Let me check"),
+            json!({"choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_weather",
+                        "type": "function",
+                        "function": {"name": "get_weather", "arguments": "{\"city\":\"Oslo\"}"}
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(events.iter().any(|e| {
+            e["type"] == "response.output_item.added" && e["item"]["call_id"] == "call_weather"
+        }));
+
+        let output = completed_response(&events)["output"]
+            .as_array()
+            .unwrap()
+            .clone();
+        assert_eq!(output[0]["content"][0]["text"], "Let me check");
+        let call = output
+            .iter()
+            .find(|item| item["type"] == "function_call")
+            .expect("function_call output item");
+        assert_eq!(call["call_id"], "call_weather");
+        assert_eq!(call["name"], "get_weather");
+        assert_eq!(call["arguments"], "{\"city\":\"Oslo\"}");
+    }
+
+    #[tokio::test]
+    async fn test_malformed_apply_patch_is_flagged() {
+        let patch_call = || {
+            sse_body(&[json!({"choices": [{
+                "index": 0,
+                "message": {
+                    "role": "assistant",
+                    "content": null,
+                    "tool_calls": [{
+                        "id": "call_patch",
+                        "type": "function",
+                        "function": {
+                            "name": "apply_patch",
+                            "arguments": "{\"patch\":\"*** Begin Patch\\n*** Update File: a.rs\\n+new\\n\"}"
+                        }
+                    }]
+                },
+                "finish_reason": "tool_calls"
+            }]})])
+        };
+
+        // Advisory by default: counted, but the call still completes
+        let backend = spawn_backend(patch_call()).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let response = completed_response(&events);
+        assert_eq!(response["status"], "completed");
+        assert!(response["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|item| item["call_id"] == "call_patch"));
+        assert_eq!(
+            app.metrics
+                .apply_patch_malformed
+                .load(std::sync::atomic::Ordering::Relaxed),
+            1
+        );
+
+        let backend = spawn_backend(patch_call()).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                apply_patch_validation: ApplyPatchValidation::Reject,
+                ..Default::default()
+            },
+        );
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let response = completed_response(&events);
+        assert_eq!(response["status"], "failed");
+        assert_eq!(response["error"]["code"], "malformed_apply_patch");
+    }
+
+    #[tokio::test]
+    async fn test_ensemble_streams_fastest_model_and_cancels_the_rest() {
+        /// Flags the slow handler being dropped before it finished
+        struct Cancelled(Arc<Notify>);
+        impl Drop for Cancelled {
+            fn drop(&mut self) {
+                self.0.notify_one();
+            }
+        }
+
+        let cancelled = Arc::new(Notify::new());
+        let slow_cancelled = Arc::clone(&cancelled);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(payload): Json<Value>| {
+                let cancelled = Arc::clone(&slow_cancelled);
+                async move {
+                    if payload["model"] == "slow-model" {
+                        let guard = Cancelled(cancelled);
+                        tokio::time::sleep(Duration::from_secs(30)).await;
+                        std::mem::forget(guard);
+                    }
+                    let answer = format!("from {}", payload["model"].as_str().unwrap());
+                    (
+                        [("content-type", "text/event-stream")],
+                        sse_body(&[text_chunk(&answer)]),
+                    )
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                ensembles: vec![(
+                    "race".to_string(),
+                    vec!["slow-model".to_string(), "fast-model".to_string()],
+                )],
+                ..Default::default()
+            },
+        );
+        let events = tokio::time::timeout(
+            Duration::from_secs(5),
+            collect_events(send(&app, request_body(json!({"model": "race"}))).await),
+        )
+        .await
+        .expect("the fast model's stream is used without waiting for the slow one");
+
+        let response = completed_response(&events);
+        assert_eq!(response["model"], "fast-model");
+        assert_eq!(
+            response["output"][0]["content"][0]["text"],
+            "from fast-model"
+        );
+        tokio::time::timeout(Duration::from_secs(5), cancelled.notified())
+            .await
+            .expect("slow backend request was cancelled");
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_item_has_summary_array() {
+        let backend =
+            spawn_backend(sse_body(&[reasoning_chunk("think"), text_chunk("answer")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        let reasoning = output
+            .iter()
+            .find(|item| item["type"] == "reasoning")
+            .expect("reasoning item");
+
+        assert_eq!(reasoning["summary"], json!([]));
+        assert_eq!(
+            reasoning["content"],
+            json!([{"type": "reasoning_text", "text": "think"}])
+        );
+        assert!(reasoning.get("text").is_none());
+
+        let events = collect_events(
+            send(
+                &app,
+                request_body(json!({"reasoning": {"summary": "auto"}})),
+            )
+            .await,
+        )
+        .await;
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        let reasoning = output
+            .iter()
+            .find(|item| item["type"] == "reasoning")
+            .unwrap();
+        assert_eq!(
+            reasoning["summary"],
+            json!([{"type": "summary_text", "text": "think"}])
+        );
+        assert_eq!(reasoning["content"], json!([]));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_suppressed_for_model_without_reasoning_feature() {
+        let logs = captured_logs();
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("spurious musing"),
+            text_chunk("answer"),
+        ]))
+        .await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                reasoning_feature_gate: true,
+                ..Default::default()
+            },
+        );
+        let model = |id: &str, features: &[&str]| crate::models::ModelInfo {
+            id: id.to_string(),
+            input_price_usd: None,
+            output_price_usd: None,
+            supported_features: features.iter().map(|f| f.to_string()).collect(),
+        };
+        *app.models_cache.write().await = Some(vec![
+            model("plain-model", &["tools"]),
+            model("thinking-model", &["tools", "reasoning"]),
+        ]);
+        let has_reasoning = |events: &[Value]| {
+            events.iter().any(|event| {
+                event["type"]
+                    .as_str()
+                    .is_some_and(|t| t.starts_with("response.reasoning"))
+            }) || completed_response(events)["output"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .any(|item| item["type"] == "reasoning")
+        };
+
+        let body = request_body(json!({"model": "plain-model"}));
+        let events = collect_events(send(&app, body).await).await;
+        assert!(!has_reasoning(&events));
+        let output = completed_response(&events)["output"].as_array().unwrap();
+        assert_eq!(output[0]["content"][0]["text"], "answer");
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("Suppressed reasoning: spurious musing")));
+
+        // Declared reasoning models, and the gate's default, keep it
+        let body = request_body(json!({"model": "thinking-model"}));
+        assert!(has_reasoning(&collect_events(send(&app, body).await).await));
+        let app = test_app(&backend.url, ProxyConfig::default());
+        *app.models_cache.write().await = Some(vec![model("plain-model", &["tools"])]);
+        let body = request_body(json!({"model": "plain-model"}));
+        assert!(has_reasoning(&collect_events(send(&app, body).await).await));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_summary_part_events_bracket_summary_deltas() {
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("thin"),
+            reasoning_chunk("king"),
+            text_chunk("answer"),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(
+            send(
+                &app,
+                request_body(json!({"reasoning": {"summary": "auto"}})),
+            )
+            .await,
+        )
+        .await;
+        let summary_events: Vec<&Value> = events
+            .iter()
+            .filter(|event| {
+                event["type"]
+                    .as_str()
+                    .is_some_and(|t| t.starts_with("response.reasoning_summary_"))
+            })
+            .collect();
+        let types: Vec<&str> = summary_events
+            .iter()
+            .map(|event| event["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                "response.reasoning_summary_part.added",
+                "response.reasoning_summary_text.delta",
+                "response.reasoning_summary_text.delta",
+                "response.reasoning_summary_text.done",
+                "response.reasoning_summary_part.done",
+            ]
+        );
+        assert_eq!(
+            summary_events[0]["part"],
+            json!({"type": "summary_text", "text": ""})
+        );
+        assert_eq!(summary_events[1]["delta"], "thin");
+        assert_eq!(summary_events[3]["text"], "thinking");
+        assert_eq!(
+            summary_events[4]["part"],
+            json!({"type": "summary_text", "text": "thinking"})
+        );
+        for event in &summary_events {
+            assert_eq!(event["summary_index"], 0);
+            assert_eq!(event["item_id"], summary_events[0]["item_id"]);
+        }
+        // The reasoning is streamed once, as the summary
+        assert!(!events.iter().any(|event| event["type"]
+            .as_str()
+            .is_some_and(|t| t.starts_with("response.reasoning_text."))));
+
+        // Without a requested summary only the reasoning_text events are sent
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(!events.iter().any(|event| event["type"]
+            .as_str()
+            .is_some_and(|t| t.starts_with("response.reasoning_summary_"))));
+    }
+
+    #[tokio::test]
+    async fn test_early_backend_error_retried_transparently() {
+        // The first attempt errors before any content, the second one streams
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&attempts);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move || {
+                let attempt = counter.fetch_add(1, Ordering::SeqCst);
+                async move {
+                    let body = if attempt == 0 {
+                        sse_body(&[
+                            json!({"choices": [{"index": 0, "delta": {"role": "assistant"}}]}),
+                            json!({"error": {"message": "worker crashed"}}),
+                        ])
+                    } else {
+                        sse_body(&[text_chunk("hello")])
+                    };
+                    ([("content-type", "text/event-stream")], body)
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let url = format!("http://{addr}/v1/chat/completions");
+
+        let config = ProxyConfig {
+            early_error_retries: 1,
+            ..Default::default()
+        };
+        let events =
+            collect_events(send(&test_app(&url, config), request_body(json!({}))).await).await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+        let response = completed_response(&events);
+        assert_eq!(response["status"], "completed");
+        assert_eq!(response["output"][0]["content"][0]["text"], "hello");
+        let created = events
+            .iter()
+            .filter(|event| event["type"] == "response.created")
+            .count();
+        assert_eq!(created, 1);
+        assert!(events
+            .iter()
+            .all(|event| event["type"] != "response.failed"));
+        assert!(events
+            .iter()
+            .all(|event| event["response"]["id"].is_null()
+                || event["response"]["id"] == response["id"]));
+
+        // Without a retry budget the same failure fails the response
+        attempts.store(0, Ordering::SeqCst);
+        let events = collect_events(
+            send(
+                &test_app(&url, ProxyConfig::default()),
+                request_body(json!({})),
+            )
+            .await,
+        )
+        .await;
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+        assert_eq!(completed_response(&events)["status"], "failed");
+
+        // Once content has streamed an error is not retried
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("partial"),
+            json!({"error": {"message": "worker crashed"}}),
+        ]))
+        .await;
+        let config = ProxyConfig {
+            early_error_retries: 1,
+            ..Default::default()
+        };
+        let events =
+            collect_events(send(&test_app(&backend.url, config), request_body(json!({}))).await)
+                .await;
+        assert_eq!(backend.requests.lock().unwrap().len(), 1);
+        assert_eq!(completed_response(&events)["status"], "failed");
+    }
+
+    #[tokio::test]
+    async fn test_slow_backend_headers_fail_fast() {
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(|| async {
+                tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+                ([("content-type", "text/event-stream")], sse_body(&[]))
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                backend_headers_timeout: Some(std::time::Duration::from_millis(100)),
+                ..Default::default()
+            },
+        );
+
+        let started = std::time::Instant::now();
+        let response = send(&app, request_body(json!({}))).await;
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+        assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"backend_connect_timeout");
+    }
+
+    #[tokio::test]
+    async fn test_message_item_lifecycle_matches_responses_schema() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hi"), text_chunk(" there")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let message_id = events
+            .iter()
+            .find(|e| e["type"] == "response.output_item.added")
+            .expect("message output_item.added")["item"]["id"]
+            .clone();
+        let lifecycle: Vec<&Value> = events
+            .iter()
+            .filter(|e| e["item_id"] == message_id)
+            .collect();
+        let types: Vec<&str> = lifecycle
+            .iter()
+            .map(|e| e["type"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "response.output_item.added",
+                "response.content_part.added",
+                "response.output_text.delta",
+                "response.output_text.delta",
+                "response.output_text.done",
+                "response.content_part.done",
+                "response.output_item.done",
+            ]
+        );
+        for event in &lifecycle {
+            assert_eq!(event["output_index"], 0);
+            assert!(event["sequence_number"].is_u64());
+        }
+        for event in &lifecycle[1..6] {
+            assert_eq!(event["content_index"], 0);
+        }
+
+        let added = &lifecycle[0]["item"];
+        assert_eq!(added["type"], "message");
+        assert_eq!(added["role"], "assistant");
+        assert_eq!(added["status"], "in_progress");
+        assert_eq!(added["content"], json!([]));
+        assert!(added.get("object").is_none());
+        let empty_part = json!({"type": "output_text", "text": "", "annotations": []});
+        assert_eq!(lifecycle[1]["part"], empty_part);
+        assert_eq!(lifecycle[4]["text"], "Hi there");
+        let full_part = json!({"type": "output_text", "text": "Hi there", "annotations": []});
+        assert_eq!(lifecycle[5]["part"], full_part);
+        let done = &lifecycle[6]["item"];
+        assert_eq!(done["status"], "completed");
+        assert_eq!(done["content"], json!([full_part]));
+        assert_eq!(completed_response(&events)["output"][0], *done);
+    }
+
+    #[tokio::test]
+    async fn test_deferred_message_item_absent_from_tool_only_stream() {
+        let tool_chunk = |function: Value| {
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function", "function": function}
+            ]}}]})
+        };
+        let backend = spawn_backend(sse_body(&[
+            tool_chunk(json!({"name": "ls", "arguments": "{}"})),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+        ]))
+        .await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                defer_message_item: true,
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(!events.iter().any(|e| {
+            e["item"]["type"] == "message"
+                || e["type"].as_str().unwrap().contains("content_part")
+                || e["type"].as_str().unwrap().contains("output_text")
+        }));
+        let added = events
+            .iter()
+            .find(|e| e["type"] == "response.output_item.added")
+            .unwrap();
+        assert_eq!(added["item"]["type"], "function_call");
+        assert_eq!(added["output_index"], 0);
+        let output = &completed_response(&events)["output"];
+        assert_eq!(output.as_array().unwrap().len(), 1);
+        assert_eq!(output[0]["type"], "function_call");
+
+        // Text still opens the message item, just before its first delta
+        let text_backend = spawn_backend(sse_body(&[text_chunk("Hi")])).await;
+        let app = test_app(
+            &text_backend.url,
+            ProxyConfig {
+                defer_message_item: true,
+                ..Default::default()
+            },
+        );
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let types: Vec<&str> = events
+            .iter()
+            .map(|e| e["type"].as_str().unwrap())
+            .skip_while(|t| *t != "response.output_item.added")
+            .take(3)
+            .collect();
+        assert_eq!(
+            types,
+            [
+                "response.output_item.added",
+                "response.content_part.added",
+                "response.output_text.delta"
+            ]
+        );
+        assert_eq!(completed_response(&events)["output"][0]["type"], "message");
+    }
+
+    #[tokio::test]
+    async fn test_text_done_carries_collected_annotations() {
+        let citation = json!({
+            "type": "url_citation",
+            "url": "https://example.com",
+            "title": "Example",
+            "start_index": 0,
+            "end_index": 5
+        });
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("Hello"),
+            json!({"choices": [{"index": 0, "delta": {"annotations": [citation.clone()]}}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        for event_type in ["response.output_text.done", "response.content_part.done"] {
+            let event = events
+                .iter()
+                .find(|e| e["type"] == event_type)
+                .unwrap_or_else(|| panic!("{event_type} event"));
+            assert_eq!(event["annotations"], json!([citation]));
+        }
+
+        let message = completed_response(&events)["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["type"] == "message")
+            .cloned()
+            .unwrap();
+        assert_eq!(message["content"][0]["annotations"], json!([citation]));
+    }
+
+    #[tokio::test]
+    async fn test_interleaved_reasoning_is_bracketed() {
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("first thought"),
+            text_chunk("Hello"),
+            reasoning_chunk("second thought"),
+            text_chunk(" world"),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let sequence: Vec<(String, String)> = events
+            .iter()
+            .filter(|e| {
+                let t = e["type"].as_str().unwrap_or("");
+                t.starts_with("response.reasoning_text") || t == "response.output_text.delta"
+            })
+            .map(|e| {
+                (
+                    e["type"].as_str().unwrap().to_string(),
+                    e["item_id"].as_str().unwrap_or("").to_string(),
+                )
+            })
+            .collect();
+
+        let types: Vec<&str> = sequence.iter().map(|(t, _)| t.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "response.reasoning_text.delta",
+                "response.reasoning_text.done",
+                "response.output_text.delta",
+                "response.reasoning_text.delta",
+                "response.reasoning_text.done",
+                "response.output_text.delta",
+            ]
+        );
+        // Resumed reasoning lives in a new item
+        assert_eq!(sequence[0].1, sequence[1].1);
+        assert_eq!(sequence[3].1, sequence[4].1);
+        assert_ne!(sequence[0].1, sequence[3].1);
+
+        let reasoning_done: Vec<_> = events
+            .iter()
+            .filter(|e| e["type"] == "response.reasoning_text.done")
+            .map(|e| e["text"].clone())
+            .collect();
+        assert_eq!(
+            reasoning_done,
+            [json!("first thought"), json!("second thought")]
+        );
+
+        let reasoning_items: Vec<_> = completed_response(&events)["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter(|item| item["type"] == "reasoning")
+            .cloned()
+            .collect();
+        assert_eq!(reasoning_items.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_and_message_use_distinct_output_indices() {
+        let backend = spawn_backend(sse_body(&[
+            reasoning_chunk("thinking"),
+            text_chunk("Answer"),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let mut index_by_item: std::collections::HashMap<String, u64> = Default::default();
+        for event in &events {
+            let (Some(item_id), Some(index)) =
+                (event["item_id"].as_str(), event["output_index"].as_u64())
+            else {
+                continue;
+            };
+            let previous = index_by_item.insert(item_id.to_string(), index);
+            assert!(
+                previous.is_none_or(|p| p == index),
+                "{item_id} changed output_index"
+            );
+        }
+        assert_eq!(index_by_item.len(), 2);
+        let mut indices: Vec<u64> = index_by_item.values().copied().collect();
+        indices.sort();
+        indices.dedup();
+        assert_eq!(indices.len(), 2, "items share an output_index");
+
+        // The reasoning item is announced before its first delta
+        let added = events
+            .iter()
+            .position(|e| {
+                e["type"] == "response.output_item.added" && e["item"]["type"] == "reasoning"
+            })
+            .unwrap();
+        let first_delta = events
+            .iter()
+            .position(|e| e["type"] == "response.reasoning_text.delta")
+            .unwrap();
+        assert!(added < first_delta);
+
+        // Final output order follows the streamed indices
+        let output = completed_response(&events)["output"].clone();
+        for (position, item) in output.as_array().unwrap().iter().enumerate() {
+            let id = item["id"].as_str().unwrap();
+            assert_eq!(index_by_item[id], position as u64);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_returns_raw_backend_lines() {
+        let raw = sse_body(&[text_chunk("Hello"), reasoning_chunk("hmm")]);
+        let backend = spawn_backend(raw.clone()).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                allow_backend_passthrough: true,
+                ..Default::default()
+            },
+        );
+
+        let mut headers = auth_headers();
+        headers.insert("x-passthrough-backend", "true".parse().unwrap());
+        let response = send_with_headers(&app, headers, request_body(json!({}))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&bytes), raw);
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_ignored_for_ensemble_requests() {
+        let logs = captured_logs();
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                allow_backend_passthrough: true,
+                ensembles: vec![(
+                    "race".to_string(),
+                    vec!["model-a".to_string(), "model-b".to_string()],
+                )],
+                ..Default::default()
+            },
+        );
+
+        let mut headers = auth_headers();
+        headers.insert("x-passthrough-backend", "true".parse().unwrap());
+        let events = collect_events(
+            send_with_headers(&app, headers, request_body(json!({"model": "race"}))).await,
+        )
+        .await;
+        assert_eq!(events[0]["type"], "response.created");
+        assert_eq!(
+            completed_response(&events)["output"][0]["content"][0]["text"],
+            "Hello"
+        );
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("x-passthrough-backend ignored for ensemble")));
+    }
+
+    #[tokio::test]
+    async fn test_proxy_debug_block_on_request() {
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("Hel"),
+            text_chunk("lo"),
+            text_chunk("!"),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(completed_response(&events).get("_proxy_debug").is_none());
+
+        let mut headers = auth_headers();
+        headers.insert("x-proxy-debug", "true".parse().unwrap());
+        let events =
+            collect_events(send_with_headers(&app, headers, request_body(json!({}))).await).await;
+        let debug = &completed_response(&events)["_proxy_debug"];
+        // Three chunks plus the `[DONE]` marker
+        assert_eq!(debug["backend_chunk_count"], 4);
+        let ttft = debug["ttft_ms"].as_u64().unwrap();
+        let total = debug["total_ms"].as_u64().unwrap();
+        assert!(ttft <= total && total < 10_000);
+        // Only the final response carries it
+        let created = events
+            .iter()
+            .find(|e| e["type"] == "response.created")
+            .unwrap();
+        assert!(created["response"].get("_proxy_debug").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_oversized_backend_event_fails_stream() {
+        let endless_line = format!("data: {{\"choices\": [{}", "x".repeat(4096));
+        let backend =
+            spawn_backend(format!("data: {}\n\n{}", text_chunk("Hi"), endless_line)).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                max_sse_event_bytes: Some(1024),
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(events.iter().any(|e| e["type"] == "response.failed"));
+        assert_eq!(
+            completed_response(&events)["error"]["code"],
+            "backend_oversized_event"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_passthrough_header_ignored_when_disabled() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let mut headers = auth_headers();
+        headers.insert("x-passthrough-backend", "true".parse().unwrap());
+        let events =
+            collect_events(send_with_headers(&app, headers, request_body(json!({}))).await).await;
+        assert_eq!(events[0]["type"], "response.created");
+    }
+
+    #[tokio::test]
+    async fn test_interim_usage_surfaced_when_requested() {
+        let backend = spawn_backend(sse_body(&[
+            json!({"choices": [{"index": 0, "delta": {"content": "a"}}],
+                   "usage": {"prompt_tokens": 10, "completion_tokens": 1}}),
+            json!({"choices": [{"index": 0, "delta": {"content": "b"}}],
+                   "usage": {"prompt_tokens": 10, "completion_tokens": 2}}),
+            json!({"choices": [], "usage": {"prompt_tokens": 10, "completion_tokens": 3}}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(
+            send(
+                &app,
+                request_body(json!({"stream_options": {"include_usage": true}})),
+            )
+            .await,
+        )
+        .await;
+
+        let interim: Vec<_> = events
+            .iter()
+            .filter(|e| e["type"] == "response.usage.updated")
+            .map(|e| e["usage"]["output_tokens"].as_u64().unwrap())
+            .collect();
+        assert_eq!(interim, [1, 2, 3]);
+
+        let usage = &completed_response(&events)["usage"];
+        assert_eq!(usage["input_tokens"], 10);
+        assert_eq!(usage["output_tokens"], 3);
+        assert_eq!(usage["total_tokens"], 13);
+
+        // Without include_usage no interim events are sent
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(!events.iter().any(|e| e["type"] == "response.usage.updated"));
+    }
+
+    #[tokio::test]
+    async fn test_legacy_choice_text_is_emitted() {
+        let backend = spawn_backend(sse_body(&[
+            json!({"choices": [{"index": 0, "text": "Hello"}]}),
+            json!({"choices": [{"index": 0, "text": " world", "finish_reason": "stop"}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let deltas: Vec<_> = events
+            .iter()
+            .filter(|e| e["type"] == "response.output_text.delta")
+            .map(|e| e["delta"].as_str().unwrap())
+            .collect();
+        assert_eq!(deltas, ["Hello", " world"]);
+        let done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .unwrap();
+        assert_eq!(done["text"], "Hello world");
+    }
+
+    #[tokio::test]
+    async fn test_max_output_tokens_clamped_to_configured_limits() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let config = ProxyConfig {
+            max_output_tokens_floor: Some(64),
+            max_output_tokens_ceiling: Some(4096),
+            ..Default::default()
+        };
+        let app = test_app(&backend.url, config);
+
+        send(&app, request_body(json!({"max_output_tokens": 50_000}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 4096);
+
+        send(&app, request_body(json!({"max_output_tokens": 8}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 64);
+
+        send(&app, request_body(json!({"max_output_tokens": 1000}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 1000);
+
+        // Omitting the limit does not escape the ceiling
+        send(&app, request_body(json!({}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 4096);
+
+        // A floor above the ceiling cannot lift requests past the cost cap
+        let config = ProxyConfig {
+            max_output_tokens_floor: Some(8192),
+            max_output_tokens_ceiling: Some(4096),
+            ..Default::default()
+        };
+        assert!(config.max_output_tokens_limits_conflict());
+        let app = test_app(&backend.url, config);
+        send(&app, request_body(json!({"max_output_tokens": 100}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 4096);
+        send(&app, request_body(json!({}))).await;
+        assert_eq!(backend.last_request()["max_tokens"], 4096);
+    }
+
+    #[tokio::test]
+    async fn test_registered_prompt_template_is_expanded() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let mut app = test_app(&backend.url, ProxyConfig::default());
+        let mut registry = PromptRegistry::new();
+        registry.insert(
+            "greeter".to_string(),
+            PromptTemplate {
+                instructions: Some("You greet {{name}} in {{language}}.".to_string()),
+                input: None,
+            },
+        );
+        app.prompt_registry = Arc::new(registry);
+
+        let response = send(
+            &app,
+            request_body(json!({"prompt": {
+                "id": "greeter",
+                "variables": {"name": "Ada", "language": "French"}
+            }})),
+        )
+        .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        collect_events(response).await;
+
+        let messages = &backend.last_request()["messages"];
+        assert_eq!(messages[0]["role"], "system");
+        assert_eq!(messages[0]["content"], "You greet Ada in French.");
+
+        // Unknown ids are still rejected
+        let result = create_response(
+            State(app.clone()),
+            Version::HTTP_11,
+            auth_headers(),
+            request_body(json!({"prompt": {"id": "missing"}})),
+        )
+        .await;
+        assert_eq!(
+            result.err(),
+            Some((StatusCode::BAD_REQUEST, "prompt_reference_not_supported"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_connection_header_omitted_on_http2() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let http1 = send(&app, request_body(json!({}))).await;
+        assert_eq!(http1.headers()["connection"], "keep-alive");
+
+        let http2 = create_response(
+            State(app.clone()),
+            Version::HTTP_2,
+            auth_headers(),
+            request_body(json!({})),
+        )
+        .await
+        .into_response();
+        assert_eq!(http2.status(), StatusCode::OK);
+        assert!(http2.headers().get("connection").is_none());
+        assert_eq!(
+            http2.headers()["content-type"],
+            "text/event-stream; charset=utf-8"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_accept_streams_json_lines() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hel"), text_chunk("lo")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let mut headers = auth_headers();
+        headers.insert("accept", "application/x-ndjson".parse().unwrap());
+
+        let response = send_with_headers(&app, headers, request_body(json!({}))).await;
+        assert_eq!(response.headers()["content-type"], "application/x-ndjson");
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+
+        assert!(body.ends_with('\n'));
+        assert!(!body.contains("data:"));
+        let events: Vec<Value> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("one JSON object per line"))
+            .collect();
+        assert_eq!(events[0]["type"], "response.created");
+        assert_eq!(events.last().unwrap()["type"], "response.done");
+        let sequence: Vec<u64> = events
+            .iter()
+            .map(|e| e["sequence_number"].as_u64().unwrap())
+            .collect();
+        assert!(sequence.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        let text_done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .expect("output_text.done event");
+        assert_eq!(text_done["text"], "Hello");
+    }
+
+    #[tokio::test]
+    async fn test_slow_client_policy_when_event_channel_fills() {
+        let chunks: Vec<Value> = (0..20).map(|i| text_chunk(&format!("w{i} "))).collect();
+        let backend = spawn_backend(sse_body(&chunks)).await;
+        let config = |policy| ProxyConfig {
+            event_channel_capacity: Some(1),
+            slow_client_policy: policy,
+            ..Default::default()
+        };
+        let text_deltas = |events: &[Value]| {
+            events
+                .iter()
+                .filter(|e| e["type"] == "response.output_text.delta")
+                .count()
+        };
+
+        // Nobody reads the body until the backend has long finished
+        let app = test_app(&backend.url, config(SlowClientPolicy::Drop));
+        let response = send(&app, request_body(json!({}))).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let events = collect_events(response).await;
+        assert!(text_deltas(&events) < 20);
+        let terminal = completed_response(&events);
+        assert_eq!(terminal["status"], "incomplete");
+        assert_eq!(terminal["incomplete_details"]["reason"], "slow_client");
+        assert_eq!(events.last().unwrap()["type"], "response.done");
+        // What survives is still a valid lifecycle: gapless sequence numbers, and
+        // every item announced before its first event and closed afterwards
+        let sequence: Vec<u64> = events
+            .iter()
+            .map(|e| e["sequence_number"].as_u64().unwrap())
+            .collect();
+        assert!(sequence.windows(2).all(|pair| pair[1] == pair[0] + 1));
+        let mut open_items = Vec::new();
+        for event in &events {
+            let Some(item_id) = event["item_id"].as_str() else {
+                continue;
+            };
+            match event["type"].as_str().unwrap() {
+                "response.output_item.added" => open_items.push(item_id),
+                "response.output_item.done" => {
+                    assert!(open_items.contains(&item_id), "{event}");
+                    open_items.retain(|open| *open != item_id);
+                }
+                _ => assert!(open_items.contains(&item_id), "{event}"),
+            }
+        }
+        assert!(open_items.is_empty(), "unclosed items: {open_items:?}");
+        for part_event in ["response.content_part.added", "response.content_part.done"] {
+            assert!(
+                events.iter().any(|e| e["type"] == part_event),
+                "{part_event}"
+            );
+        }
+        let full_text: String = (0..20).map(|i| format!("w{i} ")).collect();
+        let text_done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .expect("output_text.done event");
+        assert_eq!(text_done["text"], full_text.as_str());
+        assert!(
+            app.metrics
+                .slow_client_dropped_events
+                .load(Ordering::Relaxed)
+                > 0
+        );
+
+        // Blocking waits for the reader instead, losing nothing
+        let app = test_app(&backend.url, config(SlowClientPolicy::Block));
+        let response = send(&app, request_body(json!({}))).await;
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let events = collect_events(response).await;
+        assert_eq!(text_deltas(&events), 20);
+        assert_eq!(completed_response(&events)["status"], "completed");
+    }
+
+    #[tokio::test]
+    async fn test_early_response_created_precedes_backend_response() {
+        let gate = Arc::new(Notify::new());
+        let backend =
+            spawn_gated_backend(sse_body(&[text_chunk("late")]), Some(Arc::clone(&gate))).await;
+        let config = ProxyConfig {
+            early_response_created: true,
+            ..Default::default()
+        };
+        let app = test_app(&backend.url, config);
+
+        // The handler returns while the backend is still holding its response
+        let response = send(&app, request_body(json!({"temperature": 0.5}))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        let mut body = response.into_body().into_data_stream();
+        let first = body.next().await.unwrap().unwrap();
+        let first = String::from_utf8_lossy(&first);
+        let data = first
+            .lines()
+            .find_map(|line| line.strip_prefix("data: "))
+            .unwrap();
+        let created: Value = serde_json::from_str(data).unwrap();
+        assert_eq!(created["type"], "response.created");
+        assert_eq!(created["response"]["status"], "in_progress");
+        assert_eq!(created["response"]["temperature"], 0.5);
+
+        gate.notify_one();
+        let mut rest = String::new();
+        while let Some(chunk) = body.next().await {
+            rest.push_str(&String::from_utf8_lossy(&chunk.unwrap()));
+        }
+        assert!(rest.contains("\"response.completed\""));
+        assert!(rest.contains("late"));
+    }
+
+    #[tokio::test]
+    async fn test_early_response_created_reports_backend_failure_in_stream() {
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig {
+                early_response_created: true,
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let types: Vec<_> = events.iter().map(|e| e["type"].as_str().unwrap()).collect();
+        assert_eq!(types, ["response.created", "response.failed"]);
+        assert_eq!(
+            events[1]["response"]["error"]["code"],
+            "backend_unavailable"
+        );
+        assert_eq!(events[0]["response"]["id"], events[1]["response"]["id"]);
+    }
+
+    #[tokio::test]
+    async fn test_duplicated_tool_argument_fragment_appended_once() {
+        let tool_chunk = |function: Value| {
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function", "function": function}
+            ]}}]})
+        };
+        let backend = spawn_backend(sse_body(&[
+            tool_chunk(json!({"name": "read_file", "arguments": ""})),
+            tool_chunk(json!({"arguments": "{\"path\": \"a.txt\""})),
+            tool_chunk(json!({"arguments": "{\"path\": \"a.txt\""})),
+            tool_chunk(json!({"arguments": ", \"opts\": {}"})),
+            tool_chunk(json!({"arguments": "}"})),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let call = completed_response(&events)["output"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .find(|item| item["type"] == "function_call")
+            .expect("function call item")
+            .clone();
+        assert_eq!(call["arguments"], r#"{"path": "a.txt", "opts": {}}"#);
+        let args: Value = serde_json::from_str(call["arguments"].as_str().unwrap()).unwrap();
+        assert_eq!(args["path"], "a.txt");
+    }
+
+    #[tokio::test]
+    async fn test_sse_frames_carry_sequence_ids() {
+        let backend = spawn_backend(sse_body(&[text_chunk("hi")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let response = send(&app, request_body(json!({}))).await;
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/event-stream; charset=utf-8"
+        );
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let raw = String::from_utf8_lossy(&bytes);
+
+        let mut frames = 0;
+        for frame in raw.split("\n\n").filter(|f| !f.trim().is_empty()) {
+            let id = frame
+                .lines()
+                .find_map(|line| line.strip_prefix("id: "))
+                .expect("frame has an id line");
+            let data = frame
+                .lines()
+                .find_map(|line| line.strip_prefix("data: "))
+                .expect("frame has data");
+            let event: Value = serde_json::from_str(data).unwrap();
+            assert_eq!(id, event["sequence_number"].to_string());
+            frames += 1;
+        }
+        assert!(frames > 3);
+    }
+
+    #[tokio::test]
+    async fn test_last_event_id_reconnect_replays_remaining_events() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello"), text_chunk(" again")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                sse_replay_window: Some(std::time::Duration::from_secs(30)),
+                ..Default::default()
+            },
+        );
+        let body = request_body(json!({}));
+
+        // Read a couple of frames, then drop the connection
+        let response = send(&app, body.clone()).await;
+        let mut stream = response.into_body().into_data_stream();
+        let mut seen = String::new();
+        while seen.matches("id: ").count() < 2 {
+            seen.push_str(&String::from_utf8_lossy(
+                &stream.next().await.unwrap().unwrap(),
+            ));
+        }
+        drop(stream);
+        let last_id: u32 = seen
+            .lines()
+            .filter_map(|line| line.strip_prefix("id: "))
+            .next_back()
+            .unwrap()
+            .parse()
+            .unwrap();
+
+        let mut headers = auth_headers();
+        headers.insert("last-event-id", last_id.to_string().parse().unwrap());
+        let events = collect_events(send_with_headers(&app, headers, body).await).await;
+
+        let sequences: Vec<u64> = events
+            .iter()
+            .map(|e| e["sequence_number"].as_u64().unwrap())
+            .collect();
+        assert_eq!(sequences[0], u64::from(last_id) + 1);
+        assert!(sequences.windows(2).all(|w| w[1] == w[0] + 1));
+        assert_eq!(
+            completed_response(&events)["output"][0]["content"][0]["text"],
+            "Hello again"
+        );
+        // Generation was resumed, not restarted
+        assert_eq!(backend.requests.lock().unwrap().len(), 1);
+
+        // Unknown ids start fresh
+        let mut headers = auth_headers();
+        headers.insert("last-event-id", "3".parse().unwrap());
+        let other = request_body(json!({"temperature": 0.2}));
+        let events = collect_events(send_with_headers(&app, headers, other).await).await;
+        assert_eq!(events[0]["type"], "response.created");
+        assert_eq!(backend.requests.lock().unwrap().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_trailing_whitespace_trimmed_when_enabled() {
+        let backend = spawn_backend(sse_body(&[text_chunk("Done."), text_chunk("\n\n  ")])).await;
+        let final_text = |events: &[Value]| {
+            completed_response(events)["output"][0]["content"][0]["text"]
+                .as_str()
+                .unwrap()
+                .to_string()
+        };
+
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                trim_trailing_whitespace: true,
+                ..Default::default()
+            },
+        );
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert_eq!(final_text(&events), "Done.");
+        let done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .unwrap();
+        assert_eq!(done["text"], "Done.");
+
+        // Off by default: trailing formatting is preserved
+        let app = test_app(&backend.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert_eq!(final_text(&events), "Done.\n\n  ");
+    }
+
+    #[tokio::test]
+    async fn test_stop_sequence_does_not_truncate_tool_arguments() {
+        let tool_chunk = |function: Value| {
+            json!({"choices": [{"index": 0, "delta": {"tool_calls": [
+                {"index": 0, "id": "call_1", "type": "function", "function": function}
+            ]}}]})
+        };
+        let backend = spawn_backend(sse_body(&[
+            tool_chunk(json!({"name": "write_file", "arguments": "{\"text\": \"a END b\""})),
+            tool_chunk(json!({"arguments": "}"})),
+            json!({"choices": [{"index": 0, "delta": {}, "finish_reason": "tool_calls"}]}),
+        ]))
+        .await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let events = collect_events(send(&app, request_body(json!({"stop": ["END"]}))).await).await;
+
+        assert_eq!(backend.last_request()["stop"], json!(["END"]));
+        let args_done = events
+            .iter()
+            .find(|e| e["type"] == "response.function_call_arguments.done")
+            .expect("arguments done event");
+        assert_eq!(args_done["arguments"], r#"{"text": "a END b"}"#);
+    }
+
+    #[tokio::test]
+    async fn test_local_stop_spanning_two_deltas() {
+        let app_for = |url: &str| {
+            test_app(
+                url,
+                ProxyConfig {
+                    enforce_stop_locally: true,
+                    ..Default::default()
+                },
+            )
+        };
+        let streamed_and_done = |events: &[Value]| {
+            let streamed: String = events
+                .iter()
+                .filter(|e| e["type"] == "response.output_text.delta")
+                .map(|e| e["delta"].as_str().unwrap())
+                .collect();
+            let done = events
+                .iter()
+                .find(|e| e["type"] == "response.output_text.done")
+                .expect("output_text.done event")["text"]
+                .as_str()
+                .unwrap()
+                .to_string();
+            (streamed, done)
+        };
+
+        // "EN" could start "END", so it waits for the next delta
+        let backend =
+            spawn_backend(sse_body(&[text_chunk("Hello EN"), text_chunk("D leaked")])).await;
+        let body = request_body(json!({"stop": ["END"]}));
+        let events = collect_events(send(&app_for(&backend.url), body).await).await;
+        let (streamed, done) = streamed_and_done(&events);
+        assert_eq!(streamed, "Hello ");
+        assert_eq!(done, "Hello ");
+
+        // A held-back prefix that never completes is released as text
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello EN"), text_chunk("TER")])).await;
+        let body = request_body(json!({"stop": ["END"]}));
+        let events = collect_events(send(&app_for(&backend.url), body).await).await;
+        assert_eq!(
+            streamed_and_done(&events),
+            ("Hello ENTER".to_string(), "Hello ENTER".to_string())
+        );
+        let backend = spawn_backend(sse_body(&[text_chunk("Hello E")])).await;
+        let body = request_body(json!({"stop": ["END"]}));
+        let events = collect_events(send(&app_for(&backend.url), body).await).await;
+        assert_eq!(
+            streamed_and_done(&events),
+            ("Hello E".to_string(), "Hello E".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_local_stop_cuts_through_xml_buffer() {
+        let backend = spawn_backend(sse_body(&[
+            text_chunk("Plan: "),
+            text_chunk("<function=read_file><parameter=path>a"),
+            text_chunk(".txt END</parameter></function> leaked"),
+            text_chunk(" more leaked"),
+        ]))
+        .await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                enforce_stop_locally: true,
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({"stop": "END"}))).await).await;
+
+        let streamed: String = events
+            .iter()
+            .filter(|e| e["type"] == "response.output_text.delta")
+            .map(|e| e["delta"].as_str().unwrap())
+            .collect();
+        let expected = "Plan: <function=read_file><parameter=path>a.txt ";
+        assert_eq!(streamed, expected);
+        let text_done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .expect("output_text.done event");
+        assert_eq!(text_done["text"], expected);
+        assert!(!events
+            .iter()
+            .any(|e| e["type"] == "response.function_call_arguments.done"));
+        assert_eq!(completed_response(&events)["status"], "completed");
+        assert_eq!(app.metrics.xml_flushed_as_text.load(Ordering::Relaxed), 1);
+        assert_eq!(app.metrics.xml_conversions.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_xml_tool_call_counters() {
+        let converted = spawn_backend(sse_body(&[
+            text_chunk("<function=read_file>"),
+            text_chunk("<parameter=path>a.txt</parameter></function>"),
+        ]))
+        .await;
+        let app = test_app(&converted.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert!(events
+            .iter()
+            .any(|e| e["type"] == "response.function_call_arguments.done"));
+        assert_eq!(app.metrics.xml_buffering_started.load(Ordering::Relaxed), 1);
+        assert_eq!(app.metrics.xml_conversions.load(Ordering::Relaxed), 1);
+        assert_eq!(app.metrics.xml_flushed_as_text.load(Ordering::Relaxed), 0);
+
+        // An unterminated block is flushed as text at end of stream
+        let unterminated = spawn_backend(sse_body(&[
+            text_chunk("See "),
+            text_chunk("<function=oops and more"),
+        ]))
+        .await;
+        let app = test_app(&unterminated.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        let streamed: String = events
+            .iter()
+            .filter(|e| e["type"] == "response.output_text.delta")
+            .map(|e| e["delta"].as_str().unwrap())
+            .collect();
+        assert_eq!(streamed, "See <function=oops and more");
+        assert_eq!(app.metrics.xml_buffering_started.load(Ordering::Relaxed), 1);
+        assert_eq!(app.metrics.xml_flushed_as_text.load(Ordering::Relaxed), 1);
+        assert_eq!(app.metrics.xml_conversions.load(Ordering::Relaxed), 0);
+    }
+
+    #[tokio::test]
+    async fn test_json_tool_calls_in_text_become_function_calls() {
+        let streamed_text = |events: &[Value]| -> String {
+            events
+                .iter()
+                .filter(|e| e["type"] == "response.output_text.delta")
+                .map(|e| e["delta"].as_str().unwrap())
+                .collect()
+        };
+        let converted_call = |events: &[Value]| {
+            let done = events
+                .iter()
+                .find(|e| e["type"] == "response.function_call_arguments.done")
+                .expect("converted function call");
+            (done["name"].clone(), done["arguments"].clone())
+        };
+
+        // A bare object whose opening is split across deltas
+        let bare = spawn_backend(sse_body(&[
+            text_chunk("Checking. {\"tool"),
+            text_chunk("_calls\": [{\"type\": \"function\", \"function\": "),
+            text_chunk(
+                "{\"name\": \"read_file\", \"arguments\": \"{\\\"path\\\": \\\"a.txt\\\"}\"}}]}",
+            ),
+        ]))
+        .await;
+        let app = test_app(&bare.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert_eq!(
+            converted_call(&events),
+            (json!("read_file"), json!(r#"{"path": "a.txt"}"#))
+        );
+        assert!(!streamed_text(&events).contains("tool_calls"));
+        assert_eq!(app.metrics.json_conversions.load(Ordering::Relaxed), 1);
+
+        // A fenced block, with ordinary JSON earlier in the text left alone
+        let fenced = spawn_backend(sse_body(&[
+            text_chunk("Config is {\"port\": 1}. Now:\n``"),
+            text_chunk("`json\n{\"tool_calls\": [{\"name\": \"ls\", "),
+            text_chunk("\"arguments\": {\"dir\": \".\"}}]}\n```"),
+        ]))
+        .await;
+        let app = test_app(&fenced.url, ProxyConfig::default());
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+        assert_eq!(
+            converted_call(&events),
+            (json!("ls"), json!(r#"{"dir":"."}"#))
+        );
+        assert!(streamed_text(&events).starts_with("Config is {\"port\": 1}."));
+        assert!(!streamed_text(&events).contains("```"));
+        let text_done = events
+            .iter()
+            .find(|e| e["type"] == "response.output_text.done")
+            .unwrap();
+        assert_eq!(text_done["text"], "Config is {\"port\": 1}. Now:");
+    }
+
+    #[tokio::test]
+    async fn test_native_and_xml_calls_share_added_item_shape() {
+        let added_call_item = |events: Vec<Value>| {
+            let mut item = events
+                .into_iter()
+                .find(|e| {
+                    e["type"] == "response.output_item.added"
+                        && e["item"]["type"] == "function_call"
+                })
+                .expect("function_call output_item.added")["item"]
+                .clone();
+            // Identifiers legitimately differ between the two paths
+            for key in ["id", "call_id"] {
+                item.as_object_mut().unwrap().remove(key);
+            }
+            item
+        };
+
+        let native = spawn_backend(sse_body(&[json!({"choices": [{
+            "index": 0,
+            "delta": {"tool_calls": [{
+                "index": 0,
+                "id": "call_native",
+                "type": "function",
+                "function": {"name": "read_file", "arguments": "{\"path\":\"a.txt\"}"}
+            }]}
+        }]})]))
+        .await;
+        let app = test_app(&native.url, ProxyConfig::default());
+        let native_item =
+            added_call_item(collect_events(send(&app, request_body(json!({}))).await).await);
+
+        let xml = spawn_backend(sse_body(&[text_chunk(
+            "<function=read_file><parameter=path>a.txt</parameter></function>",
+        )]))
+        .await;
+        let app = test_app(&xml.url, ProxyConfig::default());
+        let xml_item =
+            added_call_item(collect_events(send(&app, request_body(json!({}))).await).await);
+
+        assert_eq!(native_item["arguments"], "");
+        assert_eq!(native_item, xml_item);
+    }
+
+    #[tokio::test]
+    async fn test_penalties_outside_range_are_rejected() {
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig::default(),
+        );
+        let rejected = |extra: Value| {
+            let app = app.clone();
+            async move {
+                create_response(
+                    State(app),
+                    Version::HTTP_11,
+                    auth_headers(),
+                    request_body(extra),
+                )
+                .await
+                .err()
+            }
+        };
+
+        assert_eq!(
+            rejected(json!({"frequency_penalty": 2.5})).await,
+            Some((StatusCode::BAD_REQUEST, "invalid_frequency_penalty"))
+        );
+        assert_eq!(
+            rejected(json!({"presence_penalty": -3.0})).await,
+            Some((StatusCode::BAD_REQUEST, "invalid_presence_penalty"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_logit_bias_validated_then_forwarded() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let bias = json!({"50256": -100, "1234": 2.5});
+        collect_events(send(&app, request_body(json!({"logit_bias": bias}))).await).await;
+        assert_eq!(backend.last_request()["logit_bias"], bias);
+
+        for invalid in [
+            json!({"50256": 101}),
+            json!({"50256": "-5"}),
+            json!({"hello": 1}),
+            json!([1, 2]),
+        ] {
+            let result = create_response(
+                State(app.clone()),
+                Version::HTTP_11,
+                auth_headers(),
+                request_body(json!({"logit_bias": invalid})),
+            )
+            .await;
+            assert_eq!(
+                result.err(),
+                Some((StatusCode::BAD_REQUEST, "invalid_logit_bias"))
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn test_missing_model_retried_with_fallback() {
+        let requested_models = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requested_models);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(payload): Json<Value>| {
+                let model = payload["model"].as_str().unwrap_or("").to_string();
+                recorded.lock().unwrap().push(model.clone());
+                async move {
+                    if model == "test-model" {
+                        (StatusCode::NOT_FOUND, "no such model").into_response()
+                    } else {
+                        (
+                            [("content-type", "text/event-stream")],
+                            sse_body(&[text_chunk("from fallback")]),
+                        )
+                            .into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        for early_response_created in [false, true] {
+            let app = test_app(
+                &url,
+                ProxyConfig {
+                    fallback_model: Some("backup-model".to_string()),
+                    early_response_created,
+                    ..Default::default()
+                },
+            );
+            let events = collect_events(send(&app, request_body(json!({}))).await).await;
+            let completed = completed_response(&events);
+            assert_eq!(completed["status"], "completed");
+            assert_eq!(completed["model"], "backup-model");
+            assert_eq!(
+                completed["output"][0]["content"][0]["text"],
+                "from fallback"
+            );
+        }
+        assert_eq!(
+            *requested_models.lock().unwrap(),
+            ["test-model", "backup-model", "test-model", "backup-model"]
+        );
+    }
+
+    fn image_input() -> Value {
+        json!({"input": [{
+            "type": "message",
+            "role": "user",
+            "content": [
+                {"type": "input_text", "text": "What is this?"},
+                {"type": "input_image", "image_url": {"url": "https://x/cat.png"}}
+            ]
+        }]})
+    }
+
+    #[tokio::test]
+    async fn test_images_stripped_for_text_only_model() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                vision_fallback: VisionFallback::Strip,
+                ..Default::default()
+            },
+        );
+
+        collect_events(send(&app, request_body(image_input())).await).await;
+        let content = &backend.last_request()["messages"][0]["content"];
+        assert_eq!(content, &json!("What is this?\n[image omitted]"));
+    }
+
+    #[tokio::test]
+    async fn test_images_rejected_for_text_only_model() {
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig {
+                vision_fallback: VisionFallback::Reject,
+                ..Default::default()
+            },
+        );
+
+        let result = create_response(
+            State(app),
+            Version::HTTP_11,
+            auth_headers(),
+            request_body(image_input()),
+        )
+        .await;
+        assert_eq!(
+            result.err(),
+            Some((StatusCode::BAD_REQUEST, "model_lacks_vision"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_penalties_within_range_are_forwarded() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(&backend.url, ProxyConfig::default());
+
+        let body = request_body(json!({"frequency_penalty": -2.0, "presence_penalty": 2.0}));
+        collect_events(send(&app, body).await).await;
+
+        assert_eq!(backend.last_request()["frequency_penalty"], -2.0);
+        assert_eq!(backend.last_request()["presence_penalty"], 2.0);
+    }
+
+    #[tokio::test]
+    async fn test_extra_body_merged_only_when_enabled() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let body =
+            || request_body(json!({"extra_body": {"top_k": 40, "model": "evil", "min_p": 0.1}}));
+
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                allow_extra_body: true,
+                ..Default::default()
+            },
+        );
+        collect_events(send(&app, body()).await).await;
+        let sent = backend.last_request();
+        assert_eq!(sent["top_k"], 40);
+        assert_eq!(sent["min_p"], 0.1);
+        assert_eq!(sent["model"], "test-model");
+
+        let app = test_app(&backend.url, ProxyConfig::default());
+        collect_events(send(&app, body()).await).await;
+        assert!(backend.last_request().get("top_k").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_html_200_from_backend_is_reported_as_failure() {
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(|| async {
+                (
+                    [("content-type", "text/html; charset=utf-8")],
+                    "<html><body>Please sign in</body></html>",
+                )
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig::default(),
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["type"], "response.failed");
+        assert_eq!(
+            events[0]["response"]["error"]["code"],
+            "backend_unexpected_content_type"
+        );
+        tokio::task::yield_now().await;
+        assert_eq!(app.circuit_breaker.read().await.consecutive_failures, 1);
+    }
+
+    #[tokio::test]
+    async fn test_echoed_metadata_is_bounded_and_redacted() {
+        let backend = spawn_backend(sse_body(&[text_chunk("ok")])).await;
+        let app = test_app(
+            &backend.url,
+            ProxyConfig {
+                metadata_echo_max_bytes: Some(128),
+                ..Default::default()
+            },
+        );
+        let metadata = json!({
+            "trace_id": "abc",
+            "api_key": "sk-live-123",
+            "blob": "x".repeat(4096),
+        });
+
+        let events =
+            collect_events(send(&app, request_body(json!({"metadata": metadata}))).await).await;
+        let created = &events
+            .iter()
+            .find(|e| e["type"] == "response.created")
+            .unwrap()["response"]["metadata"];
+        assert_eq!(created, &json!({"trace_id": "abc", "api_key": "***"}));
+        assert!(created.to_string().len() <= 128);
+        assert_eq!(&completed_response(&events)["metadata"], created);
+    }
+
+    #[test]
+    fn test_token_totals_saturate_and_clamp() {
+        let usage = build_usage(u32::MAX - 1, u32::MAX - 1, 0);
+        assert_eq!(usage.total_tokens, u32::MAX);
+
+        assert_eq!(sane_token_count(1234, "prompt_tokens"), 1234);
+        assert_eq!(
+            sane_token_count(u32::MAX, "prompt_tokens"),
+            MAX_REPORTED_TOKENS
+        );
+    }
+
+    #[test]
+    fn test_metadata_log_tags_include_configured_keys() {
+        let keys = vec!["trace_id".to_string(), "tenant".to_string()];
+        let metadata = json!({
+            "trace_id": "abc-123",
+            "tenant": "x".repeat(100),
+            "secret": "not logged"
+        });
+
+        let tags = metadata_log_tags(Some(&metadata), &keys);
+        assert!(tags.starts_with(", trace_id=abc-123, tenant="));
+        assert!(tags.ends_with(&"x".repeat(MAX_METADATA_TAG_LEN)));
+        assert!(!tags.contains(&"x".repeat(MAX_METADATA_TAG_LEN + 1)));
+        assert!(!tags.contains("secret"));
+
+        let spaced = json!({"trace_id": "a b\nc"});
+        assert_eq!(metadata_log_tags(Some(&spaced), &keys), ", trace_id=a_b_c");
+        assert_eq!(metadata_log_tags(None, &keys), "");
+    }
+
+    #[tokio::test]
+    async fn test_tool_message_without_call_id_reports_error_code() {
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig::default(),
+        );
+        let body = request_body(json!({
+            "input": [
+                {"type": "message", "role": "user", "content": "hi"},
+                {"type": "message", "role": "tool", "content": "42"}
+            ]
+        }));
+
+        let response = create_response(State(app), Version::HTTP_11, auth_headers(), body)
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(&bytes[..], b"tool_message_missing_tool_call_id");
+    }
+
+    #[tokio::test]
+    async fn test_combined_prompt_budget_enforced() {
+        let app = test_app(
+            "http://127.0.0.1:1/v1/chat/completions",
+            ProxyConfig {
+                prompt_budget_bytes: Some(1_000),
+                ..Default::default()
+            },
+        );
+        // Each part is far below its own limit, but together they exceed the budget
+        let body = request_body(json!({
+            "instructions": "i".repeat(400),
+            "input": "x".repeat(400),
+            "tools": [{"type": "function", "name": "lookup", "description": "d".repeat(300),
+                       "parameters": {"type": "object"}}]
+        }));
+
+        let result = create_response(State(app), Version::HTTP_11, auth_headers(), body).await;
+        assert_eq!(
+            result.err(),
+            Some((StatusCode::PAYLOAD_TOO_LARGE, "prompt_budget_exceeded"))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_fallback_when_stream_is_empty() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(payload): Json<Value>| {
+                recorded.lock().unwrap().push(payload.clone());
+                async move {
+                    if payload["stream"] == true {
+                        // Streaming "works" but carries nothing usable
+                        ([("content-type", "text/event-stream")], "data: [DONE]\n\n".to_string())
+                            .into_response()
+                    } else {
+                        Json(json!({
+                            "choices": [{"index": 0, "finish_reason": "stop",
+                                         "message": {"role": "assistant", "content": "from fallback"}}],
+                            "usage": {"prompt_tokens": 4, "completion_tokens": 2}
+                        }))
+                        .into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                non_stream_fallback: true,
+                ..Default::default()
+            },
+        );
+
+        let events = collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let completed = completed_response(&events);
+        assert_eq!(
+            completed["output"][0]["content"][0]["text"],
+            "from fallback"
+        );
+        assert_eq!(completed["usage"]["output_tokens"], 2);
+        let requests = requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert_eq!(requests[1]["stream"], false);
+        assert!(requests[1].get("stream_options").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_non_stream_fallback_uses_served_model_and_records_failure() {
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let recorded = Arc::clone(&requests);
+        let router = Router::new().route(
+            "/v1/chat/completions",
+            post(move |Json(payload): Json<Value>| {
+                recorded.lock().unwrap().push(payload.clone());
+                async move {
+                    if payload["model"] == "test-model" {
+                        StatusCode::NOT_FOUND.into_response()
+                    } else if payload["stream"] == true {
+                        (
+                            [("content-type", "text/event-stream")],
+                            "data: [DONE]\n\n".to_string(),
+                        )
+                            .into_response()
+                    } else {
+                        (StatusCode::INTERNAL_SERVER_ERROR, "boom").into_response()
+                    }
+                }
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+        let app = test_app(
+            &format!("http://{addr}/v1/chat/completions"),
+            ProxyConfig {
+                non_stream_fallback: true,
+                fallback_model: Some("backup-model".to_string()),
+                ..Default::default()
+            },
+        );
+
+        collect_events(send(&app, request_body(json!({}))).await).await;
+
+        let last = requests.lock().unwrap().last().cloned().unwrap();
+        assert_eq!(last["stream"], false);
+        assert_eq!(last["model"], "backup-model");
+        tokio::task::yield_now().await;
+        assert_eq!(app.circuit_breaker.read().await.consecutive_failures, 1);
+    }
+}
@@ -1,9 +1,16 @@
+// The /admin/config json! literal lists every setting
+#![recursion_limit = "256"]
+
 use axum::{
     routing::{get, post},
     Router,
 };
 use log::info;
-use std::{env, sync::Arc, time::Duration};
+use std::{
+    env,
+    sync::{atomic::AtomicUsize, Arc},
+    time::Duration,
+};
 use tokio::sync::RwLock;
 
 // Import our modules
@@ -12,17 +19,42 @@ mod models;
 mod services;
 mod utils;
 
-use models::{App, CircuitBreakerState};
-use services::refresh_models_cache;
+use models::{App, CircuitBreakerState, ProxyConfig, ResponseStoreKind, StartupProbe};
+use services::{
+    join_backend_url, load_prompt_registry, probe_backend, refresh_models_cache,
+    InMemoryResponseStore, ReplayBuffer, ResponseStore, DEFAULT_BACKEND_PATH,
+};
 
 #[tokio::main]
 async fn main() {
     let _ = dotenvy::dotenv();
 
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    let config = ProxyConfig::from_env();
+
+    let strip_emoji = config.log_no_emoji;
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info"))
+        .format(move |buf, record| {
+            let timestamp = buf.timestamp();
+            utils::write_log_line(
+                buf,
+                timestamp,
+                record.level(),
+                record.target(),
+                &record.args().to_string(),
+                strip_emoji,
+            )
+        })
+        .init();
 
-    let backend_url = env::var("BACKEND_URL")
-        .unwrap_or_else(|_| "https://llm.chutes.ai/v1/chat/completions".into());
+    // `BACKEND_BASE_URL` + `BACKEND_PATH` take precedence over a full `BACKEND_URL`
+    let backend_url = match env::var("BACKEND_BASE_URL") {
+        Ok(base) if !base.trim().is_empty() => {
+            let path = env::var("BACKEND_PATH").unwrap_or_else(|_| DEFAULT_BACKEND_PATH.into());
+            join_backend_url(base.trim(), path.trim())
+        }
+        _ => env::var("BACKEND_URL")
+            .unwrap_or_else(|_| "https://llm.chutes.ai/v1/chat/completions".into()),
+    };
     let backend_timeout_secs = env::var("BACKEND_TIMEOUT_SECS")
         .ok()
         .and_then(|s| s.parse::<u64>().ok())
@@ -35,7 +67,20 @@ async fn main() {
     info!("🚀 OpenAI Responses Proxy for Chutes.ai starting...");
     info!("   Backend URL: {}", backend_url);
     info!("   Backend Timeout: {}s", backend_timeout_secs);
+    if let Some(limit) = config.backend_headers_timeout {
+        info!("   Backend Headers Timeout: {}s", limit.as_secs());
+    }
+    if config.max_output_tokens_limits_conflict() {
+        log::warn!(
+            "⚠️  MAX_OUTPUT_TOKENS_FLOOR ({:?}) exceeds MAX_OUTPUT_TOKENS_CEILING ({:?}); the ceiling wins",
+            config.max_output_tokens_floor,
+            config.max_output_tokens_ceiling
+        );
+    }
     info!("   Circuit Breaker: enabled");
+    if let Some(max) = config.max_sse_connections {
+        info!("   Max SSE Connections: {}", max);
+    }
     info!(
         "   Log Volume: {}",
         if log_volume_enabled {
@@ -52,6 +97,39 @@ async fn main() {
         }
     }
 
+    // Optional local prompt templates for `prompt` references
+    let prompt_registry = match config.prompt_registry_path.as_deref() {
+        Some(path) => match load_prompt_registry(path) {
+            Ok(registry) => {
+                info!(
+                    "   Prompt Registry: {} template(s) from {}",
+                    registry.len(),
+                    path
+                );
+                registry
+            }
+            Err(e) => {
+                log::warn!("⚠️  Failed to load prompt registry: {}", e);
+                Default::default()
+            }
+        },
+        None => Default::default(),
+    };
+
+    let replay_buffer = config.sse_replay_window.map(|window| {
+        info!("   SSE Replay Window: {}s", window.as_secs());
+        Arc::new(ReplayBuffer::new(window))
+    });
+
+    let response_store: Option<Arc<dyn ResponseStore>> = match config.response_store {
+        ResponseStoreKind::Off => None,
+        ResponseStoreKind::Memory => {
+            let max_entries = config.response_store_max_entries();
+            info!("   Response Store: in-memory, {} response(s)", max_entries);
+            Some(Arc::new(InMemoryResponseStore::new(max_entries)))
+        }
+    };
+
     let models_cache = Arc::new(RwLock::new(None));
     let circuit_breaker = Arc::new(RwLock::new(CircuitBreakerState::new(true)));
 
@@ -66,15 +144,33 @@ async fn main() {
         backend_url: backend_url.clone(),
         models_cache: models_cache.clone(),
         circuit_breaker: circuit_breaker.clone(),
+        config: Arc::new(config),
+        active_sse_connections: Arc::new(AtomicUsize::new(0)),
+        prompt_registry: Arc::new(prompt_registry),
+        replay_buffer,
+        metrics: Arc::default(),
+        // Custom builds install their `ProxyHooks` here
+        hooks: Arc::default(),
+        response_store,
     };
 
-    // Initial model cache load
-    info!("🔄 Loading initial model cache...");
-    if let Err(e) = refresh_models_cache(&app).await {
-        log::warn!(
-            "⚠️  Failed to load initial model cache: {}. Continuing anyway.",
-            e
-        );
+    // Initial model cache load, doubling as the optional startup probe
+    match app.config.startup_probe {
+        StartupProbe::Off => {
+            info!("🔄 Loading initial model cache...");
+            if let Err(e) = refresh_models_cache(&app).await {
+                log::warn!(
+                    "⚠️  Failed to load initial model cache: {}. Continuing anyway.",
+                    e
+                );
+            }
+        }
+        probe => {
+            if probe_backend(&app).await.is_err() && probe == StartupProbe::Strict {
+                log::error!("❌ STARTUP_PROBE=strict and the backend probe failed, exiting");
+                std::process::exit(1);
+            }
+        }
     }
 
     // Background model cache refresh (every 60s) with graceful shutdown
@@ -86,6 +182,7 @@ async fn main() {
                 if let Err(e) = refresh_models_cache(&app_clone).await {
                     log::warn!("Failed to refresh models cache: {}", e);
                 }
+                app_clone.metrics.log_snapshot();
 
                 tokio::select! {
                     _ = tokio::time::sleep(Duration::from_secs(60)) => {
@@ -100,10 +197,26 @@ async fn main() {
         })
     };
 
+    let body_limit = app.config.request_body_limit();
     let router = Router::new()
+        .route("/", get(handlers::discovery))
+        .route("/v1", get(handlers::discovery))
         .route("/health", get(handlers::health_check))
+        .route("/health/ready", get(handlers::readiness_check))
+        .route("/admin/config", get(handlers::admin_config))
         .route("/v1/responses", post(handlers::create_response))
-        .layer(axum::extract::DefaultBodyLimit::max(10 * 1024 * 1024)) // 10MB limit
+        .route(
+            "/v1/responses/:id",
+            get(handlers::get_response).delete(handlers::delete_response),
+        )
+        .route("/v1/responses/:id/cancel", post(handlers::cancel_response))
+        .route(
+            "/v1/chat/completions",
+            post(handlers::chat_completions_passthrough),
+        )
+        .route("/v1/models", get(handlers::list_models))
+        .route("/v1/models/*id", get(handlers::get_model))
+        .layer(axum::extract::DefaultBodyLimit::max(body_limit))
         .layer(tower_http::compression::CompressionLayer::new())
         .with_state(app);
 
@@ -1,6 +1,14 @@
+use crate::models::{ProxyConfig, ProxyHooks, ProxyMetrics};
+use crate::services::{PromptRegistry, ReplayBuffer, ResponseStore};
 use log::warn;
 use reqwest::Client;
-use std::{sync::Arc, time::SystemTime};
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::SystemTime,
+};
 use tokio::sync::RwLock;
 
 #[derive(Clone, Debug)]
@@ -19,10 +27,106 @@ pub struct App {
     pub backend_url: String,
     pub models_cache: Arc<RwLock<Option<Vec<ModelInfo>>>>,
     pub circuit_breaker: Arc<RwLock<CircuitBreakerState>>,
+    pub config: Arc<ProxyConfig>,
+    pub active_sse_connections: Arc<AtomicUsize>,
+    pub prompt_registry: Arc<PromptRegistry>,
+    /// Recent SSE events for `Last-Event-ID` resumption (`None` when disabled)
+    pub replay_buffer: Option<Arc<ReplayBuffer>>,
+    pub metrics: Arc<ProxyMetrics>,
+    pub hooks: Arc<ProxyHooks>,
+    /// Where `store: true` responses are kept (`None` when persistence is off)
+    pub response_store: Option<Arc<dyn ResponseStore>>,
+}
+
+impl App {
+    /// Reserve a client SSE connection slot, honouring `max_sse_connections`.
+    /// Returns `None` when the proxy is already at capacity.
+    pub fn try_acquire_sse_slot(&self) -> Option<SseConnectionGuard> {
+        let counter = Arc::clone(&self.active_sse_connections);
+        let max = self.config.max_sse_connections.unwrap_or(usize::MAX);
+        counter
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |current| {
+                (current < max).then_some(current + 1)
+            })
+            .ok()?;
+        Some(SseConnectionGuard { counter })
+    }
+}
+
+#[cfg(test)]
+impl App {
+    /// Minimal app for handler tests: empty model cache, closed breaker,
+    /// replay buffer sized from `config`, no response store
+    pub fn for_tests(backend_url: &str, config: ProxyConfig) -> Self {
+        App {
+            client: Client::new(),
+            backend_url: backend_url.to_string(),
+            models_cache: Arc::new(RwLock::new(None)),
+            circuit_breaker: Arc::new(RwLock::new(CircuitBreakerState::new(true))),
+            active_sse_connections: Arc::new(AtomicUsize::new(0)),
+            prompt_registry: Arc::default(),
+            replay_buffer: config
+                .sse_replay_window
+                .map(|window| Arc::new(ReplayBuffer::new(window))),
+            config: Arc::new(config),
+            metrics: Arc::default(),
+            hooks: Arc::default(),
+            response_store: None,
+        }
+    }
+}
+
+/// Holds one SSE connection slot; released when the client stream is dropped
+/// (completion or disconnect).
+#[derive(Debug)]
+pub struct SseConnectionGuard {
+    counter: Arc<AtomicUsize>,
+}
+
+impl Drop for SseConnectionGuard {
+    fn drop(&mut self) {
+        self.counter.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 // ---------- Circuit breaker state ----------
 
+/// Failure classes fed to the circuit breaker; overloads count for more so the
+/// breaker backs off sooner when the backend says it is saturated
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FailureCategory {
+    Generic,
+    Overloaded,
+}
+
+impl FailureCategory {
+    /// Weight added to the failure count for this category
+    pub fn weight(self) -> u32 {
+        match self {
+            Self::Generic => 1,
+            Self::Overloaded => 3,
+        }
+    }
+
+    /// Classify a backend error body by its `error.type` / `error.code`
+    pub fn from_error_body(body: &str) -> Self {
+        let Ok(parsed) = serde_json::from_str::<serde_json::Value>(body) else {
+            return Self::Generic;
+        };
+        let error = &parsed["error"];
+        let overloaded = ["type", "code"].iter().any(|field| {
+            error[*field]
+                .as_str()
+                .is_some_and(|value| value.to_ascii_lowercase().contains("overloaded"))
+        });
+        if overloaded {
+            Self::Overloaded
+        } else {
+            Self::Generic
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub struct CircuitBreakerState {
     pub consecutive_failures: u32,
@@ -47,14 +151,14 @@ impl CircuitBreakerState {
         self.last_failure_time = None;
     }
 
-    pub fn record_failure(&mut self) {
-        self.consecutive_failures += 1;
+    pub fn record_failure(&mut self, category: FailureCategory) {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(category.weight());
         self.last_failure_time = Some(SystemTime::now());
         if self.consecutive_failures >= 5 {
             self.is_open = true;
             warn!(
-                "🔴 Circuit breaker opened after {} consecutive failures",
-                self.consecutive_failures
+                "🔴 Circuit breaker opened after {} weighted consecutive failures (last: {:?})",
+                self.consecutive_failures, category
             );
         }
     }
@@ -80,3 +184,36 @@ impl CircuitBreakerState {
         false
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_overloaded_failures_weigh_more_than_generic() {
+        let overloaded = FailureCategory::from_error_body(
+            r#"{"error":{"type":"overloaded_error","message":"busy"}}"#,
+        );
+        let generic = FailureCategory::from_error_body(
+            r#"{"error":{"type":"server_error","code":"internal"}}"#,
+        );
+        assert_eq!(overloaded, FailureCategory::Overloaded);
+        assert_eq!(generic, FailureCategory::Generic);
+        assert_eq!(
+            FailureCategory::from_error_body("not json"),
+            FailureCategory::Generic
+        );
+
+        let mut generic_cb = CircuitBreakerState::new(true);
+        generic_cb.record_failure(generic);
+        let mut overloaded_cb = CircuitBreakerState::new(true);
+        overloaded_cb.record_failure(overloaded);
+        assert!(overloaded_cb.consecutive_failures > generic_cb.consecutive_failures);
+
+        // Two overloads open the breaker; two generic failures do not
+        overloaded_cb.record_failure(overloaded);
+        generic_cb.record_failure(generic);
+        assert!(overloaded_cb.is_open);
+        assert!(!generic_cb.is_open);
+    }
+}
@@ -12,6 +12,8 @@ pub struct ChatMessage {
     pub tool_calls: Option<Vec<Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>, // For tool role messages
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reasoning_content: Option<String>, // Prior-turn reasoning for backends that accept it
 }
 
 #[derive(Serialize, Debug)]
@@ -97,6 +99,10 @@ pub struct ChatCompletionRequest {
     pub function_call: Option<Value>, // Deprecated: use tool_choice
     #[serde(skip_serializing_if = "Option::is_none")]
     pub functions: Option<Vec<Value>>, // Deprecated: use tools
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continue_final_message: Option<bool>, // Extend a trailing assistant message (vLLM)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub add_generation_prompt: Option<bool>, // Off while continuing a final message
 }
 
 // ---------- Chat Completions Response (from Chutes.ai) ----------
@@ -132,6 +138,9 @@ pub struct Choice {
     pub delta: Option<Delta>,
     #[serde(default)]
     pub message: Option<Value>,
+    /// Legacy Completions shape (`/v1/completions`) puts text here instead
+    #[serde(default)]
+    pub text: Option<String>,
     #[serde(default)]
     pub finish_reason: Option<String>,
 }
@@ -148,6 +157,9 @@ pub struct Delta {
     // Extended reasoning content (for reasoning models like DeepSeek-R1)
     #[serde(default)]
     pub reasoning_content: Option<String>,
+    // Citations attached to the text (e.g. url_citation from search-enabled backends)
+    #[serde(default)]
+    pub annotations: Option<Vec<Value>>,
 }
 
 #[derive(Deserialize, Debug)]
@@ -182,6 +194,16 @@ pub struct ChatUsage {
     #[serde(rename = "prompt_tokens_details")]
     pub _prompt_tokens_details: Option<Value>,
     #[serde(default)]
-    #[serde(rename = "completion_tokens_details")]
-    pub _completion_tokens_details: Option<Value>,
+    pub completion_tokens_details: Option<Value>,
+}
+
+impl ChatUsage {
+    /// `completion_tokens_details.reasoning_tokens`, when the backend reports it
+    pub fn reasoning_tokens(&self) -> Option<u32> {
+        self.completion_tokens_details
+            .as_ref()?
+            .get("reasoning_tokens")?
+            .as_u64()
+            .map(|tokens| tokens.min(u32::MAX as u64) as u32)
+    }
 }
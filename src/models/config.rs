@@ -0,0 +1,605 @@
+use axum::http::{HeaderName, HeaderValue};
+use std::{env, time::Duration};
+
+// ---------- Runtime configuration (environment driven) ----------
+
+/// What to do with a request that only carries `instructions` (no user turn)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InstructionsOnlyPolicy {
+    /// Append an empty user message so backends accept the conversation
+    #[default]
+    InjectEmptyUser,
+    /// Reject the request with `missing_input`
+    Reject,
+}
+
+impl InstructionsOnlyPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "inject_user" | "inject" => Some(Self::InjectEmptyUser),
+            "reject" | "error" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// What to do with image/file inputs aimed at a model without `vision` support
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VisionFallback {
+    /// Send the parts anyway and let the backend decide
+    #[default]
+    Forward,
+    /// Replace each part with a short `[image omitted]`-style text note
+    Strip,
+    /// Reject the request with `model_lacks_vision`
+    Reject,
+}
+
+impl VisionFallback {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "forward" | "passthrough" => Some(Self::Forward),
+            "strip" | "omit" => Some(Self::Strip),
+            "reject" | "error" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// How malformed `apply_patch` tool-call arguments are reported
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ApplyPatchValidation {
+    /// Do not inspect patches
+    Off,
+    /// Log a warning and count it in the metrics
+    #[default]
+    Warn,
+    /// Also fail the response with `malformed_apply_patch`
+    Reject,
+}
+
+impl ApplyPatchValidation {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" | "false" => Some(Self::Off),
+            "warn" => Some(Self::Warn),
+            "reject" | "error" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// What the streaming task does when a slow client lets the event channel fill
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SlowClientPolicy {
+    /// Wait for room; backend reads pause but no event is lost
+    #[default]
+    Block,
+    /// Drop deltas that do not fit and finish the response as `incomplete`;
+    /// lifecycle and `*.done` events always wait, so full content still arrives
+    Drop,
+}
+
+impl SlowClientPolicy {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "block" => Some(Self::Block),
+            "drop" => Some(Self::Drop),
+            _ => None,
+        }
+    }
+}
+
+/// Where responses created with `store: true` are kept
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResponseStoreKind {
+    /// Nothing is stored; `store` is answered with `false`
+    #[default]
+    Off,
+    /// The most recent responses are kept in process memory
+    Memory,
+}
+
+impl ResponseStoreKind {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "none" => Some(Self::Off),
+            "memory" => Some(Self::Memory),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the backend is checked before the proxy starts serving
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartupProbe {
+    #[default]
+    Off,
+    /// Fetch the model list and log the outcome; start either way
+    Log,
+    /// As `Log`, but refuse to start when the backend cannot be reached
+    Strict,
+}
+
+impl StartupProbe {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "false" | "0" => Some(Self::Off),
+            "log" | "on" | "true" | "1" => Some(Self::Log),
+            "strict" => Some(Self::Strict),
+            _ => None,
+        }
+    }
+}
+
+/// Which sampling parameter reaches the backend when a client sets both
+/// `temperature` and `top_p`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SamplingParamPreference {
+    /// Forward both, only logging a warning
+    #[default]
+    Both,
+    /// Forward `temperature` and drop `top_p`
+    Temperature,
+    /// Forward `top_p` and drop `temperature`
+    TopP,
+}
+
+impl SamplingParamPreference {
+    fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "both" => Some(Self::Both),
+            "temperature" => Some(Self::Temperature),
+            "top_p" => Some(Self::TopP),
+            _ => None,
+        }
+    }
+}
+
+/// Operator-tunable behaviour loaded once at startup.
+///
+/// Every field defaults to the proxy's historical behaviour so that an empty
+/// environment keeps working exactly as before.
+#[derive(Clone, Debug, Default)]
+pub struct ProxyConfig {
+    /// Maximum number of concurrently open client SSE streams (`None` = unlimited)
+    pub max_sse_connections: Option<usize>,
+    /// Strip the leading emoji decoration from log lines (`LOG_NO_EMOJI`)
+    pub log_no_emoji: bool,
+    /// Handling of instructions-only requests (`INSTRUCTIONS_ONLY_POLICY`)
+    pub instructions_only_policy: InstructionsOnlyPolicy,
+    /// Time allowed for the backend to return response headers, separate from the
+    /// overall streaming timeout (`BACKEND_HEADERS_TIMEOUT_SECS`, default 30, 0 disables)
+    pub backend_headers_timeout: Option<Duration>,
+    /// Honour `x-passthrough-backend: true` by streaming raw backend SSE bytes
+    /// (`ENABLE_BACKEND_PASSTHROUGH`, debugging only)
+    pub allow_backend_passthrough: bool,
+    /// Lowest `max_output_tokens` forwarded; smaller requests are raised (`MAX_OUTPUT_TOKENS_FLOOR`)
+    pub max_output_tokens_floor: Option<u32>,
+    /// Highest `max_output_tokens` forwarded; larger requests are clamped (`MAX_OUTPUT_TOKENS_CEILING`)
+    pub max_output_tokens_ceiling: Option<u32>,
+    /// JSON file of local prompt templates served for `prompt.id` (`PROMPT_REGISTRY_PATH`)
+    pub prompt_registry_path: Option<String>,
+    /// Send `response.created` before the backend answers (`EARLY_RESPONSE_CREATED`)
+    pub early_response_created: bool,
+    /// How long emitted events stay replayable for `Last-Event-ID` reconnects
+    /// (`SSE_REPLAY_WINDOW_SECS`, unset or 0 disables)
+    pub sse_replay_window: Option<Duration>,
+    /// Trim trailing whitespace from the final output text (`TRIM_TRAILING_WHITESPACE`)
+    pub trim_trailing_whitespace: bool,
+    /// Key required for `/admin/*` endpoints; they are disabled when unset (`ADMIN_API_KEY`)
+    pub admin_api_key: Option<String>,
+    /// Merge client `extra_body` keys into the backend request (`ALLOW_EXTRA_BODY`)
+    pub allow_extra_body: bool,
+    /// `metadata` keys copied onto request logs and metrics (`METRICS_METADATA_KEYS`, comma separated)
+    pub metrics_metadata_keys: Vec<String>,
+    /// Combined byte budget for instructions + input + tool schemas (`PROMPT_BUDGET_BYTES`)
+    pub prompt_budget_bytes: Option<usize>,
+    /// Retry once with `stream: false` when the stream yields no usable chunks
+    /// (`NON_STREAM_FALLBACK`; may bill twice if the first call did generate)
+    pub non_stream_fallback: bool,
+    /// Per-model tool-calling instruction: lowercase model substring paired with
+    /// `native`, `xml`, `none` or literal text (`TOOL_FORMAT_HINTS`, JSON object)
+    pub tool_format_hints: Vec<(String, String)>,
+    /// Largest accepted request body (`MAX_REQUEST_BODY_BYTES`, default 10 MiB)
+    pub max_request_body_bytes: Option<usize>,
+    /// Send prior-turn reasoning as assistant `reasoning_content` instead of inline
+    /// `<think>` tags (`REASONING_AS_FIELD`)
+    pub reasoning_as_field: bool,
+    /// Merge text deltas arriving within this window into one event
+    /// (`DELTA_COALESCE_MS`, unset or 0 disables)
+    pub delta_coalesce_window: Option<Duration>,
+    /// Emit a coalesced delta early once it reaches this size (`DELTA_COALESCE_MAX_BYTES`)
+    pub delta_coalesce_max_bytes: Option<usize>,
+    /// Report not ready on `/health/ready` until the model list has loaded (`REQUIRE_MODEL_CACHE`)
+    pub require_model_cache: bool,
+    /// Handling of image/file inputs for text-only models (`VISION_FALLBACK`)
+    pub vision_fallback: VisionFallback,
+    /// Model retried once when the backend answers 404 for the requested one (`FALLBACK_MODEL`)
+    pub fallback_model: Option<String>,
+    /// Envelope check for `apply_patch` tool calls (`APPLY_PATCH_VALIDATION`)
+    pub apply_patch_validation: ApplyPatchValidation,
+    /// Model aliases raced across several backend models, first usable stream
+    /// wins (`ENSEMBLE_MODELS`, JSON object of alias -> model list)
+    pub ensembles: Vec<(String, Vec<String>)>,
+    /// Bound (and redact secret-looking keys in) the `metadata` echoed in
+    /// response snapshots (`METADATA_ECHO_MAX_BYTES`)
+    pub metadata_echo_max_bytes: Option<usize>,
+    /// Ask the backend to extend a trailing assistant message
+    /// (`CONTINUE_FINAL_MESSAGE`)
+    pub continue_final_message: bool,
+    /// Replace backend error bodies with generic messages for clients, logging
+    /// the original server-side (`SANITIZE_BACKEND_ERRORS`)
+    pub sanitize_backend_errors: bool,
+    /// `object` value stamped on output items, omitted when unset; legacy
+    /// clients may want `realtime.item` (`ITEM_OBJECT`)
+    pub item_object: Option<String>,
+    /// Per-request `object` overrides keyed by the `x-responses-api-version`
+    /// header value; an empty value omits `object` (`ITEM_OBJECT_VERSIONS`)
+    pub item_object_versions: Vec<(String, String)>,
+    /// Sampling defaults for requests that leave them unset
+    /// (`DEFAULT_TEMPERATURE`, `DEFAULT_TOP_P`)
+    pub default_temperature: Option<f32>,
+    pub default_top_p: Option<f32>,
+    /// Re-serialize valid JSON tool-call arguments compactly before the
+    /// done events and final item (`COMPACT_TOOL_ARGUMENTS`)
+    pub compact_tool_arguments: bool,
+    /// Models (exact, case-insensitive) that never get the injected tool-format
+    /// and file-operation guidance (`NO_INJECT_MODELS`, comma-separated)
+    pub no_inject_models: Vec<String>,
+    /// Report invalid requests as a 200 SSE stream carrying one
+    /// `response.failed` event instead of a bare 4xx (`VALIDATION_ERRORS_AS_SSE`)
+    pub validation_errors_as_sse: bool,
+    /// Pass `cache_control` on text parts through to the backend, for
+    /// backends with prompt-cache breakpoints (`FORWARD_CACHE_CONTROL`)
+    pub forward_cache_control: bool,
+    /// Stop streaming reasoning past this many bytes while text continues
+    /// (`MAX_REASONING_BYTES`)
+    pub max_reasoning_bytes: Option<usize>,
+    /// Also apply the request's `stop` sequences to the streamed text here,
+    /// for backends that ignore them (`ENFORCE_STOP_LOCALLY`)
+    pub enforce_stop_locally: bool,
+    /// Warn when a `function_call_output` answers a call_id that no earlier
+    /// `function_call` in the same input introduced (`VERIFY_CALL_IDS`)
+    pub verify_call_ids: bool,
+    /// Events buffered per stream before the slow-client policy applies
+    /// (`EVENT_CHANNEL_CAPACITY`)
+    pub event_channel_capacity: Option<usize>,
+    /// Behaviour once that buffer is full (`SLOW_CLIENT_POLICY`)
+    pub slow_client_policy: SlowClientPolicy,
+    /// `tool_choice` sent when the client gives none and tools are present:
+    /// lowercase model substring paired with `auto`, `required` or `none`
+    /// (`DEFAULT_TOOL_CHOICE`, JSON object)
+    pub default_tool_choices: Vec<(String, String)>,
+    /// Parameter kept when a client sends both `temperature` and `top_p`
+    /// (`SAMPLING_PARAM_PREFERENCE`)
+    pub sampling_param_preference: SamplingParamPreference,
+    /// Announce the assistant message item only once text arrives, so
+    /// tool-calls-only responses carry no empty message (`DEFER_MESSAGE_ITEM`)
+    pub defer_message_item: bool,
+    /// Attach `_proxy_debug` stream timings to every final response, not just
+    /// requests sending `x-proxy-debug: true` (`PROXY_DEBUG_INFO`)
+    pub proxy_debug_info: bool,
+    /// Accept `conversation` together with `previous_response_id` (both are
+    /// ignored) instead of rejecting it (`ALLOW_CONFLICTING_STATE_PARAMS`)
+    pub allow_conflicting_state_params: bool,
+    /// Largest single backend SSE event accepted before the stream fails with
+    /// `backend_oversized_event` (`MAX_SSE_EVENT_BYTES`)
+    pub max_sse_event_bytes: Option<usize>,
+    /// Changes to the headers of streamed responses: a value adds or replaces
+    /// a header, `null` removes it (`SSE_HEADERS`, JSON object)
+    pub sse_headers: Vec<(String, Option<String>)>,
+    /// Persistence for `store: true` responses (`RESPONSE_STORE`)
+    pub response_store: ResponseStoreKind,
+    /// Responses the in-memory store keeps before evicting the oldest
+    /// (`RESPONSE_STORE_MAX_ENTRIES`)
+    pub response_store_max_entries: Option<usize>,
+    /// Backend check run at startup (`STARTUP_PROBE`)
+    pub startup_probe: StartupProbe,
+    /// Keep reasoning out of the stream for models whose backend feature list
+    /// omits `reasoning`; it is only logged (`REASONING_FEATURE_GATE`)
+    pub reasoning_feature_gate: bool,
+    /// Times a stream that fails before any content delta is transparently
+    /// re-requested from the backend (`EARLY_ERROR_RETRIES`, default 0)
+    pub early_error_retries: u32,
+}
+
+/// Request body limit applied when `MAX_REQUEST_BODY_BYTES` is unset
+pub const DEFAULT_MAX_REQUEST_BODY_BYTES: usize = 10 * 1024 * 1024;
+
+/// Per-stream event buffer used when `EVENT_CHANNEL_CAPACITY` is unset
+pub const DEFAULT_EVENT_CHANNEL_CAPACITY: usize = 64;
+
+/// Responses kept by the in-memory store when `RESPONSE_STORE_MAX_ENTRIES` is unset
+pub const DEFAULT_RESPONSE_STORE_MAX_ENTRIES: usize = 1000;
+
+/// Backend SSE event size limit used when `MAX_SSE_EVENT_BYTES` is unset (1MB)
+pub const DEFAULT_MAX_SSE_EVENT_BYTES: usize = 1_048_576;
+
+impl ProxyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            max_sse_connections: env_parse("MAX_SSE_CONNECTIONS"),
+            log_no_emoji: env_flag("LOG_NO_EMOJI"),
+            instructions_only_policy: env::var("INSTRUCTIONS_ONLY_POLICY")
+                .ok()
+                .and_then(|v| InstructionsOnlyPolicy::parse(&v))
+                .unwrap_or_default(),
+            backend_headers_timeout: Some(env_parse("BACKEND_HEADERS_TIMEOUT_SECS").unwrap_or(30))
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            allow_backend_passthrough: env_flag("ENABLE_BACKEND_PASSTHROUGH"),
+            max_output_tokens_floor: env_parse("MAX_OUTPUT_TOKENS_FLOOR"),
+            max_output_tokens_ceiling: env_parse("MAX_OUTPUT_TOKENS_CEILING"),
+            prompt_registry_path: env::var("PROMPT_REGISTRY_PATH")
+                .ok()
+                .filter(|p| !p.trim().is_empty()),
+            early_response_created: env_flag("EARLY_RESPONSE_CREATED"),
+            sse_replay_window: env_parse("SSE_REPLAY_WINDOW_SECS")
+                .filter(|secs| *secs > 0)
+                .map(Duration::from_secs),
+            trim_trailing_whitespace: env_flag("TRIM_TRAILING_WHITESPACE"),
+            admin_api_key: env::var("ADMIN_API_KEY")
+                .ok()
+                .filter(|k| !k.trim().is_empty()),
+            allow_extra_body: env_flag("ALLOW_EXTRA_BODY"),
+            metrics_metadata_keys: env_list("METRICS_METADATA_KEYS"),
+            prompt_budget_bytes: env_parse("PROMPT_BUDGET_BYTES"),
+            non_stream_fallback: env_flag("NON_STREAM_FALLBACK"),
+            tool_format_hints: env::var("TOOL_FORMAT_HINTS")
+                .ok()
+                .and_then(|raw| parse_model_patterns(&raw))
+                .unwrap_or_default(),
+            max_request_body_bytes: env_parse("MAX_REQUEST_BODY_BYTES").filter(|b| *b > 0),
+            reasoning_as_field: env_flag("REASONING_AS_FIELD"),
+            delta_coalesce_window: env_parse("DELTA_COALESCE_MS")
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            delta_coalesce_max_bytes: env_parse("DELTA_COALESCE_MAX_BYTES").filter(|b| *b > 0),
+            require_model_cache: env_flag("REQUIRE_MODEL_CACHE"),
+            vision_fallback: env::var("VISION_FALLBACK")
+                .ok()
+                .and_then(|v| VisionFallback::parse(&v))
+                .unwrap_or_default(),
+            fallback_model: env::var("FALLBACK_MODEL")
+                .ok()
+                .map(|m| m.trim().to_string())
+                .filter(|m| !m.is_empty()),
+            apply_patch_validation: env::var("APPLY_PATCH_VALIDATION")
+                .ok()
+                .and_then(|v| ApplyPatchValidation::parse(&v))
+                .unwrap_or_default(),
+            ensembles: env::var("ENSEMBLE_MODELS")
+                .ok()
+                .and_then(|raw| parse_ensembles(&raw))
+                .unwrap_or_default(),
+            metadata_echo_max_bytes: env_parse("METADATA_ECHO_MAX_BYTES"),
+            continue_final_message: env_flag("CONTINUE_FINAL_MESSAGE"),
+            sanitize_backend_errors: env_flag("SANITIZE_BACKEND_ERRORS"),
+            item_object: env::var("ITEM_OBJECT")
+                .ok()
+                .map(|o| o.trim().to_string())
+                .filter(|o| !o.is_empty()),
+            item_object_versions: env::var("ITEM_OBJECT_VERSIONS")
+                .ok()
+                .and_then(|raw| parse_model_patterns(&raw))
+                .unwrap_or_default(),
+            default_temperature: env_parse("DEFAULT_TEMPERATURE"),
+            default_top_p: env_parse("DEFAULT_TOP_P"),
+            compact_tool_arguments: env_flag("COMPACT_TOOL_ARGUMENTS"),
+            no_inject_models: env_list("NO_INJECT_MODELS"),
+            validation_errors_as_sse: env_flag("VALIDATION_ERRORS_AS_SSE"),
+            forward_cache_control: env_flag("FORWARD_CACHE_CONTROL"),
+            max_reasoning_bytes: env_parse("MAX_REASONING_BYTES").filter(|b| *b > 0),
+            enforce_stop_locally: env_flag("ENFORCE_STOP_LOCALLY"),
+            verify_call_ids: env_flag("VERIFY_CALL_IDS"),
+            event_channel_capacity: env_parse("EVENT_CHANNEL_CAPACITY").filter(|c| *c > 0),
+            slow_client_policy: env::var("SLOW_CLIENT_POLICY")
+                .ok()
+                .and_then(|v| SlowClientPolicy::parse(&v))
+                .unwrap_or_default(),
+            default_tool_choices: env::var("DEFAULT_TOOL_CHOICE")
+                .ok()
+                .and_then(|raw| parse_model_patterns(&raw))
+                .unwrap_or_default(),
+            sampling_param_preference: env::var("SAMPLING_PARAM_PREFERENCE")
+                .ok()
+                .and_then(|v| SamplingParamPreference::parse(&v))
+                .unwrap_or_default(),
+            defer_message_item: env_flag("DEFER_MESSAGE_ITEM"),
+            proxy_debug_info: env_flag("PROXY_DEBUG_INFO"),
+            allow_conflicting_state_params: env_flag("ALLOW_CONFLICTING_STATE_PARAMS"),
+            max_sse_event_bytes: env_parse("MAX_SSE_EVENT_BYTES").filter(|b| *b > 0),
+            sse_headers: env::var("SSE_HEADERS")
+                .ok()
+                .and_then(|raw| parse_header_overrides(&raw))
+                .unwrap_or_default(),
+            response_store: env::var("RESPONSE_STORE")
+                .ok()
+                .and_then(|v| ResponseStoreKind::parse(&v))
+                .unwrap_or_default(),
+            response_store_max_entries: env_parse("RESPONSE_STORE_MAX_ENTRIES").filter(|n| *n > 0),
+            startup_probe: env::var("STARTUP_PROBE")
+                .ok()
+                .and_then(|v| StartupProbe::parse(&v))
+                .unwrap_or_default(),
+            reasoning_feature_gate: env_flag("REASONING_FEATURE_GATE"),
+            early_error_retries: env_parse("EARLY_ERROR_RETRIES").unwrap_or(0),
+        }
+    }
+
+    /// Effective request body limit in bytes
+    pub fn request_body_limit(&self) -> usize {
+        self.max_request_body_bytes
+            .unwrap_or(DEFAULT_MAX_REQUEST_BODY_BYTES)
+    }
+
+    /// Effective per-stream event buffer size
+    pub fn event_channel_capacity(&self) -> usize {
+        self.event_channel_capacity
+            .unwrap_or(DEFAULT_EVENT_CHANNEL_CAPACITY)
+    }
+
+    /// Effective capacity of the in-memory response store
+    pub fn response_store_max_entries(&self) -> usize {
+        self.response_store_max_entries
+            .unwrap_or(DEFAULT_RESPONSE_STORE_MAX_ENTRIES)
+    }
+
+    /// Effective per-event size limit for backend SSE streams
+    pub fn max_sse_event_bytes(&self) -> usize {
+        self.max_sse_event_bytes
+            .unwrap_or(DEFAULT_MAX_SSE_EVENT_BYTES)
+    }
+
+    /// Tool-calling hint configured for a model; the longest matching pattern wins
+    pub fn tool_format_hint_for(&self, model: &str) -> Option<&str> {
+        longest_pattern_match(&self.tool_format_hints, model)
+    }
+
+    /// Default `tool_choice` configured for a model; the longest matching pattern wins
+    pub fn default_tool_choice_for(&self, model: &str) -> Option<&str> {
+        longest_pattern_match(&self.default_tool_choices, model)
+    }
+
+    /// `object` for output items of a request declaring `version`, falling back
+    /// to `ITEM_OBJECT` for unknown or missing versions
+    pub fn item_object_for(&self, version: Option<&str>) -> Option<&str> {
+        let version = version.map(|v| v.trim().to_ascii_lowercase());
+        let mapped = self
+            .item_object_versions
+            .iter()
+            .find(|(known, _)| Some(known) == version.as_ref());
+        match mapped {
+            Some((_, object)) => Some(object.as_str()).filter(|o| !o.is_empty()),
+            None => self.item_object.as_deref(),
+        }
+    }
+
+    /// Backend models raced for a requested model alias, if it names an ensemble
+    pub fn ensemble_for(&self, model: &str) -> Option<&[String]> {
+        self.ensembles
+            .iter()
+            .find(|(alias, _)| alias.eq_ignore_ascii_case(model.trim()))
+            .map(|(_, models)| models.as_slice())
+    }
+
+    /// Apply the operator's floor/ceiling to a requested output token limit.
+    /// The ceiling is a cost cap, so it wins over a floor set above it.
+    pub fn clamp_max_output_tokens(&self, requested: u32) -> u32 {
+        let mut tokens = requested;
+        if let Some(floor) = self.max_output_tokens_floor {
+            tokens = tokens.max(floor);
+        }
+        if let Some(ceiling) = self.max_output_tokens_ceiling {
+            tokens = tokens.min(ceiling);
+        }
+        tokens
+    }
+
+    /// Whether `MAX_OUTPUT_TOKENS_FLOOR` exceeds `MAX_OUTPUT_TOKENS_CEILING`
+    pub fn max_output_tokens_limits_conflict(&self) -> bool {
+        matches!(
+            (self.max_output_tokens_floor, self.max_output_tokens_ceiling),
+            (Some(floor), Some(ceiling)) if floor > ceiling
+        )
+    }
+}
+
+/// Value paired with the longest pattern contained in `model`
+fn longest_pattern_match<'a>(patterns: &'a [(String, String)], model: &str) -> Option<&'a str> {
+    let model = model.to_ascii_lowercase();
+    patterns
+        .iter()
+        .filter(|(pattern, _)| model.contains(pattern.as_str()))
+        .max_by_key(|(pattern, _)| pattern.len())
+        .map(|(_, value)| value.as_str())
+}
+
+/// Parse `{"model-substring": "value", ...}`, skipping empty patterns and non-string
+/// values; keys are lowercased, so it also suits other case-insensitive lookups
+fn parse_model_patterns(raw: &str) -> Option<Vec<(String, String)>> {
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(raw).ok()?;
+    Some(
+        map.into_iter()
+            .filter_map(|(pattern, hint)| {
+                let pattern = pattern.trim().to_ascii_lowercase();
+                let hint = hint.as_str()?.to_string();
+                (!pattern.is_empty()).then_some((pattern, hint))
+            })
+            .collect(),
+    )
+}
+
+/// Parse `SSE_HEADERS`, e.g. `{"x-accel-buffering": null, "x-buffering": "off"}`;
+/// names are lowercased and entries that are not valid HTTP headers are dropped
+fn parse_header_overrides(raw: &str) -> Option<Vec<(String, Option<String>)>> {
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(raw).ok()?;
+    Some(
+        map.into_iter()
+            .filter_map(|(name, value)| {
+                let name = name.trim().to_ascii_lowercase();
+                HeaderName::from_bytes(name.as_bytes()).ok()?;
+                let value = match value {
+                    serde_json::Value::Null => None,
+                    serde_json::Value::String(value) => {
+                        HeaderValue::from_str(&value).ok()?;
+                        Some(value)
+                    }
+                    _ => return None,
+                };
+                Some((name, value))
+            })
+            .collect(),
+    )
+}
+
+/// Parse `ENSEMBLE_MODELS`, e.g. `{"fast": ["model-a", "model-b"]}`; aliases
+/// with fewer than two models are not ensembles and are dropped
+fn parse_ensembles(raw: &str) -> Option<Vec<(String, Vec<String>)>> {
+    let map: serde_json::Map<String, serde_json::Value> = serde_json::from_str(raw).ok()?;
+    Some(
+        map.into_iter()
+            .filter_map(|(alias, models)| {
+                let models: Vec<String> = models
+                    .as_array()?
+                    .iter()
+                    .filter_map(|m| m.as_str())
+                    .map(str::trim)
+                    .filter(|m| !m.is_empty())
+                    .map(String::from)
+                    .collect();
+                (models.len() > 1).then(|| (alias.trim().to_string(), models))
+            })
+            .collect(),
+    )
+}
+
+/// Parse an environment variable, ignoring missing or malformed values
+fn env_parse<T: std::str::FromStr>(key: &str) -> Option<T> {
+    env::var(key).ok().and_then(|s| s.trim().parse::<T>().ok())
+}
+
+/// Split a comma-separated environment variable, dropping empty entries
+fn env_list(key: &str) -> Vec<String> {
+    env::var(key)
+        .map(|value| {
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+                .map(String::from)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Interpret an environment variable as a boolean flag (`1`, `true`, `yes`, `on`)
+fn env_flag(key: &str) -> bool {
+    env::var(key)
+        .map(|value| {
+            let normalized = value.trim().to_ascii_lowercase();
+            matches!(normalized.as_str(), "1" | "true" | "yes" | "on")
+        })
+        .unwrap_or(false)
+}
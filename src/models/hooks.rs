@@ -0,0 +1,28 @@
+use crate::models::StreamEvent;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// Edits the serialized Chat Completions body just before it is sent
+pub type BackendRequestHook = Arc<dyn Fn(&mut Value) + Send + Sync>;
+
+/// Rewrites a stream event before it is sequenced and sent; `None` drops it.
+/// Like the other hooks it only runs in a build that installs it in `main.rs`.
+pub type StreamEventHook = Arc<dyn Fn(StreamEvent) -> Option<StreamEvent> + Send + Sync>;
+
+/// Extension points for backend-specific tweaks. Every hook is optional and
+/// none are installed: the crate is a binary only, so a hook is added where
+/// `App` is built in `main.rs`.
+#[derive(Clone, Default)]
+pub struct ProxyHooks {
+    pub backend_request: Option<BackendRequestHook>,
+    pub stream_event: Option<StreamEventHook>,
+}
+
+impl ProxyHooks {
+    /// Run the backend request hook, if one is installed
+    pub fn transform_backend_request(&self, body: &mut Value) {
+        if let Some(hook) = &self.backend_request {
+            hook(body);
+        }
+    }
+}
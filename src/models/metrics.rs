@@ -0,0 +1,73 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+// ---------- Process-wide counters ----------
+
+/// Counters for behaviour operators tune at runtime, logged periodically
+/// under the `metrics` log target.
+#[derive(Debug, Default)]
+pub struct ProxyMetrics {
+    /// Text deltas that started XML tool-call buffering
+    pub xml_buffering_started: AtomicU64,
+    /// XML tool calls converted into native function calls
+    pub xml_conversions: AtomicU64,
+    /// Buffers that reached a closing tag but failed to parse
+    pub xml_parse_failures: AtomicU64,
+    /// JSON `tool_calls` structures in text converted into native function calls
+    pub json_conversions: AtomicU64,
+    /// Buffers still open at end of stream, emitted as plain text
+    pub xml_flushed_as_text: AtomicU64,
+    /// Streams abandoned because the client disconnected before completion
+    pub client_cancelled: AtomicU64,
+    /// `apply_patch` calls whose patch lacked the expected envelope
+    pub apply_patch_malformed: AtomicU64,
+    /// Events dropped because a slow client's event buffer was full
+    pub slow_client_dropped_events: AtomicU64,
+    /// Streamed requests whose timing breakdown was recorded
+    pub timed_requests: AtomicU64,
+    /// Milliseconds spent validating and converting requests
+    pub prepare_ms_total: AtomicU64,
+    /// Milliseconds spent waiting for the backend's first chunk
+    pub backend_ms_total: AtomicU64,
+    /// Milliseconds spent streaming after the first backend chunk
+    pub stream_ms_total: AtomicU64,
+}
+
+impl ProxyMetrics {
+    pub fn incr(counter: &AtomicU64) {
+        Self::add(counter, 1);
+    }
+
+    pub fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+
+    /// Emit the current counter values on the `metrics` log target
+    pub fn log_snapshot(&self) {
+        log::info!(target: "metrics",
+            "xml_tool_calls: buffering_started={}, conversions={}, json_conversions={}, parse_failures={}, flushed_as_text={}",
+            self.xml_buffering_started.load(Ordering::Relaxed),
+            self.xml_conversions.load(Ordering::Relaxed),
+            self.json_conversions.load(Ordering::Relaxed),
+            self.xml_parse_failures.load(Ordering::Relaxed),
+            self.xml_flushed_as_text.load(Ordering::Relaxed),
+        );
+        log::info!(target: "metrics",
+            "streams: client_cancelled={}, slow_client_dropped_events={}",
+            self.client_cancelled.load(Ordering::Relaxed),
+            self.slow_client_dropped_events.load(Ordering::Relaxed),
+        );
+        log::info!(target: "metrics",
+            "apply_patch: malformed={}",
+            self.apply_patch_malformed.load(Ordering::Relaxed),
+        );
+        let timed = self.timed_requests.load(Ordering::Relaxed);
+        let average = |total: &AtomicU64| total.load(Ordering::Relaxed) / timed.max(1);
+        log::info!(target: "metrics",
+            "timing: requests={}, avg_prepare_ms={}, avg_backend_ms={}, avg_stream_ms={}",
+            timed,
+            average(&self.prepare_ms_total),
+            average(&self.backend_ms_total),
+            average(&self.stream_ms_total),
+        );
+    }
+}
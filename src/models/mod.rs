@@ -1,7 +1,13 @@
 pub mod app;
 pub mod chat_completions;
+pub mod config;
+pub mod hooks;
+pub mod metrics;
 pub mod openai_responses;
 
 pub use app::*;
 pub use chat_completions::*;
+pub use config::*;
+pub use hooks::*;
+pub use metrics::*;
 pub use openai_responses::*;
@@ -7,9 +7,33 @@ use serde_json::Value;
 #[serde(untagged)]
 pub enum ResponseInput {
     String(String),
+    #[serde(deserialize_with = "deserialize_input_items")]
     Array(Vec<ResponseInputItem>),
 }
 
+/// Input items, where a bare string is shorthand for a user message
+fn deserialize_input_items<'de, D>(deserializer: D) -> Result<Vec<ResponseInputItem>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    Vec::<Value>::deserialize(deserializer)?
+        .into_iter()
+        .map(|item| {
+            let item = match item {
+                Value::String(text) => serde_json::json!({
+                    "type": "message",
+                    "role": "user",
+                    "content": text,
+                }),
+                other => other,
+            };
+            serde_json::from_value(item).map_err(D::Error::custom)
+        })
+        .collect()
+}
+
 #[derive(Deserialize, Debug)]
 #[serde(tag = "type")]
 pub enum ResponseInputItem {
@@ -17,7 +41,7 @@ pub enum ResponseInputItem {
     Message {
         role: String,
         content: ResponseContent,
-        #[serde(default)]
+        #[serde(default, alias = "call_id")]
         tool_call_id: Option<String>,
         #[allow(dead_code)]
         #[serde(default)]
@@ -55,9 +79,18 @@ pub enum ResponseContent {
 #[serde(tag = "type")]
 pub enum ContentPart {
     #[serde(rename = "input_text")]
-    InputText { text: String },
+    InputText {
+        text: String,
+        /// Anthropic-style prompt-cache breakpoint, forwarded when enabled
+        #[serde(default)]
+        cache_control: Option<Value>,
+    },
     #[serde(rename = "output_text")] // Accept output_text in input (for multi-turn)
-    OutputText { text: String },
+    OutputText {
+        text: String,
+        #[serde(default)]
+        cache_control: Option<Value>,
+    },
     #[serde(rename = "input_image")]
     InputImage { image_url: ImageUrl },
     #[serde(rename = "input_file")]
@@ -175,6 +208,80 @@ impl Tool {
     }
 }
 
+/// Accept `tools` as the usual array or as an object keyed by tool name
+/// (`{"get_weather": {"parameters": ...}}`); the key fills in a missing `name`
+/// and `type` defaults to `function`
+fn deserialize_tools<'de, D>(deserializer: D) -> Result<Option<Vec<Tool>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let tools = match Option::<Value>::deserialize(deserializer)? {
+        None | Some(Value::Null) => return Ok(None),
+        Some(tools @ Value::Array(_)) => tools,
+        Some(Value::Object(by_name)) => by_name
+            .into_iter()
+            .map(|(name, mut tool)| {
+                let Some(fields) = tool.as_object_mut() else {
+                    return Err(D::Error::custom(format!(
+                        "tool '{}' must be an object",
+                        name
+                    )));
+                };
+                fields
+                    .entry("type")
+                    .or_insert_with(|| Value::String("function".to_string()));
+                if !fields.contains_key("function") {
+                    fields.entry("name").or_insert(Value::String(name));
+                }
+                Ok(tool)
+            })
+            .collect::<Result<Vec<_>, _>>()?
+            .into(),
+        Some(_) => {
+            return Err(D::Error::custom(
+                "tools must be an array or an object keyed by tool name",
+            ))
+        }
+    };
+    serde_json::from_value(tools)
+        .map(Some)
+        .map_err(D::Error::custom)
+}
+
+/// `instructions` as sent by the client: a string or an array of content parts
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Instructions {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+/// Array-form instructions are reduced to their text parts, one per line,
+/// so the rest of the proxy keeps seeing a single system prompt string
+fn deserialize_instructions<'de, D>(deserializer: D) -> Result<Option<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(match Option::<Instructions>::deserialize(deserializer)? {
+        None => None,
+        Some(Instructions::Text(text)) => Some(text),
+        Some(Instructions::Parts(parts)) => Some(
+            parts
+                .iter()
+                .filter_map(|part| match part {
+                    ContentPart::InputText { text, .. } | ContentPart::OutputText { text, .. } => {
+                        Some(text.as_str())
+                    }
+                    _ => None,
+                })
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ),
+    })
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct FunctionDef {
     pub name: String,
@@ -191,12 +298,43 @@ pub enum ToolChoice {
 }
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
+#[serde(from = "ToolChoiceSpecificShape")]
 pub struct ToolChoiceSpecific {
     #[serde(rename = "type")]
     pub type_: String, // "function"
     pub function: FunctionChoice,
 }
 
+/// Accepted forms of a specific `tool_choice`: Chat Completions nests the name
+/// under `function`, the Responses API puts it beside `type`. Both serialize in
+/// the nested form backends expect.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ToolChoiceSpecificShape {
+    Nested {
+        #[serde(rename = "type")]
+        type_: String,
+        function: FunctionChoice,
+    },
+    Flat {
+        #[serde(rename = "type")]
+        type_: String,
+        name: String,
+    },
+}
+
+impl From<ToolChoiceSpecificShape> for ToolChoiceSpecific {
+    fn from(shape: ToolChoiceSpecificShape) -> Self {
+        match shape {
+            ToolChoiceSpecificShape::Nested { type_, function } => Self { type_, function },
+            ToolChoiceSpecificShape::Flat { type_, name } => Self {
+                type_,
+                function: FunctionChoice { name },
+            },
+        }
+    }
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct FunctionChoice {
     pub name: String,
@@ -204,6 +342,8 @@ pub struct FunctionChoice {
 
 #[derive(Deserialize, Serialize, Debug, Clone)]
 pub struct StreamOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub include_usage: Option<bool>,
     #[serde(default)]
     pub include_obfuscation: Option<bool>,
 }
@@ -263,7 +403,7 @@ pub struct ResponseRequest {
     pub model: Option<String>,
     #[serde(default)]
     pub input: Option<ResponseInput>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_instructions")]
     pub instructions: Option<String>,
     #[serde(default)]
     pub max_output_tokens: Option<u32>,
@@ -273,7 +413,7 @@ pub struct ResponseRequest {
     pub temperature: Option<f32>,
     #[serde(default)]
     pub top_p: Option<f32>,
-    #[serde(default)]
+    #[serde(default, deserialize_with = "deserialize_tools")]
     pub tools: Option<Vec<Tool>>,
     #[serde(default)]
     pub tool_choice: Option<ToolChoice>,
@@ -344,6 +484,8 @@ pub struct ResponseRequest {
     #[serde(default)]
     pub presence_penalty: Option<f32>,
     #[serde(default)]
+    pub extra_body: Option<Value>, // Provider-specific params merged into the backend request
+    #[serde(default)]
     pub seed: Option<u64>,
     #[serde(default)]
     pub logit_bias: Option<Value>,
@@ -413,6 +555,19 @@ pub struct Response {
     pub prompt_cache_key: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub service_tier: Option<String>,
+    /// Proxy-side stream timings, only when requested
+    #[serde(rename = "_proxy_debug", skip_serializing_if = "Option::is_none")]
+    pub proxy_debug: Option<ProxyDebugInfo>,
+}
+
+/// Stream statistics attached to the final response for performance analysis
+#[derive(Serialize, Debug, Clone)]
+pub struct ProxyDebugInfo {
+    pub backend_chunk_count: u32,
+    /// Time from request arrival to the first usable backend chunk
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttft_ms: Option<u64>,
+    pub total_ms: u64,
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -429,8 +584,9 @@ pub struct IncompleteDetails {
 #[derive(Serialize, Debug, Clone)]
 pub struct OutputItem {
     pub id: String,
-    #[serde(rename = "object")]
-    pub object: String,
+    /// Omitted for Responses clients; set only when `ITEM_OBJECT` asks for one
+    #[serde(rename = "object", skip_serializing_if = "Option::is_none")]
+    pub object: Option<String>,
     #[serde(rename = "type")]
     pub type_: String, // "message", "function_call", "function_call_output", "reasoning", "refusal"
     pub status: String,
@@ -448,6 +604,18 @@ pub struct OutputItem {
     // For function_call_output items
     #[serde(skip_serializing_if = "Option::is_none")]
     pub output: Option<String>,
+    // For reasoning items
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<Vec<ReasoningSummaryPart>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub encrypted_content: Option<String>,
+}
+
+#[derive(Serialize, Debug, Clone)]
+#[serde(tag = "type")]
+pub enum ReasoningSummaryPart {
+    #[serde(rename = "summary_text")]
+    SummaryText { text: String },
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -456,11 +624,12 @@ pub enum OutputContent {
     #[serde(rename = "output_text")]
     OutputText {
         text: String,
-        #[serde(skip_serializing_if = "Vec::is_empty")]
         annotations: Vec<Value>,
     },
-    #[serde(rename = "reasoning")]
+    #[serde(rename = "reasoning_text")]
     Reasoning { text: String },
+    #[serde(rename = "summary_text")]
+    SummaryText { text: String },
 }
 
 #[derive(Serialize, Debug, Clone)]
@@ -516,4 +685,93 @@ pub struct StreamEvent {
     // For error events
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<ResponseError>,
+    // For output_text.done / content_part.done events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Vec<Value>>,
+    // For interim usage events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
+    // For content_part.* / reasoning_summary_part.* events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub part: Option<OutputContent>,
+    // For reasoning_summary_* events
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary_index: Option<u32>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_bare_string_input_items_become_user_messages() {
+        let req: ResponseRequest = serde_json::from_value(json!({
+            "model": "m",
+            "input": [
+                "hello",
+                {"type": "message", "role": "assistant", "content": "hi there"}
+            ]
+        }))
+        .unwrap();
+        let Some(ResponseInput::Array(items)) = req.input else {
+            panic!("expected array input");
+        };
+        assert_eq!(items.len(), 2);
+        assert!(matches!(
+            &items[0],
+            ResponseInputItem::Message { role, content: ResponseContent::String(text), .. }
+                if role == "user" && text == "hello"
+        ));
+        assert!(matches!(
+            &items[1],
+            ResponseInputItem::Message { role, .. } if role == "assistant"
+        ));
+    }
+
+    #[test]
+    fn test_flat_tool_choice_normalized_to_nested() {
+        let nested = json!({"type": "function", "function": {"name": "get_weather"}});
+        for tool_choice in [
+            json!({"type": "function", "name": "get_weather"}),
+            nested.clone(),
+        ] {
+            let req: ResponseRequest = serde_json::from_value(json!({
+                "model": "m",
+                "tool_choice": tool_choice
+            }))
+            .unwrap();
+            let Some(ToolChoice::Specific(spec)) = req.tool_choice else {
+                panic!("expected a specific tool choice");
+            };
+            assert_eq!(spec.function.name, "get_weather");
+            assert_eq!(serde_json::to_value(&spec).unwrap(), nested);
+        }
+    }
+
+    #[test]
+    fn test_tools_accepted_as_object_keyed_by_name() {
+        let req: ResponseRequest = serde_json::from_value(json!({
+            "model": "m",
+            "tools": {
+                "get_weather": {
+                    "description": "Weather for a city",
+                    "parameters": {"type": "object", "properties": {"city": {"type": "string"}}}
+                }
+            }
+        }))
+        .unwrap();
+        let tools = req.tools.unwrap();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].type_(), "function");
+        let def = tools[0].function_def();
+        assert_eq!(def.name, "get_weather");
+        assert_eq!(def.description.as_deref(), Some("Weather for a city"));
+        assert_eq!(def.parameters["properties"]["city"]["type"], "string");
+
+        let invalid = serde_json::from_value::<ResponseRequest>(json!({"tools": "get_weather"}));
+        assert!(invalid.is_err());
+        let invalid = serde_json::from_value::<ResponseRequest>(json!({"tools": {"a": 1}}));
+        assert!(invalid.is_err());
+    }
 }
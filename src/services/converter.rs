@@ -1,6 +1,7 @@
 use crate::models::{
-    ChatCompletionRequest, ChatFunction, ChatMessage, ChatTool, ContentPart, ResponseContent,
-    ResponseInput, ResponseInputItem, ResponseRequest,
+    ChatCompletionRequest, ChatFunction, ChatMessage, ChatTool, ContentPart,
+    InstructionsOnlyPolicy, ProxyConfig, ResponseContent, ResponseInput, ResponseInputItem,
+    ResponseRequest, SamplingParamPreference,
 };
 use serde_json::{json, Value};
 
@@ -8,6 +9,7 @@ use serde_json::{json, Value};
 pub fn convert_to_chat_completions(
     req: &ResponseRequest,
     supports_native_tools: bool,
+    config: &ProxyConfig,
 ) -> Result<ChatCompletionRequest, String> {
     let model = req.model.as_ref().ok_or("Model is required")?.clone();
 
@@ -27,6 +29,16 @@ To call a function, you MUST use the following XML format:\n\
 \n\
 Do not use JSON tool calls. Use the XML format above.";
 
+    // Operators may pin the nudge per model; otherwise follow the capability check
+    let tool_override = match config.tool_format_hint_for(&model) {
+        Some("native") => native_tool_override.to_string(),
+        Some("xml") => xml_tool_override.to_string(),
+        Some("none") => String::new(),
+        Some(custom) => format!("\n\n---\n\n{}", custom),
+        None if supports_native_tools => native_tool_override.to_string(),
+        None => xml_tool_override.to_string(),
+    };
+
     let file_ops_guidance = "\n\nFile Operation Best Practices:\n\
 - Use relative paths (e.g. 'test.py', 'src/main.rs') for files in the workspace\n\
 - Read each file ONCE before editing - do not re-read files you've already successfully read\n\
@@ -37,13 +49,14 @@ Do not use JSON tool calls. Use the XML format above.";
     // Determine which instructions to use
     let mut system_instructions = req.instructions.clone().unwrap_or_default();
 
-    // Only append overrides if tools are actually present or requested
-    if req.tools.is_some() {
-        if supports_native_tools {
-            system_instructions.push_str(native_tool_override);
-        } else {
-            system_instructions.push_str(xml_tool_override);
-        }
+    // Only append overrides if tools are actually present or requested, and
+    // never for models the operator has opted out entirely
+    let inject_guidance = !config
+        .no_inject_models
+        .iter()
+        .any(|m| m.eq_ignore_ascii_case(&model));
+    if req.tools.is_some() && inject_guidance {
+        system_instructions.push_str(&tool_override);
         // Append general guidance
         system_instructions.push_str(file_ops_guidance);
     }
@@ -55,6 +68,7 @@ Do not use JSON tool calls. Use the XML format above.";
             content: Some(json!(system_instructions)),
             tool_calls: None,
             tool_call_id: None,
+            reasoning_content: None,
         });
     }
 
@@ -81,11 +95,16 @@ Do not use JSON tool calls. Use the XML format above.";
                     content: Some(json!(text)),
                     tool_calls: None,
                     tool_call_id: None,
+                    reasoning_content: None,
                 });
             }
             ResponseInput::Array(items) => {
                 let mut accumulated_reasoning: Vec<String> = Vec::new();
                 let mut pending_tool_calls: Vec<Value> = Vec::new();
+                // call_ids of function_call items that have not been answered yet
+                let mut unanswered_call_ids: Vec<String> = Vec::new();
+                // Every function_call id seen so far, for VERIFY_CALL_IDS
+                let mut seen_call_ids: Vec<&str> = Vec::new();
 
                 for item in items {
                     match item {
@@ -109,10 +128,11 @@ Do not use JSON tool calls. Use the XML format above.";
                             }
 
                             if role == "tool" {
-                                let call_id = tool_call_id.clone().ok_or_else(|| {
-                                    log::error!("❌ Tool role message missing tool_call_id");
-                                    "tool_message_missing_tool_call_id".to_string()
-                                })?;
+                                let call_id = resolve_tool_message_call_id(
+                                    tool_call_id.as_deref(),
+                                    &unanswered_call_ids,
+                                )?;
+                                unanswered_call_ids.retain(|id| id != &call_id);
 
                                 let tool_payload = extract_tool_message_body(content)?;
 
@@ -121,19 +141,32 @@ Do not use JSON tool calls. Use the XML format above.";
                                     content: Some(json!(tool_payload)),
                                     tool_calls: None,
                                     tool_call_id: Some(call_id),
+                                    reasoning_content: None,
                                 });
 
                                 continue;
                             }
 
                             let (mut msg_content, content_reasoning) =
-                                convert_response_content(content)?;
+                                convert_response_content(content, config.forward_cache_control)?;
 
                             // If content has inline reasoning, accumulate it
                             if let Some(content_think) = content_reasoning {
                                 accumulated_reasoning.push(content_think);
                             }
 
+                            let mut reasoning_content = None;
+                            if role == "assistant"
+                                && !accumulated_reasoning.is_empty()
+                                && config.reasoning_as_field
+                            {
+                                let thinking_text = accumulated_reasoning.join("\n");
+                                log::info!("🧠 INPUT: Attached {} reasoning part(s) ({} chars) to assistant message as reasoning_content",
+                                    accumulated_reasoning.len(), thinking_text.len());
+                                reasoning_content = Some(thinking_text);
+                                accumulated_reasoning.clear();
+                            }
+
                             // If assistant message and we have accumulated reasoning, prepend as <think> tags
                             if role == "assistant" && !accumulated_reasoning.is_empty() {
                                 let thinking_text = accumulated_reasoning.join("\n");
@@ -159,6 +192,7 @@ Do not use JSON tool calls. Use the XML format above.";
                                     content: Some(msg_content),
                                     tool_calls: Some(pending_tool_calls.clone()),
                                     tool_call_id: None,
+                                    reasoning_content,
                                 });
                                 pending_tool_calls.clear();
                             } else {
@@ -167,6 +201,7 @@ Do not use JSON tool calls. Use the XML format above.";
                                     content: Some(msg_content),
                                     tool_calls: None,
                                     tool_call_id: None,
+                                    reasoning_content,
                                 });
                             }
                         }
@@ -175,6 +210,9 @@ Do not use JSON tool calls. Use the XML format above.";
                             name,
                             arguments,
                         } => {
+                            unanswered_call_ids.push(call_id.clone());
+                            seen_call_ids.push(call_id);
+
                             // Accumulate tool calls to attach to the next assistant message
                             pending_tool_calls.push(json!({
                                 "id": call_id,
@@ -187,6 +225,15 @@ Do not use JSON tool calls. Use the XML format above.";
                             log::info!("🔧 INPUT: Found function_call ({}) - will attach to assistant message", name);
                         }
                         ResponseInputItem::FunctionCallOutput { call_id, output } => {
+                            unanswered_call_ids.retain(|id| id != call_id);
+                            if config.verify_call_ids && !seen_call_ids.contains(&call_id.as_str())
+                            {
+                                log::warn!(
+                                    "⚠️  function_call_output references call_id {} with no earlier function_call in this input",
+                                    call_id
+                                );
+                            }
+
                             // The output field is a string that may contain nested JSON from Codex
                             // (e.g., {"output":"...", "metadata":{...}}). Try to extract the actual
                             // output content, otherwise use the raw string.
@@ -211,6 +258,7 @@ Do not use JSON tool calls. Use the XML format above.";
                                 content: Some(json!(content_str)),
                                 tool_calls: None,
                                 tool_call_id: Some(call_id.clone()),
+                                reasoning_content: None,
                             });
                             log::info!(
                                 "🔧 INPUT: Added function_call_output (call_id: {}, {} bytes)",
@@ -249,11 +297,32 @@ Do not use JSON tool calls. Use the XML format above.";
         }
     }
 
+    // Backends reject conversations that consist solely of a system message
+    if !messages.is_empty() && messages.iter().all(|m| m.role == "system") {
+        match config.instructions_only_policy {
+            InstructionsOnlyPolicy::InjectEmptyUser => {
+                log::info!("📨 Instructions-only request - appending empty user message");
+                messages.push(ChatMessage {
+                    role: "user".to_string(),
+                    content: Some(json!("")),
+                    tool_calls: None,
+                    tool_call_id: None,
+                    reasoning_content: None,
+                });
+            }
+            InstructionsOnlyPolicy::Reject => {
+                log::error!("❌ Request has instructions but no input messages");
+                return Err("missing_input".to_string());
+            }
+        }
+    }
+
     let response_format = req
         .text
         .as_ref()
         .and_then(|t| t.format.clone())
-        .or_else(|| req.response_format.clone());
+        .or_else(|| req.response_format.clone())
+        .map(chat_response_format);
 
     // Handle logprobs - support both Responses API (top_logprobs) and Chat Completions (logprobs + top_logprobs)
     let (logprobs, top_logprobs) = match (req.logprobs, req.top_logprobs) {
@@ -311,6 +380,7 @@ Do not use JSON tool calls. Use the XML format above.";
     // is configured in the model family. The proxy simply forwards whatever
     // tools the client provides.
 
+    let tools = dedupe_tools(tools)?;
     let tools = if tools.is_empty() { None } else { Some(tools) };
 
     // Convert tool_choice to Value for backend
@@ -321,13 +391,46 @@ Do not use JSON tool calls. Use the XML format above.";
             ToolChoice::Specific(spec) => json!(spec),
         }
     });
+    // Operators may standardize tool eagerness per model when the client is silent
+    let tool_choice = tool_choice.or_else(|| {
+        let default = config
+            .default_tool_choice_for(&model)
+            .filter(|_| tools.is_some())?;
+        log::debug!("🔧 Using default tool_choice '{}' for {}", default, model);
+        Some(json!(default))
+    });
+
+    // OpenAI advises setting only one of temperature and top_p
+    let mut temperature = req.temperature.or(config.default_temperature);
+    let mut top_p = req.top_p.or(config.default_top_p);
+    if req.temperature.is_some() && req.top_p.is_some() {
+        log::warn!(
+            "⚠️  Both temperature and top_p set; forwarding {:?}",
+            config.sampling_param_preference
+        );
+        match config.sampling_param_preference {
+            SamplingParamPreference::Both => {}
+            SamplingParamPreference::Temperature => top_p = None,
+            SamplingParamPreference::TopP => temperature = None,
+        }
+    }
+
+    let reasoning_effort = reasoning_effort(req)?;
+
+    // Prefill: ask the backend to extend a trailing assistant message rather
+    // than open a new assistant turn after it
+    let continue_final =
+        config.continue_final_message && messages.last().is_some_and(|m| m.role == "assistant");
+    if continue_final {
+        log::debug!("✍️  Continuing trailing assistant message");
+    }
 
     Ok(ChatCompletionRequest {
         model,
         messages,
         max_tokens: req.max_output_tokens.or(req.max_tokens), // Support both field names
-        temperature: req.temperature,
-        top_p: req.top_p,
+        temperature,
+        top_p,
         response_format,
         tools,
         tool_choice,
@@ -352,19 +455,165 @@ Do not use JSON tool calls. Use the XML format above.";
         max_completion_tokens: req.max_completion_tokens,
         modalities: req.modalities.clone(),
         prediction: req.prediction.clone(),
-        reasoning_effort: req.reasoning_effort.clone(),
+        reasoning_effort,
         verbosity: req.verbosity.clone(),
         safety_identifier: req.safety_identifier.clone(),
         prompt_cache_key: req.prompt_cache_key.clone(),
         web_search_options: req.web_search_options.clone(),
         function_call: req.function_call.clone(),
         functions: req.functions.clone(),
+        continue_final_message: continue_final.then_some(true),
+        add_generation_prompt: continue_final.then_some(false),
     })
 }
 
+/// Effort levels forwarded to the backend as `reasoning_effort`
+const REASONING_EFFORTS: &[&str] = &["minimal", "low", "medium", "high"];
+
+/// The Chat Completions `reasoning_effort` for `reasoning.effort` (or the flat
+/// field); `none` turns reasoning off by omitting it
+fn reasoning_effort(req: &ResponseRequest) -> Result<Option<String>, String> {
+    let effort = req
+        .reasoning
+        .as_ref()
+        .and_then(|reasoning| reasoning.effort.as_deref())
+        .or(req.reasoning_effort.as_deref());
+    let Some(effort) = effort.map(str::to_ascii_lowercase) else {
+        return Ok(None);
+    };
+    if effort == "none" {
+        log::debug!("🧠 reasoning effort 'none': omitting reasoning_effort");
+        return Ok(None);
+    }
+    if !REASONING_EFFORTS.contains(&effort.as_str()) {
+        log::warn!("❌ Unsupported reasoning effort '{}'", effort);
+        return Err("invalid_reasoning_effort".to_string());
+    }
+    Ok(Some(effort))
+}
+
+/// `json_schema` name used when the client's format does not give one
+const DEFAULT_JSON_SCHEMA_NAME: &str = "response";
+
+/// The Chat Completions `response_format` for a Responses `text.format`. A
+/// `json_schema` format is nested under `json_schema` with its `name` (defaulted
+/// when missing), `strict` and `description` kept; other formats pass through.
+fn chat_response_format(format: Value) -> Value {
+    if format.get("type").and_then(Value::as_str) != Some("json_schema") {
+        return format;
+    }
+    let mut spec = match format.get("json_schema") {
+        // Already in Chat Completions form
+        Some(Value::Object(nested)) => nested.clone(),
+        _ => {
+            let mut spec = serde_json::Map::new();
+            for key in ["name", "description", "schema", "strict"] {
+                if let Some(value) = format.get(key) {
+                    spec.insert(key.to_string(), value.clone());
+                }
+            }
+            spec
+        }
+    };
+    if !spec.get("name").is_some_and(Value::is_string) {
+        spec.insert("name".to_string(), json!(DEFAULT_JSON_SCHEMA_NAME));
+    }
+    json!({"type": "json_schema", "json_schema": spec})
+}
+
+/// Drop repeated function definitions that are identical and reject names that
+/// are defined more than once with different schemas (backends reject both).
+fn dedupe_tools(tools: Vec<ChatTool>) -> Result<Vec<ChatTool>, String> {
+    let mut unique: Vec<ChatTool> = Vec::with_capacity(tools.len());
+
+    for tool in tools {
+        let ChatTool::Function { function, .. } = &tool;
+        let existing = unique.iter().find(|seen| {
+            let ChatTool::Function { function: seen, .. } = seen;
+            seen.name == function.name
+        });
+
+        match existing {
+            None => unique.push(tool),
+            Some(ChatTool::Function { function: seen, .. })
+                if seen.description == function.description
+                    && seen.parameters == function.parameters =>
+            {
+                log::warn!(
+                    "⚠️ Dropping duplicate definition of tool '{}'",
+                    function.name
+                );
+            }
+            Some(_) => {
+                log::error!(
+                    "❌ Tool '{}' is defined more than once with different schemas",
+                    function.name
+                );
+                return Err("duplicate_tool_name".to_string());
+            }
+        }
+    }
+
+    Ok(unique)
+}
+
+/// Whether any input message carries an image or file part
+pub fn input_has_media(input: &ResponseInput) -> bool {
+    let ResponseInput::Array(items) = input else {
+        return false;
+    };
+    items.iter().any(|item| match item {
+        ResponseInputItem::Message {
+            content: ResponseContent::Array(parts),
+            ..
+        } => parts.iter().any(|part| {
+            matches!(
+                part,
+                ContentPart::InputImage { .. } | ContentPart::InputFile { .. }
+            )
+        }),
+        _ => false,
+    })
+}
+
+/// Replace image and file parts with text notes for text-only models,
+/// returning how many parts were replaced
+pub fn strip_media_parts(input: &mut ResponseInput) -> usize {
+    let ResponseInput::Array(items) = input else {
+        return 0;
+    };
+    let mut stripped = 0;
+    for item in items {
+        let ResponseInputItem::Message {
+            content: ResponseContent::Array(parts),
+            ..
+        } = item
+        else {
+            continue;
+        };
+        for part in parts.iter_mut() {
+            let note = match part {
+                ContentPart::InputImage { .. } => "[image omitted]",
+                ContentPart::InputFile { .. } => "[file omitted]",
+                _ => continue,
+            };
+            *part = ContentPart::InputText {
+                text: note.to_string(),
+                cache_control: None,
+            };
+            stripped += 1;
+        }
+    }
+    stripped
+}
+
 /// Convert ResponseContent to JSON value for Chat Completions
-/// Returns (content_value, extracted_reasoning_text)
-fn convert_response_content(content: &ResponseContent) -> Result<(Value, Option<String>), String> {
+/// Returns (content_value, extracted_reasoning_text). With `forward_cache_control`,
+/// text parts keep their `cache_control` breakpoint.
+fn convert_response_content(
+    content: &ResponseContent,
+    forward_cache_control: bool,
+) -> Result<(Value, Option<String>), String> {
     match content {
         ResponseContent::String(text) => Ok((json!(text), None)),
         ResponseContent::Array(parts) => {
@@ -373,11 +622,24 @@ fn convert_response_content(content: &ResponseContent) -> Result<(Value, Option<
 
             for part in parts {
                 match part {
-                    ContentPart::InputText { text } | ContentPart::OutputText { text } => {
-                        converted.push(json!({
+                    ContentPart::InputText {
+                        text,
+                        cache_control,
+                    }
+                    | ContentPart::OutputText {
+                        text,
+                        cache_control,
+                    } => {
+                        let mut text_part = json!({
                             "type": "text",
                             "text": text
-                        }));
+                        });
+                        if let Some(cache_control) =
+                            cache_control.as_ref().filter(|_| forward_cache_control)
+                        {
+                            text_part["cache_control"] = cache_control.clone();
+                        }
+                        converted.push(text_part);
                     }
                     ContentPart::ToolOutput { body, .. } => {
                         converted.push(json!({
@@ -410,42 +672,60 @@ fn convert_response_content(content: &ResponseContent) -> Result<(Value, Option<
                 }
             }
 
-            // If all text parts (no images), concatenate into string
-            let has_images = parts
-                .iter()
-                .any(|p| matches!(p, ContentPart::InputImage { .. }));
-            let has_reasoning = !reasoning_text.is_empty();
-
-            if !has_images && !converted.is_empty() {
-                let text: String = parts
-                    .iter()
-                    .filter_map(|p| match p {
-                        ContentPart::InputText { text } | ContentPart::OutputText { text } => {
-                            Some(text.as_str())
-                        }
-                        ContentPart::ToolOutput { body, .. } => Some(body.as_str()),
-                        _ => None,
-                    })
-                    .collect::<Vec<_>>()
-                    .join("\n");
-                Ok((
-                    json!(text),
-                    if has_reasoning {
-                        Some(reasoning_text)
-                    } else {
-                        None
-                    },
-                ))
-            } else {
-                Ok((
-                    json!(converted),
-                    if has_reasoning {
-                        Some(reasoning_text)
-                    } else {
-                        None
-                    },
-                ))
+            // Reasoning never lands in `converted`; it travels separately so the
+            // caller can wrap it in <think> tags
+            let reasoning = (!reasoning_text.is_empty()).then_some(reasoning_text);
+
+            // Multimodal (or empty) content keeps the parts array so no image is dropped,
+            // as does content carrying cache breakpoints; plain text collapses to a string
+            let has_images = converted.iter().any(|p| p["type"] == "image_url");
+            let has_cache_control = converted.iter().any(|p| p.get("cache_control").is_some());
+            if has_images || has_cache_control || converted.is_empty() {
+                return Ok((json!(converted), reasoning));
             }
+            let text = converted
+                .iter()
+                .filter_map(|p| p["text"].as_str())
+                .collect::<Vec<_>>()
+                .join("\n");
+            Ok((json!(text), reasoning))
+        }
+    }
+}
+
+/// Determine the tool_call_id for a `role: "tool"` message item.
+///
+/// Backends reject tool messages without a `tool_call_id`, so when the client
+/// omits it we only derive one if exactly one earlier function_call is still
+/// unanswered; anything else is rejected with a descriptive error.
+fn resolve_tool_message_call_id(
+    tool_call_id: Option<&str>,
+    unanswered_call_ids: &[String],
+) -> Result<String, String> {
+    if let Some(id) = tool_call_id.map(str::trim).filter(|id| !id.is_empty()) {
+        return Ok(id.to_string());
+    }
+
+    match unanswered_call_ids {
+        [only] => {
+            log::info!(
+                "🔧 INPUT: Tool role message missing tool_call_id - using pending call {}",
+                only
+            );
+            Ok(only.clone())
+        }
+        [] => {
+            log::error!(
+                "❌ Tool role message missing tool_call_id and no preceding function_call to answer"
+            );
+            Err("tool_message_missing_tool_call_id".to_string())
+        }
+        _ => {
+            log::error!(
+                "❌ Tool role message missing tool_call_id with {} unanswered function calls",
+                unanswered_call_ids.len()
+            );
+            Err("tool_message_ambiguous_tool_call_id".to_string())
         }
     }
 }
@@ -459,7 +739,7 @@ fn extract_tool_message_body(content: &ResponseContent) -> Result<String, String
 
             for part in parts {
                 match part {
-                    ContentPart::InputText { text } | ContentPart::OutputText { text } => {
+                    ContentPart::InputText { text, .. } | ContentPart::OutputText { text, .. } => {
                         if !combined.is_empty() {
                             combined.push('\n');
                         }
@@ -501,3 +781,492 @@ pub fn translate_finish_reason(finish_reason: Option<&str>) -> &'static str {
         None => "in_progress",
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(value: Value) -> ResponseRequest {
+        serde_json::from_value(value).expect("valid request")
+    }
+
+    #[test]
+    fn test_array_instructions_become_system_message() {
+        let req = request(json!({
+            "model": "test-model",
+            "instructions": [
+                {"type": "input_text", "text": "You are terse."},
+                {"type": "input_text", "text": "Answer in French."}
+            ],
+            "input": "hi"
+        }));
+        assert_eq!(
+            req.instructions.as_deref(),
+            Some("You are terse.\nAnswer in French.")
+        );
+
+        let chat = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(chat.messages[0].role, "system");
+        let system = chat.messages[0].content.as_ref().unwrap().as_str().unwrap();
+        assert!(system.starts_with("You are terse.\nAnswer in French."));
+    }
+
+    #[test]
+    fn test_temperature_and_top_p_conflict() {
+        let logs = crate::utils::captured_logs();
+        let req = request(json!({
+            "model": "test-model",
+            "input": "hi",
+            "temperature": 0.3,
+            "top_p": 0.9
+        }));
+        let forwarded = |preference| {
+            let config = ProxyConfig {
+                sampling_param_preference: preference,
+                ..Default::default()
+            };
+            let chat = convert_to_chat_completions(&req, true, &config).unwrap();
+            (chat.temperature, chat.top_p)
+        };
+
+        assert_eq!(
+            forwarded(SamplingParamPreference::Both),
+            (Some(0.3), Some(0.9))
+        );
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains("Both temperature and top_p set")));
+        assert_eq!(
+            forwarded(SamplingParamPreference::Temperature),
+            (Some(0.3), None)
+        );
+        assert_eq!(forwarded(SamplingParamPreference::TopP), (None, Some(0.9)));
+    }
+
+    #[test]
+    fn test_reasoning_effort_forwarding() {
+        let convert = |body: Value| {
+            convert_to_chat_completions(&request(body), true, &ProxyConfig::default())
+        };
+
+        let chat =
+            convert(json!({"model": "m", "input": "hi", "reasoning": {"effort": "minimal"}}))
+                .unwrap();
+        assert_eq!(chat.reasoning_effort.as_deref(), Some("minimal"));
+        let chat =
+            convert(json!({"model": "m", "input": "hi", "reasoning_effort": "High"})).unwrap();
+        assert_eq!(chat.reasoning_effort.as_deref(), Some("high"));
+
+        // Disabling reasoning sends no reasoning_effort at all
+        let chat =
+            convert(json!({"model": "m", "input": "hi", "reasoning": {"effort": "none"}})).unwrap();
+        assert_eq!(chat.reasoning_effort, None);
+        assert!(serde_json::to_value(&chat)
+            .unwrap()
+            .get("reasoning_effort")
+            .is_none());
+
+        let err = convert(json!({"model": "m", "input": "hi", "reasoning": {"effort": "extreme"}}))
+            .unwrap_err();
+        assert_eq!(err, "invalid_reasoning_effort");
+    }
+
+    #[test]
+    fn test_json_schema_format_keeps_name_and_strict() {
+        let convert = |body: Value| {
+            convert_to_chat_completions(&request(body), true, &ProxyConfig::default())
+                .unwrap()
+                .response_format
+                .unwrap()
+        };
+        let schema = json!({"type": "object", "properties": {"city": {"type": "string"}}});
+
+        let format = convert(json!({
+            "model": "m",
+            "input": "hi",
+            "text": {"format": {"type": "json_schema", "name": "weather", "strict": true, "schema": schema}}
+        }));
+        assert_eq!(
+            format,
+            json!({
+                "type": "json_schema",
+                "json_schema": {"name": "weather", "strict": true, "schema": schema}
+            })
+        );
+
+        // Nameless specs get a default name; Chat-style formats stay nested
+        let format = convert(json!({
+            "model": "m",
+            "input": "hi",
+            "response_format": {"type": "json_schema", "json_schema": {"strict": false, "schema": schema}}
+        }));
+        assert_eq!(format["json_schema"]["name"], "response");
+        assert_eq!(format["json_schema"]["strict"], false);
+        assert_eq!(format["json_schema"]["schema"], schema);
+
+        let format = convert(json!({
+            "model": "m",
+            "input": "hi",
+            "text": {"format": {"type": "json_object"}}
+        }));
+        assert_eq!(format, json!({"type": "json_object"}));
+    }
+
+    #[test]
+    fn test_dangling_call_id_warns_when_verifying() {
+        let logs = crate::utils::captured_logs();
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "function_call", "call_id": "call_known", "name": "ls", "arguments": "{}"},
+                {"type": "function_call_output", "call_id": "call_known", "output": "a.txt"},
+                {"type": "function_call_output", "call_id": "call_dangling", "output": "b.txt"}
+            ]
+        }));
+        let config = ProxyConfig {
+            verify_call_ids: true,
+            ..Default::default()
+        };
+
+        convert_to_chat_completions(&req, true, &config).unwrap();
+
+        let logs = logs.lock().unwrap();
+        let dangling: Vec<_> = logs
+            .iter()
+            .filter(|line| line.contains("with no earlier function_call"))
+            .collect();
+        assert!(dangling.iter().any(|line| line.contains("call_dangling")));
+        assert!(!dangling.iter().any(|line| line.contains("call_known")));
+    }
+
+    #[test]
+    fn test_tool_message_without_call_id_is_rejected() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "message", "role": "user", "content": "hi"},
+                {"type": "message", "role": "tool", "content": "42"}
+            ]
+        }));
+
+        let err = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap_err();
+        assert_eq!(err, "tool_message_missing_tool_call_id");
+    }
+
+    #[test]
+    fn test_tool_format_hint_selected_per_model() {
+        let config = ProxyConfig {
+            tool_format_hints: vec![
+                ("coder".to_string(), "xml".to_string()),
+                (
+                    "glm".to_string(),
+                    "Emit <tool_call>{json}</tool_call> blocks".to_string(),
+                ),
+            ],
+            ..Default::default()
+        };
+        let system_prompt = |model: &str| {
+            let req = request(json!({
+                "model": model,
+                "instructions": "Be brief",
+                "input": "hi",
+                "tools": [{"type": "function", "name": "lookup", "parameters": {}}]
+            }));
+            let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+            chat_req.messages[0].content.clone().unwrap().to_string()
+        };
+
+        let glm = system_prompt("zai-org/GLM-4.6");
+        assert!(glm.contains("Emit <tool_call>{json}</tool_call> blocks"));
+        assert!(!glm.contains("native function calling"));
+
+        // Preset names swap in the built-in instruction despite native support
+        assert!(system_prompt("Qwen3-Coder").contains("<function=function_name>"));
+
+        // Unmatched models keep the capability-based default
+        assert!(system_prompt("other-model").contains("native function calling"));
+    }
+
+    #[test]
+    fn test_no_inject_models_get_no_tool_guidance() {
+        let config = ProxyConfig {
+            no_inject_models: vec!["fragile-model".to_string()],
+            // Even an explicit hint does not override the denylist
+            tool_format_hints: vec![("fragile".to_string(), "xml".to_string())],
+            ..Default::default()
+        };
+        let system_prompt = |model: &str| {
+            let req = request(json!({
+                "model": model,
+                "instructions": "Be brief",
+                "input": "hi",
+                "tools": [{"type": "function", "name": "lookup", "parameters": {}}]
+            }));
+            let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+            chat_req.messages[0].content.clone().unwrap()
+        };
+
+        assert_eq!(system_prompt("Fragile-Model"), json!("Be brief"));
+        let other = system_prompt("fragile-model-v2").to_string();
+        assert!(other.contains("Tool Calling Format Override"));
+        assert!(other.contains("File Operation Best Practices"));
+    }
+
+    #[test]
+    fn test_tool_message_call_id_derived_from_single_pending_call() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "function_call", "call_id": "call_1", "name": "lookup", "arguments": "{}"},
+                {"type": "message", "role": "assistant", "content": ""},
+                {"type": "message", "role": "tool", "content": "42"}
+            ]
+        }));
+
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        let tool_msg = chat_req.messages.last().unwrap();
+        assert_eq!(tool_msg.role, "tool");
+        assert_eq!(tool_msg.tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[test]
+    fn test_tool_message_ambiguous_call_id_is_rejected() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "function_call", "call_id": "call_1", "name": "a", "arguments": "{}"},
+                {"type": "function_call", "call_id": "call_2", "name": "b", "arguments": "{}"},
+                {"type": "message", "role": "assistant", "content": ""},
+                {"type": "message", "role": "tool", "content": "42"}
+            ]
+        }));
+
+        let err = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap_err();
+        assert_eq!(err, "tool_message_ambiguous_tool_call_id");
+    }
+
+    #[test]
+    fn test_tool_message_accepts_call_id_field() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "message", "role": "tool", "call_id": "call_9", "content": "ok"}
+            ]
+        }));
+
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(chat_req.messages[0].tool_call_id.as_deref(), Some("call_9"));
+    }
+
+    #[test]
+    fn test_instructions_only_appends_empty_user_message() {
+        let req = request(json!({"model": "test-model", "instructions": "Be terse."}));
+
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        let roles: Vec<_> = chat_req.messages.iter().map(|m| m.role.as_str()).collect();
+        assert_eq!(roles, ["system", "user"]);
+        assert_eq!(chat_req.messages[1].content, Some(json!("")));
+    }
+
+    #[test]
+    fn test_instructions_only_rejected_when_configured() {
+        let req = request(json!({"model": "test-model", "instructions": "Be terse."}));
+        let config = ProxyConfig {
+            instructions_only_policy: InstructionsOnlyPolicy::Reject,
+            ..Default::default()
+        };
+
+        let err = convert_to_chat_completions(&req, true, &config).unwrap_err();
+        assert_eq!(err, "missing_input");
+    }
+
+    #[test]
+    fn test_prior_reasoning_sent_as_reasoning_content_when_enabled() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "message", "role": "user", "content": "hi"},
+                {"type": "reasoning", "text": "greet back"},
+                {"type": "message", "role": "assistant", "content": "Hello!"},
+                {"type": "message", "role": "user", "content": "again"}
+            ]
+        }));
+
+        let inline = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(
+            inline.messages[1].content,
+            Some(json!("<think>greet back</think>\nHello!"))
+        );
+        assert_eq!(inline.messages[1].reasoning_content, None);
+
+        let config = ProxyConfig {
+            reasoning_as_field: true,
+            ..Default::default()
+        };
+        let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+        let assistant = &chat_req.messages[1];
+        assert_eq!(assistant.role, "assistant");
+        assert_eq!(assistant.content, Some(json!("Hello!")));
+        assert_eq!(assistant.reasoning_content.as_deref(), Some("greet back"));
+    }
+
+    #[test]
+    fn test_trailing_assistant_message_marked_for_continuation() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [
+                {"type": "message", "role": "user", "content": "Write a haiku"},
+                {"type": "message", "role": "assistant", "content": "Autumn moonlight"}
+            ]
+        }));
+
+        let default = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(default.continue_final_message, None);
+
+        let config = ProxyConfig {
+            continue_final_message: true,
+            ..Default::default()
+        };
+        let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+        assert_eq!(chat_req.continue_final_message, Some(true));
+        assert_eq!(chat_req.add_generation_prompt, Some(false));
+
+        // A trailing user turn is answered normally
+        let req = request(json!({"model": "test-model", "input": "hi"}));
+        let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+        assert_eq!(chat_req.continue_final_message, None);
+        assert_eq!(chat_req.add_generation_prompt, None);
+    }
+
+    fn tool_names(chat_req: &ChatCompletionRequest) -> Vec<String> {
+        chat_req
+            .tools
+            .iter()
+            .flatten()
+            .map(|tool| {
+                let ChatTool::Function { function, .. } = tool;
+                function.name.clone()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_identical_duplicate_tools_are_deduplicated() {
+        let tool =
+            json!({"type": "function", "name": "read_file", "parameters": {"type": "object"}});
+        let req = request(json!({
+            "model": "test-model",
+            "input": "hi",
+            "tools": [tool.clone(), tool]
+        }));
+
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(tool_names(&chat_req), ["read_file"]);
+    }
+
+    #[test]
+    fn test_conflicting_duplicate_tools_are_rejected() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": "hi",
+            "tools": [
+                {"type": "function", "name": "read_file", "parameters": {"type": "object"}},
+                {"type": "function", "name": "read_file", "description": "other", "parameters": {}}
+            ]
+        }));
+
+        let err = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap_err();
+        assert_eq!(err, "duplicate_tool_name");
+    }
+
+    #[test]
+    fn test_explicit_zero_sampling_params_are_forwarded() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": "hi",
+            "temperature": 0,
+            "top_p": 0.0
+        }));
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        let body = serde_json::to_value(&chat_req).unwrap();
+        assert_eq!(body["temperature"], json!(0.0));
+        assert_eq!(body["top_p"], json!(0.0));
+
+        // Absent values stay absent so the backend applies its own defaults
+        let req = request(json!({"model": "test-model", "input": "hi"}));
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        let body = serde_json::to_value(&chat_req).unwrap();
+        assert!(body.get("temperature").is_none());
+        assert!(body.get("top_p").is_none());
+    }
+
+    #[test]
+    fn test_text_part_cache_control_forwarded_when_enabled() {
+        let req = request(json!({
+            "model": "test-model",
+            "input": [{"type": "message", "role": "user", "content": [
+                {"type": "input_text", "text": "long shared context", "cache_control": {"type": "ephemeral"}},
+                {"type": "input_text", "text": "question"}
+            ]}]
+        }));
+
+        // Off by default: the parts collapse to plain text
+        let chat_req = convert_to_chat_completions(&req, true, &ProxyConfig::default()).unwrap();
+        assert_eq!(
+            chat_req.messages[0].content,
+            Some(json!("long shared context\nquestion"))
+        );
+
+        let config = ProxyConfig {
+            forward_cache_control: true,
+            ..Default::default()
+        };
+        let chat_req = convert_to_chat_completions(&req, true, &config).unwrap();
+        assert_eq!(
+            chat_req.messages[0].content,
+            Some(json!([
+                {"type": "text", "text": "long shared context", "cache_control": {"type": "ephemeral"}},
+                {"type": "text", "text": "question"}
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_content_array_keeps_images_and_extracts_reasoning() {
+        let content = |parts: Value| -> ResponseContent {
+            serde_json::from_value(parts).expect("valid content")
+        };
+        let image = json!({"type": "input_image", "image_url": {"url": "https://x/cat.png"}});
+        let text = json!({"type": "input_text", "text": "describe"});
+        let reasoning = json!({"type": "reasoning", "text": "thinking"});
+
+        // image + text: both parts survive as an array
+        let (value, extracted) =
+            convert_response_content(&content(json!([image, text])), false).unwrap();
+        assert_eq!(value[0]["type"], "image_url");
+        assert_eq!(value[0]["image_url"]["url"], "https://x/cat.png");
+        assert_eq!(value[1], json!({"type": "text", "text": "describe"}));
+        assert_eq!(extracted, None);
+
+        // image + reasoning: image kept, reasoning extracted
+        let (value, extracted) =
+            convert_response_content(&content(json!([image, reasoning])), false).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 1);
+        assert_eq!(value[0]["type"], "image_url");
+        assert_eq!(extracted.as_deref(), Some("thinking"));
+
+        // text + reasoning: text collapses to a string, reasoning extracted
+        let (value, extracted) =
+            convert_response_content(&content(json!([text, reasoning])), false).unwrap();
+        assert_eq!(value, json!("describe"));
+        assert_eq!(extracted.as_deref(), Some("thinking"));
+
+        // image + text + reasoning
+        let (value, extracted) =
+            convert_response_content(&content(json!([reasoning, image, text])), false).unwrap();
+        assert_eq!(value.as_array().unwrap().len(), 2);
+        assert_eq!(extracted.as_deref(), Some("thinking"));
+    }
+}
@@ -8,6 +8,25 @@ pub fn format_backend_error(error_msg: &str, _raw_body: &str) -> String {
     )
 }
 
+/// Client-safe stand-in for a backend error, chosen by HTTP status only so no
+/// backend URLs, traces or provider details reach the client
+pub fn sanitized_backend_error(status: u16) -> String {
+    let summary = match status {
+        400 | 422 => "The backend rejected the request as invalid.",
+        401 | 403 => "The backend rejected the API key.",
+        404 => "The requested model or resource was not found.",
+        408 | 504 => "The backend timed out.",
+        413 => "The request is too large for the backend.",
+        429 => "The backend is rate limiting requests.",
+        500..=599 => "The backend is temporarily unavailable.",
+        _ => "The backend returned an error.",
+    };
+    format!(
+        "⚠️ Backend Error ({}):\n\n{}\n\nPlease try again or contact the proxy operator.",
+        status, summary
+    )
+}
+
 /// Build a formatted model list for 404 responses
 pub fn build_model_list_content(requested_model: &str, models: &[ModelInfo]) -> String {
     let mut content = format!("❌ Model '{}' not found.\n\n", requested_model);
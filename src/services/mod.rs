@@ -2,10 +2,16 @@ pub mod auth;
 pub mod converter;
 pub mod error_formatting;
 pub mod model_cache;
+pub mod prompt_registry;
+pub mod replay;
+pub mod response_store;
 pub mod streaming;
 
 pub use auth::*;
 pub use converter::*;
 pub use error_formatting::*;
 pub use model_cache::*;
+pub use prompt_registry::*;
+pub use replay::*;
+pub use response_store::*;
 pub use streaming::*;
@@ -1,17 +1,33 @@
 use crate::models::{App, ModelInfo};
 use serde_json::Value;
 
-/// Build `/v1/models` URL from backend chat completions URL.
+/// Default Chat Completions path joined onto `BACKEND_BASE_URL`
+pub const DEFAULT_BACKEND_PATH: &str = "/v1/chat/completions";
+
+/// Join a backend base URL and endpoint path with exactly one `/` between them
+pub fn join_backend_url(base: &str, path: &str) -> String {
+    let path = path.trim_start_matches('/');
+    let base = base.trim_end_matches('/');
+    if path.is_empty() {
+        base.to_string()
+    } else {
+        format!("{}/{}", base, path)
+    }
+}
+
+/// Build the models URL from the backend chat completions URL.
 fn models_url_from_backend_url(backend_url: &str) -> String {
-    // best-effort: replace trailing `/v1/chat/completions` with `/v1/models`
-    if let Some(idx) = backend_url.rfind("/v1/chat/completions") {
-        let mut s = String::with_capacity(backend_url.len());
-        s.push_str(&backend_url[..idx]);
-        s.push_str("/v1/models");
+    // best-effort: replace trailing `/chat/completions` with `/models`, which
+    // covers both `/v1/chat/completions` and bare `/chat/completions` paths
+    let trimmed = backend_url.trim_end_matches('/');
+    if let Some(idx) = trimmed.rfind("/chat/completions") {
+        let mut s = String::with_capacity(trimmed.len());
+        s.push_str(&trimmed[..idx]);
+        s.push_str("/models");
         s
     } else {
         // fallback: assume same host, standard path
-        format!("{}/../models", backend_url.trim_end_matches('/'))
+        format!("{}/../models", trimmed)
     }
 }
 
@@ -76,6 +92,27 @@ pub async fn refresh_models_cache(app: &App) -> Result<(), Box<dyn std::error::E
     Ok(())
 }
 
+/// Startup self-test: load the model list once and log whether the backend
+/// answered and how many models it serves
+pub async fn probe_backend(app: &App) -> Result<usize, String> {
+    if let Err(e) = refresh_models_cache(app).await {
+        let message = e.to_string();
+        log::error!(
+            "❌ Startup probe: backend {} is not reachable: {}",
+            app.backend_url,
+            message
+        );
+        return Err(message);
+    }
+    let count = app.models_cache.read().await.as_ref().map_or(0, Vec::len);
+    log::info!(
+        "🩺 Startup probe: backend {} reachable, {} model(s) available",
+        app.backend_url,
+        count
+    );
+    Ok(count)
+}
+
 /// Get cached models or fetch if not available
 pub async fn get_available_models(app: &App) -> Vec<ModelInfo> {
     {
@@ -134,3 +171,99 @@ pub async fn model_supports_feature(model: &str, feature: &str, app: &App) -> bo
     }
     false
 }
+
+/// Whether a model's declared capability list leaves out `feature`. Models
+/// missing from the cache, or declaring no features at all, are not ruled out.
+pub async fn model_lacks_feature(model: &str, feature: &str, app: &App) -> bool {
+    let cache = app.models_cache.read().await;
+    cache
+        .as_ref()
+        .and_then(|models| models.iter().find(|m| m.id.eq_ignore_ascii_case(model)))
+        .is_some_and(|model_info| {
+            !model_info.supported_features.is_empty()
+                && !model_info
+                    .supported_features
+                    .iter()
+                    .any(|f| f.eq_ignore_ascii_case(feature))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::ProxyConfig;
+    use axum::{routing::get, Json, Router};
+    use serde_json::json;
+
+    fn probe_app(backend_url: String) -> App {
+        App::for_tests(&backend_url, ProxyConfig::default())
+    }
+
+    #[tokio::test]
+    async fn test_startup_probe_logs_model_count() {
+        let router = Router::new().route(
+            "/v1/models",
+            get(|| async { Json(json!({"data": [{"id": "model-a"}, {"id": "model-b"}]})) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(listener, router).await.unwrap();
+        });
+
+        let backend_url = format!("http://{}/v1/chat/completions", addr);
+        let app = probe_app(backend_url.clone());
+        assert_eq!(probe_backend(&app).await, Ok(2));
+        let logs = crate::utils::captured_logs();
+        let expected = format!(
+            "Startup probe: backend {} reachable, 2 model(s) available",
+            backend_url
+        );
+        assert!(logs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|line| line.contains(&expected)));
+
+        // Nothing listens on port 1
+        let app = probe_app("http://127.0.0.1:1/v1/chat/completions".to_string());
+        assert!(probe_backend(&app).await.is_err());
+    }
+
+    #[test]
+    fn test_join_backend_url_handles_slashes() {
+        let expected = "http://llm.local:8000/v1/chat/completions";
+        for (base, path) in [
+            ("http://llm.local:8000", "/v1/chat/completions"),
+            ("http://llm.local:8000/", "/v1/chat/completions"),
+            ("http://llm.local:8000/", "v1/chat/completions"),
+            ("http://llm.local:8000", "v1/chat/completions"),
+            ("http://llm.local:8000//", "//v1/chat/completions"),
+        ] {
+            assert_eq!(join_backend_url(base, path), expected, "{base} + {path}");
+        }
+        assert_eq!(
+            join_backend_url("https://gw.example.com/openai/", DEFAULT_BACKEND_PATH),
+            "https://gw.example.com/openai/v1/chat/completions"
+        );
+        assert_eq!(
+            join_backend_url("http://llm.local", "/"),
+            "http://llm.local"
+        );
+    }
+
+    #[test]
+    fn test_models_url_shares_backend_base() {
+        assert_eq!(
+            models_url_from_backend_url(&join_backend_url(
+                "http://llm.local/",
+                DEFAULT_BACKEND_PATH
+            )),
+            "http://llm.local/v1/models"
+        );
+        assert_eq!(
+            models_url_from_backend_url(&join_backend_url("http://llm.local", "/chat/completions")),
+            "http://llm.local/models"
+        );
+    }
+}
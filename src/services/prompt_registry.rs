@@ -0,0 +1,127 @@
+use crate::models::{ResponseContent, ResponseInput, ResponseInputItem, ResponsePrompt};
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+/// Locally maintained prompt template, addressed by `prompt.id` in requests.
+/// Placeholders use the `{{name}}` syntax and are filled from `prompt.variables`.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct PromptTemplate {
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub input: Option<String>,
+}
+
+/// Prompt id -> template
+pub type PromptRegistry = HashMap<String, PromptTemplate>;
+
+/// Load the prompt registry from a JSON file (`{"<id>": {"instructions": ..., "input": ...}}`)
+pub fn load_prompt_registry(path: &str) -> Result<PromptRegistry, String> {
+    let raw = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&raw).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Replace `{{name}}` placeholders with the matching variable values.
+/// String variables are inserted verbatim; other JSON values are serialized.
+/// The template is scanned once, so placeholders inside inserted values stay literal.
+fn render_template(template: &str, variables: Option<&Value>) -> String {
+    let vars = variables.and_then(|v| v.as_object());
+    let mut rendered = String::with_capacity(template.len());
+    let mut unresolved = false;
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        let end = start + 2 + len + 2;
+        rendered.push_str(&rest[..start]);
+        match vars.and_then(|vars| vars.get(&rest[start + 2..end - 2])) {
+            Some(Value::String(s)) => rendered.push_str(s),
+            Some(other) => rendered.push_str(&other.to_string()),
+            None => {
+                unresolved = true;
+                rendered.push_str(&rest[start..end]);
+            }
+        }
+        rest = &rest[end..];
+    }
+    rendered.push_str(rest);
+    if unresolved {
+        log::warn!("⚠️  Prompt template has unresolved placeholders after expansion");
+    }
+    rendered
+}
+
+/// Expand a registered template into the request's `instructions` and `input`.
+///
+/// Template instructions are placed ahead of any client instructions; template
+/// input becomes the leading user turn.
+pub fn expand_prompt(
+    template: &PromptTemplate,
+    prompt: &ResponsePrompt,
+    instructions: &mut Option<String>,
+    input: &mut Option<ResponseInput>,
+) {
+    let variables = prompt.variables.as_ref();
+
+    if let Some(template_instructions) = &template.instructions {
+        let rendered = render_template(template_instructions, variables);
+        *instructions = Some(match instructions.take() {
+            Some(existing) if !existing.is_empty() => format!("{}\n\n{}", rendered, existing),
+            _ => rendered,
+        });
+    }
+
+    if let Some(template_input) = &template.input {
+        let rendered = render_template(template_input, variables);
+        *input = Some(match input.take() {
+            None => ResponseInput::String(rendered),
+            Some(ResponseInput::String(existing)) => {
+                ResponseInput::String(format!("{}\n\n{}", rendered, existing))
+            }
+            Some(ResponseInput::Array(mut items)) => {
+                items.insert(
+                    0,
+                    ResponseInputItem::Message {
+                        role: "user".to_string(),
+                        content: ResponseContent::String(rendered),
+                        tool_call_id: None,
+                        metadata: None,
+                        attachments: None,
+                    },
+                );
+                ResponseInput::Array(items)
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_render_template_fills_variables() {
+        let vars = json!({"name": "Ada", "count": 3});
+        assert_eq!(
+            render_template("Hi {{name}}, you have {{count}} items", Some(&vars)),
+            "Hi Ada, you have 3 items"
+        );
+        assert_eq!(render_template("Hi {{name}}", None), "Hi {{name}}");
+    }
+
+    #[test]
+    fn test_render_template_leaves_placeholders_in_values() {
+        let vars = json!({"a": "{{b}}", "b": "secret"});
+        assert_eq!(
+            render_template("{{a}} and {{b}}", Some(&vars)),
+            "{{b}} and secret"
+        );
+        assert_eq!(
+            render_template("{{missing}} {{a}} {{", Some(&vars)),
+            "{{missing}} {{b}} {{"
+        );
+    }
+}
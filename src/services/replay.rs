@@ -0,0 +1,183 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+/// Upper bound on events retained per response; older events fall out of the window
+const MAX_REPLAY_EVENTS: usize = 4096;
+
+/// Serialized SSE events of one response, kept for `Last-Event-ID` resumption
+struct ReplayLog {
+    events: VecDeque<(u32, String)>,
+    complete: bool,
+    touched: Instant,
+    notify: Arc<Notify>,
+}
+
+/// Short-lived per-request buffer of emitted SSE events.
+///
+/// Logs are keyed by a fingerprint of the client key and request body, so a
+/// client that re-sends the same request with `Last-Event-ID` resumes the
+/// original generation instead of starting a new one.
+pub struct ReplayBuffer {
+    window: Duration,
+    logs: Mutex<HashMap<u64, ReplayLog>>,
+}
+
+/// Result of looking up events after a given sequence number
+pub struct ReplayBatch {
+    pub events: Vec<(u32, String)>,
+    pub complete: bool,
+}
+
+impl ReplayBuffer {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window,
+            logs: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Identify a request for replay purposes
+    pub fn fingerprint(client_key: &str, body: &str) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        client_key.hash(&mut hasher);
+        body.hash(&mut hasher);
+        hasher.finish()
+    }
+
+    /// Start (or restart) the log for a request, dropping expired logs. An
+    /// identical request still generating keeps its log, and the newcomer gets
+    /// no recorder, so a reconnect can never be served the other generation.
+    pub fn begin(self: &Arc<Self>, fingerprint: u64) -> Option<ReplayRecorder> {
+        let mut logs = self.logs.lock().unwrap();
+        let window = self.window;
+        logs.retain(|_, log| log.touched.elapsed() < window);
+        if logs.get(&fingerprint).is_some_and(|log| !log.complete) {
+            return None;
+        }
+        logs.insert(
+            fingerprint,
+            ReplayLog {
+                events: VecDeque::new(),
+                complete: false,
+                touched: Instant::now(),
+                notify: Arc::new(Notify::new()),
+            },
+        );
+        Some(ReplayRecorder {
+            buffer: Arc::clone(self),
+            fingerprint,
+        })
+    }
+
+    /// Whether events after `last_seq` can still be replayed for this request
+    pub fn can_resume(&self, fingerprint: u64, last_seq: u32) -> bool {
+        let logs = self.logs.lock().unwrap();
+        logs.get(&fingerprint).is_some_and(|log| {
+            log.touched.elapsed() < self.window
+                && log
+                    .events
+                    .front()
+                    .is_some_and(|(first, _)| *first <= last_seq.saturating_add(1))
+        })
+    }
+
+    /// Handle notified whenever the request's log gains events or completes
+    pub fn notifier(&self, fingerprint: u64) -> Option<Arc<Notify>> {
+        let logs = self.logs.lock().unwrap();
+        logs.get(&fingerprint).map(|log| Arc::clone(&log.notify))
+    }
+
+    /// Buffered events after `last_seq`, oldest first
+    pub fn events_after(&self, fingerprint: u64, last_seq: u32) -> Option<ReplayBatch> {
+        let logs = self.logs.lock().unwrap();
+        let log = logs.get(&fingerprint)?;
+        let events = log
+            .events
+            .iter()
+            .filter(|(seq, _)| *seq > last_seq)
+            .cloned()
+            .collect();
+        Some(ReplayBatch {
+            events,
+            complete: log.complete,
+        })
+    }
+
+    /// How long an idle log is kept
+    pub fn window(&self) -> Duration {
+        self.window
+    }
+
+    fn push(&self, fingerprint: u64, sequence: u32, json: &str) {
+        let mut logs = self.logs.lock().unwrap();
+        if let Some(log) = logs.get_mut(&fingerprint) {
+            if log.events.len() >= MAX_REPLAY_EVENTS {
+                log.events.pop_front();
+            }
+            log.events.push_back((sequence, json.to_string()));
+            log.touched = Instant::now();
+            log.notify.notify_waiters();
+        }
+    }
+
+    fn finish(&self, fingerprint: u64) {
+        let mut logs = self.logs.lock().unwrap();
+        if let Some(log) = logs.get_mut(&fingerprint) {
+            log.complete = true;
+            log.touched = Instant::now();
+            log.notify.notify_waiters();
+        }
+    }
+}
+
+/// Write handle for one response's log; marks the log complete when dropped
+pub struct ReplayRecorder {
+    buffer: Arc<ReplayBuffer>,
+    fingerprint: u64,
+}
+
+impl ReplayRecorder {
+    pub fn record(&self, sequence: u32, json: &str) {
+        self.buffer.push(self.fingerprint, sequence, json);
+    }
+}
+
+impl Drop for ReplayRecorder {
+    fn drop(&mut self) {
+        self.buffer.finish(self.fingerprint);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_identical_request_in_flight_keeps_its_log() {
+        let buffer = Arc::new(ReplayBuffer::new(Duration::from_secs(60)));
+        let fingerprint = ReplayBuffer::fingerprint("key", "{}");
+
+        let first = buffer.begin(fingerprint).expect("first recorder");
+        first.record(1, "first-1");
+        // A concurrent retry of the same request does not take over the log
+        assert!(buffer.begin(fingerprint).is_none());
+        first.record(2, "first-2");
+        let batch = buffer.events_after(fingerprint, 0).unwrap();
+        assert_eq!(
+            batch.events,
+            vec![(1, "first-1".to_string()), (2, "first-2".to_string())]
+        );
+
+        // Once the first generation is finished the next one starts fresh
+        drop(first);
+        let second = buffer.begin(fingerprint).expect("second recorder");
+        second.record(1, "second-1");
+        let batch = buffer.events_after(fingerprint, 0).unwrap();
+        assert_eq!(batch.events, vec![(1, "second-1".to_string())]);
+    }
+}
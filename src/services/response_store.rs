@@ -0,0 +1,83 @@
+use crate::models::Response;
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::Mutex,
+};
+
+/// Persistence for finished responses created with `store: true`.
+///
+/// Entries are scoped to an owner (see `store_owner`) so one client can never
+/// read or delete another client's responses by guessing an id.
+pub trait ResponseStore: Send + Sync {
+    fn put(&self, owner: &str, response: Response);
+    fn get(&self, owner: &str, id: &str) -> Option<Response>;
+    /// Remove a response, reporting whether it existed
+    fn delete(&self, owner: &str, id: &str) -> bool;
+}
+
+/// Owner key for a client API key; the key itself is never stored. Stable for
+/// the lifetime of the process.
+pub fn store_owner(client_key: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    client_key.hash(&mut hasher);
+    format!("{:016x}", hasher.finish())
+}
+
+/// Owner and response id
+type StoreKey = (String, String);
+
+#[derive(Default)]
+struct StoredResponses {
+    responses: HashMap<StoreKey, Response>,
+    /// Insertion order, oldest first, for eviction
+    order: VecDeque<StoreKey>,
+}
+
+/// Process-local store keeping the most recent `max_entries` responses
+pub struct InMemoryResponseStore {
+    max_entries: usize,
+    entries: Mutex<StoredResponses>,
+}
+
+impl InMemoryResponseStore {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            entries: Mutex::default(),
+        }
+    }
+}
+
+impl ResponseStore for InMemoryResponseStore {
+    fn put(&self, owner: &str, response: Response) {
+        let key = (owner.to_string(), response.id.clone());
+        let mut entries = self.entries.lock().unwrap();
+        if entries.responses.insert(key.clone(), response).is_none() {
+            entries.order.push_back(key);
+        }
+        while entries.order.len() > self.max_entries {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.responses.remove(&oldest);
+            }
+        }
+    }
+
+    fn get(&self, owner: &str, id: &str) -> Option<Response> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .responses
+            .get(&(owner.to_string(), id.to_string()))
+            .cloned()
+    }
+
+    fn delete(&self, owner: &str, id: &str) -> bool {
+        let key = (owner.to_string(), id.to_string());
+        let mut entries = self.entries.lock().unwrap();
+        if entries.responses.remove(&key).is_none() {
+            return false;
+        }
+        entries.order.retain(|entry| entry != &key);
+        true
+    }
+}
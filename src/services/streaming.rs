@@ -1,5 +1,11 @@
-/// Maximum buffer size before clearing (1MB)
-const MAX_BUFFER_SIZE: usize = 1_048_576;
+use crate::models::DEFAULT_MAX_SSE_EVENT_BYTES;
+
+/// A backend event grew past the parser's limit without being terminated
+#[derive(Debug)]
+pub struct OversizedSseEvent {
+    pub bytes: usize,
+    pub limit: usize,
+}
 
 /// Simple SSE event parser that accumulates lines until a blank line, then yields the combined `data:` payload.
 /// This follows the SSE spec: multiple `data:` lines per event are joined by `\n`.
@@ -7,6 +13,7 @@ pub struct SseEventParser {
     buf: String,
     // Accumulates data: lines for the current event until blank line.
     cur_data_lines: Vec<String>,
+    max_event_bytes: usize,
 }
 
 impl SseEventParser {
@@ -14,34 +21,29 @@ impl SseEventParser {
         Self {
             buf: String::with_capacity(16 * 1024),
             cur_data_lines: Vec::with_capacity(4),
+            max_event_bytes: DEFAULT_MAX_SSE_EVENT_BYTES,
         }
     }
 
+    /// Fail once a single unterminated event exceeds `limit` bytes
+    pub fn with_max_event_bytes(mut self, limit: usize) -> Self {
+        self.max_event_bytes = limit;
+        self
+    }
+
     /// Feed bytes and extract zero or more complete SSE event payloads (already joined).
-    pub fn push_and_drain_events(&mut self, chunk: &[u8]) -> Vec<String> {
+    /// Errors when the event still being received outgrows the size limit; the
+    /// parser is then reset, as the stream cannot be trusted past that point.
+    pub fn push_and_drain_events(
+        &mut self,
+        chunk: &[u8],
+    ) -> Result<Vec<String>, OversizedSseEvent> {
         let s = String::from_utf8_lossy(chunk);
-
-        // Check buffer size limit to prevent unbounded growth
-        if self.buf.len() + s.len() > MAX_BUFFER_SIZE {
-            log::warn!(
-                "⚠️  SSE buffer exceeded {}MB limit (current: {} bytes, incoming: {} bytes). Clearing buffer to prevent memory exhaustion.",
-                MAX_BUFFER_SIZE / 1_048_576,
-                self.buf.len(),
-                s.len()
-            );
-            // Clear buffer and start fresh with new chunk
-            self.buf.clear();
-            self.cur_data_lines.clear();
-        }
-
         self.buf.push_str(&s);
         let mut out = Vec::new();
 
-        loop {
-            // Find next newline
-            let Some(pos) = self.buf.find('\n') else {
-                break;
-            };
+        // Find next newline
+        while let Some(pos) = self.buf.find('\n') {
             // Take one line (retain possible preceding \r, we'll trim)
             let mut line = self.buf.drain(..=pos).collect::<String>();
             if line.ends_with('\n') {
@@ -54,20 +56,152 @@ impl SseEventParser {
 
             // Blank line => event terminator
             if trimmed.is_empty() {
-                if !self.cur_data_lines.is_empty() {
-                    let payload = self.cur_data_lines.join("\n");
-                    self.cur_data_lines.clear();
-                    out.push(payload);
-                }
+                out.extend(self.take_pending());
                 continue;
             }
 
             // Only collect `data:` lines, ignore others (e.g., `event:`/`id:`)
             if let Some(rest) = trimmed.strip_prefix("data:") {
-                self.cur_data_lines.push(rest.trim_start().to_string());
+                let data = rest.trim_start();
+                // Some backends skip the blank line between events; a new `data:`
+                // line after a complete JSON payload (or `[DONE]`) starts a new event
+                if data == "[DONE]" || self.pending_is_complete_json() {
+                    if let Some(payload) = self.take_pending() {
+                        log::debug!("🔧 SSE event was not terminated by a blank line");
+                        out.push(payload);
+                    }
+                }
+                if data == "[DONE]" {
+                    out.push(data.to_string());
+                } else {
+                    self.cur_data_lines.push(data.to_string());
+                }
             }
         }
 
+        // Whatever is left belongs to one event that has not been terminated yet
+        let pending_bytes =
+            self.buf.len() + self.cur_data_lines.iter().map(String::len).sum::<usize>();
+        if pending_bytes > self.max_event_bytes {
+            self.buf.clear();
+            self.cur_data_lines.clear();
+            return Err(OversizedSseEvent {
+                bytes: pending_bytes,
+                limit: self.max_event_bytes,
+            });
+        }
+
+        Ok(out)
+    }
+
+    /// Flush what the stream left unterminated once the backend closes it: a
+    /// trailing line without `\n` and any event missing its blank line
+    pub fn finish(&mut self) -> Vec<String> {
+        let mut out = if self.buf.is_empty() {
+            Vec::new()
+        } else {
+            self.push_and_drain_events(b"\n").unwrap_or_default()
+        };
+        out.extend(self.take_pending());
         out
     }
+
+    fn take_pending(&mut self) -> Option<String> {
+        if self.cur_data_lines.is_empty() {
+            return None;
+        }
+        let payload = self.cur_data_lines.join("\n");
+        self.cur_data_lines.clear();
+        Some(payload)
+    }
+
+    fn pending_is_complete_json(&self) -> bool {
+        !self.cur_data_lines.is_empty()
+            && serde_json::from_str::<serde::de::IgnoredAny>(&self.cur_data_lines.join("\n"))
+                .is_ok()
+    }
+}
+
+/// Split a `data:` payload that batches several chunk objects into a JSON
+/// array into one payload per chunk; anything else is returned unchanged
+pub fn split_batched_payload(payload: String) -> Vec<String> {
+    if !payload.trim_start().starts_with('[') {
+        return vec![payload];
+    }
+    match serde_json::from_str::<Vec<serde_json::Value>>(&payload) {
+        Ok(chunks) => chunks.iter().map(|chunk| chunk.to_string()).collect(),
+        Err(_) => vec![payload],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_without_blank_lines_are_surfaced() {
+        let mut parser = SseEventParser::new();
+        let events = parser
+            .push_and_drain_events(
+                b"data: {\"n\": 1}\ndata: {\"n\": 2}\ndata: {\"n\": 3}\ndata: [DONE]\n",
+            )
+            .unwrap();
+        assert_eq!(events, ["{\"n\": 1}", "{\"n\": 2}", "{\"n\": 3}", "[DONE]"]);
+        assert!(parser.finish().is_empty());
+
+        // The last event is held only until the stream ends
+        let mut parser = SseEventParser::new();
+        assert_eq!(
+            parser
+                .push_and_drain_events(b"data: {\"n\": 1}\ndata: {\"n\": 2}\n")
+                .unwrap(),
+            ["{\"n\": 1}"]
+        );
+        assert_eq!(parser.finish(), ["{\"n\": 2}"]);
+
+        // Even a final line without its newline
+        let mut parser = SseEventParser::new();
+        assert!(parser
+            .push_and_drain_events(b"data: {\"n\": 1}")
+            .unwrap()
+            .is_empty());
+        assert_eq!(parser.finish(), ["{\"n\": 1}"]);
+    }
+
+    #[test]
+    fn test_multiline_data_still_joined() {
+        let mut parser = SseEventParser::new();
+        let events = parser
+            .push_and_drain_events(b"data: {\"text\":\ndata: \"hi\"}\n\ndata: [DONE]\n\n")
+            .unwrap();
+        assert_eq!(events, ["{\"text\":\n\"hi\"}", "[DONE]"]);
+    }
+
+    #[test]
+    fn test_unterminated_event_is_bounded() {
+        let mut parser = SseEventParser::new().with_max_event_bytes(64);
+        // Many small complete events in one chunk are fine
+        let burst = "data: {\"n\": 1}\n\n".repeat(20);
+        assert_eq!(
+            parser
+                .push_and_drain_events(burst.as_bytes())
+                .unwrap()
+                .len(),
+            20
+        );
+
+        assert!(parser.push_and_drain_events(b"data: {\"text\": \"").is_ok());
+        let mut result = Ok(Vec::new());
+        for _ in 0..10 {
+            result = parser.push_and_drain_events(&[b'a'; 16]);
+            if result.is_err() {
+                break;
+            }
+        }
+        let err = result.unwrap_err();
+        assert_eq!(err.limit, 64);
+        assert!(err.bytes > 64 && err.bytes <= 64 + 16);
+        // Nothing of the oversized event is retained
+        assert!(parser.finish().is_empty());
+    }
 }
@@ -0,0 +1,74 @@
+/// Envelope checks for `apply_patch` tool-call arguments
+/// Expected shape:
+/// *** Begin Patch
+/// *** Update File: path
+/// ...
+/// *** End Patch
+use serde_json::Value;
+
+const BEGIN_MARKER: &str = "*** Begin Patch";
+const END_MARKER: &str = "*** End Patch";
+const FILE_OPERATIONS: [&str; 3] = ["*** Add File:", "*** Update File:", "*** Delete File:"];
+
+/// Describe what is wrong with an `apply_patch` call's patch, if anything.
+/// Accepts JSON arguments carrying `patch` or `input`, or the raw patch text.
+pub fn apply_patch_problem(arguments: &str) -> Option<&'static str> {
+    let parsed = serde_json::from_str::<Value>(arguments).ok();
+    let patch = match &parsed {
+        Some(Value::Object(map)) => match map
+            .get("patch")
+            .or_else(|| map.get("input"))
+            .and_then(Value::as_str)
+        {
+            Some(patch) => patch,
+            None => return Some("missing patch argument"),
+        },
+        Some(Value::String(patch)) => patch.as_str(),
+        _ => arguments,
+    };
+
+    let patch = patch.trim();
+    if !patch.starts_with(BEGIN_MARKER) {
+        return Some("missing *** Begin Patch");
+    }
+    if !patch.ends_with(END_MARKER) {
+        return Some("missing *** End Patch");
+    }
+    if !patch
+        .lines()
+        .any(|line| FILE_OPERATIONS.iter().any(|op| line.starts_with(op)))
+    {
+        return Some("no file operations");
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_apply_patch_envelope_checks() {
+        let valid = "*** Begin Patch\n*** Update File: a.rs\n@@\n-old\n+new\n*** End Patch\n";
+        assert_eq!(
+            apply_patch_problem(&json!({ "patch": valid }).to_string()),
+            None
+        );
+        assert_eq!(apply_patch_problem(valid), None);
+
+        let truncated = "*** Begin Patch\n*** Update File: a.rs\n+new\n";
+        assert_eq!(
+            apply_patch_problem(&json!({ "input": truncated }).to_string()),
+            Some("missing *** End Patch")
+        );
+        assert_eq!(
+            apply_patch_problem(&json!({ "patch": "+new\n*** End Patch" }).to_string()),
+            Some("missing *** Begin Patch")
+        );
+        assert_eq!(
+            apply_patch_problem(&json!({ "path": "a.rs" }).to_string()),
+            Some("missing patch argument")
+        );
+    }
+}
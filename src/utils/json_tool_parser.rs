@@ -0,0 +1,203 @@
+/// Parse OpenAI-style tool calls that a model wrote into its text as JSON
+/// Handles formats like:
+/// {"tool_calls": [{"type": "function", "function": {"name": ..., "arguments": ...}}]}
+/// ```json
+/// {"tool_calls": [...]}
+/// ```
+use super::xml_tool_parser::ParsedToolCall;
+use serde_json::Value;
+
+const FENCE_OPEN: &str = "```json";
+const FENCE_CLOSE: &str = "```";
+const TOOL_CALLS_KEY: &str = "\"tool_calls\"";
+
+/// Outcome of scanning buffered text for a JSON tool-call structure
+#[derive(Debug)]
+pub enum JsonToolScan {
+    /// The object (or its code fence) is still open; keep buffering
+    Incomplete,
+    /// Complete structure holding tool calls, with the text it leaves behind
+    Calls(String, Vec<ParsedToolCall>),
+    /// Complete, but not a tool-call structure; release it as text
+    NotToolCalls,
+}
+
+/// Whether `text` starts with `marker`, or is cut short while still matching it
+fn opens_with(text: &str, marker: &str) -> bool {
+    text.starts_with(marker) || marker.starts_with(text)
+}
+
+/// Byte offset where a JSON tool-call structure may begin: a ```` ```json ````
+/// fence or an object whose first key is `"tool_calls"`. Text ending partway
+/// through either opening also counts, so it can be held back until it resolves.
+pub fn json_tool_call_start(text: &str) -> Option<usize> {
+    text.char_indices().map(|(i, _)| i).find(|&i| {
+        let rest = &text[i..];
+        opens_with(rest, FENCE_OPEN)
+            || rest
+                .strip_prefix('{')
+                .is_some_and(|after| opens_with(after.trim_start(), TOOL_CALLS_KEY))
+    })
+}
+
+/// Scan `text` for the structure found by `json_tool_call_start` at `start`
+pub fn scan_json_tool_calls(text: &str, start: usize) -> JsonToolScan {
+    let rest = &text[start..];
+    let (body, fenced) = match rest.strip_prefix(FENCE_OPEN) {
+        Some(after_fence) => (after_fence, true),
+        None if FENCE_OPEN.starts_with(rest) => return JsonToolScan::Incomplete,
+        None => (rest, false),
+    };
+    let object_start = body.len() - body.trim_start().len();
+    let body = &body[object_start..];
+    if body.is_empty() {
+        return JsonToolScan::Incomplete;
+    }
+    if !body.starts_with('{') {
+        // A fenced block of something else; wait for its fence to close
+        return match (fenced, body.find(FENCE_CLOSE)) {
+            (true, None) => JsonToolScan::Incomplete,
+            _ => JsonToolScan::NotToolCalls,
+        };
+    }
+    let first_key = body[1..].trim_start();
+    if !first_key.starts_with(TOOL_CALLS_KEY) {
+        if !fenced && TOOL_CALLS_KEY.starts_with(first_key) {
+            return JsonToolScan::Incomplete;
+        }
+        if !fenced {
+            return JsonToolScan::NotToolCalls;
+        }
+    }
+    let Some(object_len) = balanced_object_len(body) else {
+        return JsonToolScan::Incomplete;
+    };
+
+    let mut consumed = text.len() - body.len() + object_len;
+    if fenced {
+        let after = &text[consumed..];
+        let trimmed = after.trim_start();
+        if trimmed.is_empty() {
+            return JsonToolScan::Incomplete;
+        }
+        let Some(after_close) = trimmed.strip_prefix(FENCE_CLOSE) else {
+            return JsonToolScan::NotToolCalls;
+        };
+        consumed = text.len() - after_close.len();
+    }
+
+    let calls = serde_json::from_str::<Value>(&body[..object_len])
+        .ok()
+        .and_then(|value| parse_tool_calls(&value));
+    match calls {
+        Some(calls) if !calls.is_empty() => {
+            let cleaned = format!("{}{}", &text[..start], &text[consumed..]);
+            JsonToolScan::Calls(cleaned.trim().to_string(), calls)
+        }
+        _ => JsonToolScan::NotToolCalls,
+    }
+}
+
+/// Length of the JSON object opening `text`, once its closing brace arrived
+fn balanced_object_len(text: &str) -> Option<usize> {
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (i, c) in text.char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Calls from `{"tool_calls": [...]}`, each either Chat Completions shaped
+/// (`{"function": {"name", "arguments"}}`) or flat (`{"name", "arguments"}`)
+fn parse_tool_calls(value: &Value) -> Option<Vec<ParsedToolCall>> {
+    value
+        .get("tool_calls")?
+        .as_array()?
+        .iter()
+        .map(|call| {
+            let function = call.get("function").unwrap_or(call);
+            let name = function.get("name")?.as_str()?.to_string();
+            let arguments = match function.get("arguments") {
+                Some(Value::String(arguments)) => arguments.clone(),
+                Some(arguments) => arguments.to_string(),
+                None => "{}".to_string(),
+            };
+            Some(ParsedToolCall { name, arguments })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(text: &str) -> JsonToolScan {
+        let start = json_tool_call_start(text).expect("structure start");
+        scan_json_tool_calls(text, start)
+    }
+
+    #[test]
+    fn test_bare_tool_calls_object() {
+        let text = r#"Reading it now. { "tool_calls": [{"type": "function", "function": {"name": "read_file", "arguments": "{\"path\": \"a.txt\"}"}}]}"#;
+        let JsonToolScan::Calls(cleaned, calls) = scan(text) else {
+            panic!("expected tool calls");
+        };
+        assert_eq!(cleaned, "Reading it now.");
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].name, "read_file");
+        assert_eq!(calls[0].arguments, r#"{"path": "a.txt"}"#);
+
+        // Still open: braces inside strings do not close the object
+        let open = r#"{"tool_calls": [{"name": "echo", "arguments": {"text": "}"}"#;
+        assert!(matches!(scan(open), JsonToolScan::Incomplete));
+
+        // A cut-off opening is held until the next delta decides it
+        assert!(matches!(scan(r#"Plan: {"tool"#), JsonToolScan::Incomplete));
+        assert!(matches!(scan("Plan: {"), JsonToolScan::Incomplete));
+        assert!(matches!(
+            scan_json_tool_calls("Plan: {\"path\": 1}", 6),
+            JsonToolScan::NotToolCalls
+        ));
+        assert_eq!(json_tool_call_start("if (x) { y }"), None);
+    }
+
+    #[test]
+    fn test_fenced_tool_calls_block() {
+        let text = "Sure.\n```json\n{\"tool_calls\": [{\"name\": \"ls\", \"arguments\": {\"dir\": \".\"}}]}\n```\nDone.";
+        let JsonToolScan::Calls(cleaned, calls) = scan(text) else {
+            panic!("expected tool calls");
+        };
+        assert_eq!(cleaned, "Sure.\n\nDone.");
+        assert_eq!(calls[0].name, "ls");
+        assert_eq!(calls[0].arguments, r#"{"dir":"."}"#);
+
+        // The object is complete but the fence is not closed yet
+        let unclosed = "```json\n{\"tool_calls\": []}\n";
+        assert!(matches!(scan(unclosed), JsonToolScan::Incomplete));
+
+        // An ordinary JSON code block is released as text
+        let data = "```json\n{\"port\": 8080}\n```";
+        assert!(matches!(scan(data), JsonToolScan::NotToolCalls));
+    }
+}
@@ -1,11 +1,40 @@
 use chrono::Utc;
 use std::env;
 use std::fs;
+use std::future::Future;
+use std::io::Write;
 use std::path::PathBuf;
 use std::sync::OnceLock;
 
 static LOG_VOLUME_ENABLED: OnceLock<bool> = OnceLock::new();
 
+tokio::task_local! {
+    /// Id of the request whose task is currently running, used to tag log lines
+    static REQUEST_ID: String;
+}
+
+/// Run a future with `request_id` attached to every log line it emits
+pub async fn with_request_id<F: Future>(request_id: String, fut: F) -> F::Output {
+    REQUEST_ID.scope(request_id, fut).await
+}
+
+/// `tokio::spawn` that carries the current request id, if any, into the task
+pub fn spawn_with_request_id<F>(fut: F) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    match current_request_id() {
+        Some(request_id) => tokio::spawn(REQUEST_ID.scope(request_id, fut)),
+        None => tokio::spawn(fut),
+    }
+}
+
+/// Request id of the current task, if any
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
 fn log_volume_enabled() -> bool {
     *LOG_VOLUME_ENABLED.get_or_init(|| {
         env::var("ENABLE_LOG_VOLUME")
@@ -149,3 +178,162 @@ pub fn init_log_dir() -> std::io::Result<()> {
     log::info!("📁 Logging directory initialized: {}", dir.display());
     Ok(())
 }
+
+/// Remove the leading emoji decoration (with any variation selector and the
+/// space after it) from a log message, and spell out arrows as ASCII. The rest
+/// of the message, such as non-English content or paths, is kept as-is.
+pub fn strip_log_decoration(message: &str) -> String {
+    let body = message
+        .trim_start_matches(|c: char| c.is_whitespace() || (!c.is_ascii() && !c.is_alphanumeric()));
+    let mut plain = String::with_capacity(body.len());
+    for ch in body.chars() {
+        match ch {
+            '→' => plain.push_str("->"),
+            '←' => plain.push_str("<-"),
+            c => plain.push(c),
+        }
+    }
+    plain
+}
+
+/// Write a single log line, tagged with the current request id when one is in
+/// scope. With `strip_emoji` the leading emoji decoration is removed and arrows
+/// are spelled out (see [`strip_log_decoration`]).
+pub fn write_log_line(
+    out: &mut impl Write,
+    timestamp: impl std::fmt::Display,
+    level: log::Level,
+    target: &str,
+    message: &str,
+    strip_emoji: bool,
+) -> std::io::Result<()> {
+    let message = if strip_emoji {
+        strip_log_decoration(message)
+    } else {
+        message.to_string()
+    };
+
+    match current_request_id() {
+        Some(request_id) => writeln!(
+            out,
+            "[{} {:<5} {}] [{}] {}",
+            timestamp, level, target, request_id, message
+        ),
+        None => writeln!(out, "[{} {:<5} {}] {}", timestamp, level, target, message),
+    }
+}
+
+/// Logger that keeps every record so tests can assert on server-side detail
+#[cfg(test)]
+struct CaptureLogger {
+    lines: std::sync::Mutex<Vec<String>>,
+}
+
+#[cfg(test)]
+impl log::Log for CaptureLogger {
+    fn enabled(&self, _: &log::Metadata) -> bool {
+        true
+    }
+
+    fn log(&self, record: &log::Record) {
+        let line = match current_request_id() {
+            Some(request_id) => format!("[{}] {}", request_id, record.args()),
+            None => record.args().to_string(),
+        };
+        self.lines.lock().unwrap().push(line);
+    }
+
+    fn flush(&self) {}
+}
+
+/// Process-wide log capture for tests; installed on first use
+#[cfg(test)]
+pub fn captured_logs() -> &'static std::sync::Mutex<Vec<String>> {
+    static LOGGER: OnceLock<&'static CaptureLogger> = OnceLock::new();
+    let logger = LOGGER.get_or_init(|| {
+        let logger = Box::leak(Box::new(CaptureLogger {
+            lines: std::sync::Mutex::new(Vec::new()),
+        }));
+        let _ = log::set_logger(logger);
+        log::set_max_level(log::LevelFilter::Debug);
+        logger
+    });
+    &logger.lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain_log_lines_drop_leading_emoji() {
+        let mut out = Vec::new();
+        for message in [
+            "🔑 Client API Key: Bearer sk-abc...wxyz",
+            "✅ Backend responded successfully (200 OK)",
+            "❌ Backend connection failed: refused",
+            "🔄 Model: foo → Foo (case-corrected)",
+        ] {
+            write_log_line(
+                &mut out,
+                "2025-01-01T00:00:00Z",
+                log::Level::Info,
+                "proxy",
+                message,
+                true,
+            )
+            .unwrap();
+        }
+
+        let text = String::from_utf8(out).unwrap();
+        let lines: Vec<_> = text.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].ends_with("] Client API Key: Bearer sk-abc...wxyz"));
+        assert!(lines[1].ends_with("] Backend responded successfully (200 OK)"));
+        assert!(lines[2].ends_with("] Backend connection failed: refused"));
+        assert!(lines[3].ends_with("] Model: foo -> Foo (case-corrected)"));
+    }
+
+    #[test]
+    fn test_stripping_keeps_non_ascii_message_body() {
+        assert_eq!(
+            strip_log_decoration("⚠️  Failed to load /data/café/プロンプト.json: données"),
+            "Failed to load /data/café/プロンプト.json: données"
+        );
+        assert_eq!(
+            strip_log_decoration("🔄 Model: qwen → Qwen (modèle ✅ trouvé)"),
+            "Model: qwen -> Qwen (modèle ✅ trouvé)"
+        );
+        // A message that starts with text keeps its first word
+        assert_eq!(strip_log_decoration("Ünïcode first"), "Ünïcode first");
+    }
+
+    fn helper_log_line(message: &str) -> String {
+        let mut out = Vec::new();
+        write_log_line(&mut out, "ts", log::Level::Debug, "proxy", message, false).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_log_lines_tagged_with_request_id_in_scope() {
+        assert_eq!(helper_log_line("outside"), "[ts DEBUG proxy] outside\n");
+
+        let line = with_request_id("18a2f".to_string(), async {
+            // Nested helpers and awaited calls share the request scope
+            tokio::task::yield_now().await;
+            helper_log_line("🏁 Received [DONE] marker from backend")
+        })
+        .await;
+        assert_eq!(
+            line,
+            "[ts DEBUG proxy] [18a2f] 🏁 Received [DONE] marker from backend\n"
+        );
+
+        let spawned = tokio::spawn(with_request_id("beef".to_string(), async {
+            helper_log_line("streaming task")
+        }))
+        .await
+        .unwrap();
+        assert!(spawned.contains("[beef] streaming task"));
+    }
+}
@@ -1,5 +1,9 @@
+pub mod apply_patch;
+pub mod json_tool_parser;
 pub mod logging;
 pub mod xml_tool_parser;
 
+pub use apply_patch::*;
+pub use json_tool_parser::*;
 pub use logging::*;
 pub use xml_tool_parser::*;